@@ -1,11 +1,13 @@
 // cmds.rs
 
+use std::io::{BufRead, IsTerminal};
 use std::process::exit;
-use crate::{GitCommand, CommandDefinition, CommandResult}; // main.rs からインポート
+use crate::{GitCommand, GitCommandTrait, DryRunGitCommand, CommandDefinition, CommandResult}; // main.rs からインポート
+use crate::utils;
 use colored::*; // colored の Colorize トレイトをインポート
-use promptuity::prompts::Input;
+use promptuity::prompts::{Input, MultiSelect, MultiSelectOption, Select, SelectOption};
 use promptuity::themes::MinimalTheme;
-use promptuity::{Promptuity, Term};
+use promptuity::{Prompt, Promptuity, Term};
 
 // エラー処理を一箇所にまとめるヘルパー
 fn handle_command_result<T, F>(result: CommandResult<T>, success_action: F)
@@ -24,12 +26,47 @@ fn handle_command_result_void(result: CommandResult<()>) {
     handle_command_result(result, |_| {});
 }
 
+// コマンド固有の引数 (args[2..]) にフラグが含まれるか調べるヘルパー
+fn has_flag(args: &[String], names: &[&str]) -> bool {
+    args.iter().skip(2).any(|a| names.contains(&a.as_str()))
+}
+
+// コマンド固有の引数 (args[2..]) から `--name value` / `--name=value` 形式の値を取り出すヘルパー
+fn flag_value(args: &[String], names: &[&str]) -> Option<String> {
+    let mut iter = args.iter().skip(2);
+    while let Some(arg) = iter.next() {
+        if names.contains(&arg.as_str()) {
+            return iter.next().cloned();
+        }
+        for name in names {
+            if let Some(value) = arg.strip_prefix(&format!("{}=", name)) {
+                return Some(value.to_string());
+            }
+        }
+    }
+    None
+}
+
+// フラグとその値 (value_flags に挙げたもの) を除いた位置引数を返すヘルパー
+fn positional_args(args: &[String], value_flags: &[&str]) -> Vec<String> {
+    let mut positionals = Vec::new();
+    let mut iter = args.iter().skip(2);
+    while let Some(arg) = iter.next() {
+        if value_flags.contains(&arg.as_str()) {
+            iter.next();
+        } else if !arg.starts_with('-') {
+            positionals.push(arg.clone());
+        }
+    }
+    positionals
+}
+
 // CommandHandler の型エイリアスは main.rs で pub として定義されているので、ここでは不要
 // pub type CommandHandler = fn(&[String]);
 
 
 pub fn print_usage_and_exit(program_name: &str, commands: &[CommandDefinition]) {
-    eprintln!("{} {} {{command}} [args]", "Usage:".bold(), program_name.green());
+    eprintln!("{} {} [--path <dir>] [--remote <name>] [--yes|-y] [--verbose|-v] [--log-level <level>] {{command}} [args]", "Usage:".bold(), program_name.green());
     eprintln!("\n利用可能なコマンド:");
     for cmd_def in commands {
         eprintln!("  {:<10} {}", cmd_def.name.cyan(), cmd_def.description);
@@ -37,19 +74,80 @@ pub fn print_usage_and_exit(program_name: &str, commands: &[CommandDefinition])
     exit(1);
 }
 
+// help に表示するグローバルオプション (解析は utils::extract_global_options)
+const GLOBAL_OPTIONS: &[(&str, &str)] = &[
+    ("--path <dir>", "status/save/changelog などの対象を指定ディレクトリ配下に限定します。"),
+    ("--remote <name>", "origin の代わりに使うリモート名 (プッシュ/取得/リモートブランチの表示などが対象)。"),
+    ("--yes, -y", "確認をすべて y とみなします (スクリプト/CI 向け)。選択や入力が必要な場面ではエラーで終了します。"),
+    ("--verbose, -v", "実行する git コマンドを '$ git ...' の形で表示します。"),
+    ("--log-level <level>", "補足メッセージの表示レベル (error/warn/info/debug、既定は info)。debug では実行した git コマンドと所要時間も表示します。"),
+];
+
 pub fn show_help(args: &[String]) {
     let program_name = args.first().map_or("mygit", |s|s.as_str());
     // CARGO_PKG_VERSION を使うには std::env を use する必要がある
     println!("{} {} - Git操作を簡略化するCLIツール", program_name.bold().green(), std::env!("CARGO_PKG_VERSION"));
     println!("\n{} {}", "Usage:".bold(), program_name.green());
-    println!("  {} {{save|setup|branch|switch|merge|copy|delete|create|help}}", program_name.green());
-    println!("\n{} {}{}", "利用可能なコマンド:".bold(), "(詳細は ".dimmed(), "各コマンドのヘルプを参照ください (未実装)".dimmed());
+    let names: Vec<&str> = crate::COMMAND_DEFINITIONS.iter().map(|c| c.name).collect();
+    println!("  {} {{{}}}", program_name.green(), names.join("|"));
+    println!("\n{} {}{}", "利用可能なコマンド:".bold(), "(詳細は ".dimmed(), "examples <command> を参照ください)".dimmed());
     for cmd_def in crate::COMMAND_DEFINITIONS {
         println!("  {:<10} {}", cmd_def.name.cyan(), cmd_def.description);
     }
+    println!("\n{}", "グローバルオプション:".bold());
+    for (option, description) in GLOBAL_OPTIONS {
+        println!("  {:<20} {}", option.cyan(), description);
+    }
+    println!("\n{}", "エイリアスの一覧は aliases コマンドで確認できます。".dimmed());
     exit(0);
 }
 
+pub fn print_examples(cmd_def: &CommandDefinition) {
+    println!("{} {}", cmd_def.name.cyan().bold(), cmd_def.description.dimmed());
+    for (example_args, explanation) in cmd_def.examples {
+        let invocation = if example_args.is_empty() { format!("mygit {}", cmd_def.name) } else { format!("mygit {} {}", cmd_def.name, example_args) };
+        println!("  {}", invocation.green());
+        println!("      {}", explanation);
+    }
+}
+
+pub fn show_examples(args: &[String]) {
+    match args.get(2) {
+        Some(name) => match crate::COMMAND_DEFINITIONS.iter().find(|c| c.name == name.as_str()) {
+            Some(cmd_def) => print_examples(cmd_def),
+            None => { eprintln!("エラー: 不明なコマンド '{}'", name.red()); exit(1); }
+        },
+        None => {
+            for (i, cmd_def) in crate::COMMAND_DEFINITIONS.iter().enumerate() {
+                if i > 0 { println!(); }
+                print_examples(cmd_def);
+            }
+        }
+    }
+}
+pub fn show_aliases(_args: &[String]) {
+    println!("{}", "組み込みエイリアス:".bold());
+    for cmd_def in crate::COMMAND_DEFINITIONS {
+        for alias in cmd_def.aliases {
+            println!("  {:<10} → {}", alias.green(), cmd_def.name.cyan());
+        }
+    }
+
+    let user_aliases = &utils::config().aliases;
+    println!("\n{}", "ユーザー定義エイリアス (設定ファイル [aliases]):".bold());
+    if user_aliases.is_empty() { println!("  {}", "(なし)".dimmed()); }
+    for (alias, expansion) in user_aliases {
+        let target = expansion.split_whitespace().next().unwrap_or("");
+        let note = if crate::find_command(alias).is_some() {
+            "(組み込みコマンドと重複しているため無効)".red().to_string()
+        } else if crate::find_command(target).is_none() {
+            format!("(不明なコマンド '{}')", target).red().to_string()
+        } else {
+            String::new()
+        };
+        println!("  {:<10} → {} {}", alias.green(), expansion.cyan(), note);
+    }
+}
 
 fn get_current_branch_name() -> String {
     let mut current_branch = String::new();
@@ -57,26 +155,289 @@ fn get_current_branch_name() -> String {
     current_branch
 }
 
-fn prompt_input(message: &str) -> String {
+// 標準入力が端末でない場合 (例: `mygit save < answers.txt`) は、プロンプトを表示せず1行ずつ回答を読む
+fn read_piped_answer(message: &str) -> String {
+    let mut line = String::new();
+    match std::io::stdin().lock().read_line(&mut line) {
+        Ok(0) => { eprintln!("エラー: 標準入力の回答が不足しています (質問: {})", message.trim()); exit(1); }
+        Ok(_) => {}
+        Err(e) => { eprintln!("エラー: 標準入力の読み込み ({})", e); exit(1); }
+    }
+    let answer = line.trim().to_string();
+    eprintln!("{}{}", message, answer.dimmed()); // ログで回答の対応が分かるようにエコー
+    answer
+}
+
+fn run_prompt<O>(prompt: &mut dyn Prompt<Output = O>) -> O {
     let mut term = Term::default();
     let mut theme = MinimalTheme::default();
     let mut p = Promptuity::new(&mut term, &mut theme);
-    let mut input_prompt = Input::new(message.to_string());
     if let Err(e) = p.begin() { eprintln!("エラー: プロンプト初期化 ({:?})", e); exit(1); }
-    let result = match p.prompt(&mut input_prompt) {
+    let result = match p.prompt(prompt) {
         Ok(res) => res,
         Err(e) => { eprintln!("エラー: 入力取得 ({:?})", e); exit(1); }
     };
-    if let Err(e) = p.finish() { eprintln!("警告: プロンプト終了処理 ({:?})", e); }
+    if let Err(e) = p.finish() { log::warn!("警告: プロンプト終了処理 ({:?})", e); }
     result
 }
 
+// --yes 指定時に回答が必要な質問に出会ったら、待たずに終了する
+fn exit_needs_answer(message: &str) -> ! {
+    eprintln!("{} {}", "エラー: --yes 指定時は回答が必要な質問に答えられません:".red(), message.trim());
+    exit(1);
+}
+
+fn prompt_input(message: &str) -> String {
+    if utils::assume_yes() { exit_needs_answer(message); }
+    if !std::io::stdin().is_terminal() {
+        return read_piped_answer(message);
+    }
+    run_prompt(&mut Input::new(message.to_string()))
+}
+
+// 空の回答は既定値として扱う
+fn prompt_input_with_default(message: &str, default: &str) -> String {
+    if utils::assume_yes() { return default.to_string(); }
+    let answer = if !std::io::stdin().is_terminal() {
+        read_piped_answer(message)
+    } else {
+        run_prompt(Input::new(message.to_string()).with_default(default))
+    };
+    if answer.is_empty() { default.to_string() } else { answer }
+}
+
+// パイプ入力時は選択肢の値 (またはラベル) をそのまま1行で回答する
+fn prompt_select(message: &str, options: Vec<SelectOption<String>>) -> String {
+    if utils::assume_yes() { exit_needs_answer(message); }
+    if !std::io::stdin().is_terminal() {
+        let answer = read_piped_answer(&format!("{}: ", message));
+        match options.iter().find(|o| o.value == answer || o.label == answer) {
+            Some(option) => return option.value.clone(),
+            None => { eprintln!("エラー: '{}' は選択肢にありません。", answer.red()); exit(1); }
+        }
+    }
+    run_prompt(&mut Select::new(message, options))
+}
+
+// パイプ入力時は選択する値を空白区切りで1行に回答する (空行で何も選択しない)
+fn prompt_multi_select(message: &str, options: Vec<MultiSelectOption<String>>) -> Vec<String> {
+    if utils::assume_yes() { exit_needs_answer(message); }
+    if !std::io::stdin().is_terminal() {
+        let answer = read_piped_answer(&format!("{}: ", message));
+        return answer.split_whitespace().map(|value| {
+            match options.iter().find(|o| o.value == value) {
+                Some(option) => option.value.clone(),
+                None => { eprintln!("エラー: '{}' は選択肢にありません。", value.red()); exit(1); }
+            }
+        }).collect();
+    }
+    run_prompt(MultiSelect::new(message, options).with_required(false))
+}
+
+// 空行や y/yes 以外の回答は No として扱う (パイプ入力時も同じ)。--yes 指定時は尋ねずに Yes
 fn confirm(message: &str) -> bool {
-    prompt_input(&format!("{} (y/N): ", message)).eq_ignore_ascii_case("y")
+    confirm_with_default(message, false)
+}
+
+// 空のまま Enter した場合は default を採用する。表示は default に応じて (Y/n) / (y/N)
+fn confirm_with_default(message: &str, default: bool) -> bool {
+    let hint = if default { "(Y/n)" } else { "(y/N)" };
+    if utils::assume_yes() {
+        eprintln!("{} {}: {}", message, hint, "y (--yes)".dimmed());
+        return true;
+    }
+    let answer = prompt_input(&format!("{} {}: ", message, hint));
+    if answer.is_empty() { return default; }
+    answer.eq_ignore_ascii_case("y") || answer.eq_ignore_ascii_case("yes")
+}
+
+fn get_local_branch_names() -> Vec<String> {
+    let mut branches_str = String::new();
+    handle_command_result(GitCommand::branch_list_local_str(), |s| branches_str = s);
+    branches_str.lines()
+        // 先頭の "* " (現在) や "+ " (他のワークツリー) の印を除く (出力全体が trim されるため、1行目は字下げが無い)
+        .map(|l| { let l = l.trim_start(); l.strip_prefix("* ").or_else(|| l.strip_prefix("+ ")).unwrap_or(l).trim().to_string() })
+        .filter(|name| !name.is_empty() && !name.starts_with('('))  // "(HEAD detached at ...)" を除外
+        .collect()
+}
+
+// 最近のコミットを "<短縮sha> <件名>" のラベル、完全な sha を値とした選択肢にする
+fn get_commit_select_options(branch: &str, max_count: usize) -> Vec<SelectOption<String>> {
+    let mut log_str = String::new();
+    handle_command_result(GitCommand::log_oneline(branch, max_count), |s| log_str = s);
+    log_str.lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, '\t');
+            let (sha, short_sha, subject) = (parts.next()?, parts.next()?, parts.next().unwrap_or(""));
+            Some(SelectOption::new(format!("{} {}", short_sha, subject), sha.to_string()))
+        })
+        .collect()
+}
+
+// --path に関わらずリポジトリ全体の変更の件数 (コミットや切り替えはリポジトリ全体が対象のため)
+fn get_working_tree_status() -> utils::WorkingTreeStatus {
+    let mut status = utils::WorkingTreeStatus::default();
+    handle_command_result(GitCommand::status_porcelain_v2_branch(), |s| status = utils::parse_working_tree_status(&s));
+    status
+}
+
+fn get_status_entries() -> Vec<utils::StatusEntry> {
+    let mut entries = Vec::new();
+    handle_command_result(GitCommand::status_porcelain_v2(), |s| entries = utils::parse_status(&s));
+    entries
+}
+
+// --autostash フラグまたは設定ファイルの autostash = true で有効
+fn autostash_enabled(args: &[String]) -> bool {
+    has_flag(args, &["--autostash"]) || utils::config().autostash
+}
+
+// origin/HEAD が指すブランチ、無ければ main/master のうち存在するものを既定ブランチとする
+fn get_default_branch() -> Option<String> {
+    if let Ok(remote_head) = GitCommand::symbolic_ref_remote_head(utils::remote())
+        && let Some(name) = remote_head.strip_prefix(&format!("{}/", utils::remote())) {
+        if GitCommand::rev_parse_verify(&format!("refs/remotes/{}", remote_head)).unwrap_or(false) {
+            return Some(name.to_string());
+        }
+        // リモートで既定ブランチが改名・削除された場合など
+        log::warn!("警告: {}/HEAD が存在しない '{}' を指しています。'mygit repo remote fix-head' で修正できます。", utils::remote(), remote_head);
+    }
+    ["main", "master"].iter()
+        .find(|name| GitCommand::rev_parse_verify(&format!("refs/heads/{}", name)).unwrap_or(false))
+        .map(|name| name.to_string())
+}
+
+// 既定ブランチを特定できない場合のエラー。origin があれば origin/HEAD の修正を案内する
+fn exit_no_default_branch(hint: &str) -> ! {
+    eprintln!("{}", format!("エラー: 既定ブランチ (main/master) を特定できません。{}", hint).red());
+    if GitCommand::remote_get_url(utils::remote()).is_ok() {
+        eprintln!("{}/HEAD が未設定または壊れている可能性があります。'mygit repo remote fix-head' で修正できます。", utils::remote());
+    }
+    exit(1);
+}
+
+// 既定ブランチの比較対象。origin 側があればそちらを優先する
+fn get_default_base_ref() -> Option<String> {
+    get_default_branch().map(|name| {
+        let remote_name = format!("{}/{}", utils::remote(), name);
+        if GitCommand::rev_parse_verify(&remote_name).unwrap_or(false) { remote_name } else { name }
+    })
+}
+
+// 途中で止まっている rebase/merge/cherry-pick があれば、その操作名を返す
+fn operation_in_progress() -> Option<&'static str> {
+    let git_dir = std::path::PathBuf::from(GitCommand::git_dir().ok()?);
+    [("rebase-merge", "rebase"), ("rebase-apply", "rebase"), ("MERGE_HEAD", "merge"), ("CHERRY_PICK_HEAD", "cherry-pick")]
+        .iter()
+        .find(|(file, _)| git_dir.join(file).exists())
+        .map(|(_, operation)| *operation)
+}
+
+fn has_uncommitted_changes() -> bool {
+    let mut dirty = false;
+    handle_command_result(GitCommand::status_porcelain_v1(), |s| dirty = !s.is_empty());
+    dirty
+}
+
+// 未追跡ファイルを除いた変更があるか (チェックアウト等の妨げになるのはこちら)
+fn has_tracked_changes() -> bool {
+    has_tracked_changes_with(&GitCommand)
+}
+
+fn has_tracked_changes_with(git: &dyn GitCommandTrait) -> bool {
+    let mut status = String::new();
+    handle_command_result(git.status_porcelain_v1(), |s| status = s);
+    status.lines().any(|line| !line.starts_with("??"))
+}
+
+// 切り替えやマージの前に未コミットの変更の扱いを選ばせる。続行してよければ true
+// 回答順 (パイプ入力時): 変更の扱い (stash/commit/discard/cancel) (→ commit: コミットメッセージ / discard: 破棄確認)
+fn handle_uncommitted_changes_before_action(action: &str) -> bool {
+    handle_uncommitted_changes_before_action_with(&GitCommand, action)
+}
+
+fn handle_uncommitted_changes_before_action_with(git: &dyn GitCommandTrait, action: &str) -> bool {
+    if !has_tracked_changes_with(git) { return true; }
+    // 件数も変更の有無の判定と同じく --path の範囲で数える
+    let mut porcelain = String::new();
+    handle_command_result(git.status_porcelain_v2(), |s| porcelain = s);
+    let status = utils::parse_working_tree_status(&porcelain);
+    println!("{}", format!("未コミットの変更があります ({})。{}の前に扱いを選んでください。", status.summary(), action).yellow());
+    let options = vec![
+        SelectOption::new("退避 (stash) してから続ける", "stash".to_string()),
+        SelectOption::new("コミットしてから続ける", "commit".to_string()),
+        SelectOption::new("変更を破棄して続ける", "discard".to_string()),
+        SelectOption::new("中止する", "cancel".to_string()),
+    ];
+    match prompt_select("未コミットの変更", options).as_str() {
+        "stash" => {
+            handle_command_result_void(git.stash_push(Some(&format!("mygit: {}の前に退避", action))));
+            println!("変更を退避しました。'mygit stash pop' で戻せます。");
+            true
+        }
+        "commit" => {
+            let message = prompt_input("コミットメッセージ: ");
+            if message.trim().is_empty() { eprintln!("{}", "エラー: メッセージ必須。".red()); exit(1); }
+            handle_command_result_void(git.add(utils::path_scope().unwrap_or(".")));
+            handle_command_result_void(git.commit(&message));
+            true
+        }
+        "discard" => {
+            match utils::path_scope() {
+                // --path 指定時は、その配下の変更だけを破棄する (範囲外の変更には触れない)
+                Some(path) => {
+                    if !confirm(&format!("'{}' 配下の追跡中のファイルの未コミットの変更を破棄しますか？ (元に戻せません)", path)) { println!("キャンセルしました。"); return false; }
+                    handle_command_result_void(git.restore_head_in(path));
+                }
+                None => {
+                    if !confirm("追跡中のファイルの未コミットの変更をすべて破棄しますか？ (元に戻せません)") { println!("キャンセルしました。"); return false; }
+                    handle_command_result_void(git.reset_hard_head());
+                }
+            }
+            true
+        }
+        _ => { println!("キャンセルしました。"); false }
+    }
+}
+
+// rerere が有効なら、記録済みの解決で自動解決されたファイルを知らせる (確認は必要)。
+// 無効でコンフリクトが起きている場合は有効化を提案する
+fn report_rerere() {
+    if GitCommand::config_get("rerere.enabled").is_ok_and(|v| v == "true") {
+        let remaining: Vec<String> = GitCommand::rerere_remaining().unwrap_or_default().lines().map(|l| l.to_string()).collect();
+        // 索引上はコンフリクトのままでも、作業ツリーは rerere が解決済みの内容に書き換えている
+        let resolved: Vec<String> = get_status_entries().into_iter()
+            .filter(|e| e.is_conflicted && !remaining.contains(&e.path))
+            .map(|e| e.path)
+            .collect();
+        if !resolved.is_empty() {
+            log::info!("{}", "rerere が以前のコンフリクト解決を次のファイルに適用しました。内容を確認してから 'git add' してください:".cyan());
+            resolved.iter().for_each(|path| log::info!("  {}", path.cyan()));
+        }
+        if !remaining.is_empty() {
+            log::warn!("手動での解決が必要なファイル: {}", remaining.join(", "));
+        }
+        return;
+    }
+    if !get_status_entries().iter().any(|e| e.is_conflicted) { return; }
+    if confirm("rerere (コンフリクトの解決を記録し、同じコンフリクトに次回から自動適用) を有効にしますか？") {
+        handle_command_result_void(GitCommand::config_set("rerere.enabled", "true", false));
+        handle_command_result_void(GitCommand::rerere());
+        println!("{}", "rerere を有効にしました。今回のコンフリクトの解決から記録されます。".green());
+    }
+}
+
+// リベースが途中で止まったことを知らせて終了する
+fn exit_rebase_stopped(message: &str) -> ! {
+    log::warn!("警告: {}", message);
+    report_rerere();
+    log::warn!("解決後に 'git rebase --continue'、中止する場合は 'git rebase --abort' を実行してください。");
+    exit(1);
 }
 
 fn handle_conflict_and_offer_new_branch(operation_name: &str, _current_branch_for_checkout_b: &str) {
-    eprintln!("警告: {} に失敗しました。コンフリクトの可能性があります。", operation_name.yellow());
+    log::warn!("警告: {} に失敗しました。コンフリクトの可能性があります。", operation_name);
+    report_rerere();
     if confirm("この状態で新しいブランチを作成して変更を保持しますか？") {
         let new_branch_name = prompt_input("新しいブランチ名: ");
         if new_branch_name.is_empty() {
@@ -93,132 +454,573 @@ fn handle_conflict_and_offer_new_branch(operation_name: &str, _current_branch_fo
         }
     }
     println!("新しいブランチは作成しませんでした。手動で状況を確認してください。");
+    println!("操作を中止して開始前の状態に戻す場合は 'mygit undo' を実行してください。");
+    exit(1);
+}
+
+
+// 設定ファイルの pre_save_command を実行し、失敗した場合はコミットせずに終了する
+fn run_pre_save_check() {
+    let Some(command_line) = utils::config().pre_save_command.as_deref() else {
+        eprintln!("{}", "エラー: --check には設定ファイルの pre_save_command が必要です。".red());
+        exit(1);
+    };
+    log::info!("コミット前チェックを実行します: {}", command_line);
+    let mut passed = false;
+    handle_command_result(utils::run_shell_command(command_line), |success| passed = success);
+    if !passed {
+        eprintln!("{}", "エラー: コミット前チェックが失敗したため、コミットを中止しました。".red());
+        exit(1);
+    }
+    println!("{}", "コミット前チェックに成功しました。".green());
+}
+
+// 設定の commit_subject_* に従って件名を確認する。commit_subject_strict の場合は違反があればコミットしない
+fn check_commit_subject(subject: &str) {
+    let config = utils::config();
+    let problems = utils::lint_commit_subject(subject, config);
+    if problems.is_empty() { return; }
+    for problem in &problems {
+        if problem.is_error { log::error!("エラー: {}", problem.message); } else { log::warn!("警告: {}", problem.message); }
+    }
+    if problems.iter().any(|p| p.is_error) {
+        eprintln!("{}", "コミットを中止しました。変更はステージされたままです。".red());
+        exit(1);
+    }
+}
+
+// --merge/--rebase/--ff-only フラグ、無ければ設定ファイルの update_strategy
+fn selected_update_strategy(args: &[String]) -> Option<utils::UpdateStrategy> {
+    utils::UpdateStrategy::ALL.into_iter()
+        .find(|s| has_flag(args, &[&format!("--{}", s.as_str())]))
+        .or(utils::config().update_strategy)
+}
+
+// update_strategy が ff-only の場合に、fast-forward できない取り込みを拒否して終了する
+fn refuse_non_fast_forward(upstream: &str) -> ! {
+    eprintln!("{}", format!("エラー: '{}' を fast-forward で取り込めません (ローカルにも独自のコミットがあります)。", upstream).red());
+    eprintln!("取り込み方法が ff-only に設定されているため中止しました。");
+    eprintln!("  - 今回だけマージ/リベースする場合は --merge または --rebase を指定してください。");
+    eprintln!("  - 設定を変える場合は 'mygit strategy merge' などを実行してください。");
     exit(1);
 }
 
+// 既定ブランチの最新を取得し、現在のブランチがその先端から分岐しているか確認する。古い場合は取り込み方法に従って更新する
+// (取り込み方法が未設定の場合は選ばせる)
+fn ensure_updated_base(current_branch: &str, strategy: Option<utils::UpdateStrategy>) {
+    let Some(default_branch) = get_default_branch() else { return };
+    if default_branch == current_branch { return; }
+    let base_ref = match GitCommand::fetch_branch(utils::remote(), &default_branch) {
+        Ok(()) => format!("{}/{}", utils::remote(), default_branch),
+        Err(_) => default_branch.clone(), // リモートに無い場合はローカルの既定ブランチと比べる
+    };
+    let mut merge_base = String::new();
+    handle_command_result(GitCommand::merge_base("HEAD", &base_ref), |b| merge_base = b);
+    let mut tip = String::new();
+    handle_command_result(GitCommand::rev_parse_commit_id(&base_ref), |id| tip = id);
+    if merge_base == tip {
+        log::info!("'{}' の最新に追従しています。", base_ref);
+        return;
+    }
+
+    let behind = GitCommand::rev_list_count(&format!("HEAD..{}", base_ref)).unwrap_or(0);
+    println!("{}", format!("警告: '{}' は '{}' の {} 個前のコミットから分岐しています。", current_branch, base_ref, behind).yellow());
+    // プッシュ済みのブランチをリベースすると通常のプッシュが拒否されるため、未プッシュの場合のみ選べる
+    let can_rebase = !GitCommand::rev_parse_verify(&format!("refs/remotes/{}/{}", utils::remote(), current_branch)).unwrap_or(false);
+    let action = match strategy {
+        Some(utils::UpdateStrategy::FfOnly) => refuse_non_fast_forward(&base_ref),
+        Some(utils::UpdateStrategy::Merge) => "merge".to_string(),
+        Some(utils::UpdateStrategy::Rebase) if can_rebase => "rebase".to_string(),
+        _ => {
+            if strategy.is_some() { log::warn!("'{}' はプッシュ済みのため、リベースせずに更新方法を選んでください。", current_branch); }
+            let mut options = vec![SelectOption::new(format!("'{}' をマージする", base_ref), "merge".to_string())];
+            if can_rebase { options.push(SelectOption::new(format!("'{}' にリベースする", base_ref), "rebase".to_string())); }
+            options.push(SelectOption::new("更新せずにプッシュする", String::new()));
+            prompt_select("プッシュ前に更新しますか？", options)
+        }
+    };
+    let mut success = true;
+    match action.as_str() {
+        "merge" => handle_command_result(GitCommand::merge(&base_ref), |s| success = s),
+        "rebase" => handle_command_result(GitCommand::rebase(&base_ref), |s| success = s),
+        _ => return,
+    }
+    if !success {
+        if action == "merge" { handle_conflict_and_offer_new_branch("マージ", current_branch); }
+        exit_rebase_stopped("リベース中にコンフリクトが発生しました。プッシュは中止しました。");
+    }
+    println!("{}", format!("'{}' の最新を取り込みました。", base_ref).green());
+}
+
+// ステージした変更を一覧表示し、選んだファイルをステージから外してから差分を見せて確認する。
+// コミットを続けない場合 (すべて外した/確認で中止した) は false
+fn review_staged_files(full_diff: bool) -> bool {
+    let staged: Vec<utils::StatusEntry> = get_status_entries().into_iter().filter(|e| e.is_staged()).collect();
+    if staged.is_empty() {
+        println!("{}", "コミットする変更がないため中止しました。".yellow());
+        return false;
+    }
+    println!("{}", format!("ステージした変更 ({} 件):", staged.len()).bold());
+    staged.iter().for_each(|e| println!("  {} {}", e.index.to_string().green(), e.path));
+    let options: Vec<MultiSelectOption<String>> = staged.iter()
+        .map(|e| MultiSelectOption::new(e.path.clone(), e.path.clone()).with_hint(e.index.to_string()))
+        .collect();
+    let excluded = prompt_multi_select("コミットから除外するファイル (なければそのまま確定)", options);
+    excluded.iter().for_each(|p| handle_command_result_void(GitCommand::unstage(p)));
+    if !excluded.is_empty() { println!("{} 件のファイルをステージから外しました (変更は作業ツリーに残っています)。", excluded.len()); }
+    if excluded.len() == staged.len() {
+        println!("{}", "コミットする変更がないため中止しました。".yellow());
+        return false;
+    }
+    handle_command_result_void(GitCommand::diff_cached(full_diff));
+    if !confirm("この内容でコミットしますか？") {
+        handle_command_result_void(GitCommand::unstage(utils::path_scope().unwrap_or(".")));
+        println!("{}", "コミットを中止しました。変更はステージから外し、作業ツリーに残っています。".yellow());
+        return false;
+    }
+    true
+}
+
+// 設定または --merge/--rebase/--ff-only で選んだ方法でリモートの同名ブランチをプルする
+fn pull_current_branch(args: &[String], current_branch: &str) {
+    let strategy = selected_update_strategy(args).unwrap_or(utils::UpdateStrategy::Merge);
+    let mut pull_success = false;
+    let pull_result = match strategy {
+        utils::UpdateStrategy::Merge => GitCommand::pull(utils::remote(), current_branch),
+        utils::UpdateStrategy::Rebase => GitCommand::pull_rebase(utils::remote(), current_branch),
+        utils::UpdateStrategy::FfOnly => GitCommand::pull_ff_only(utils::remote(), current_branch),
+    };
+    handle_command_result(pull_result, |success| pull_success = success);
+    if pull_success {
+        println!("{}", "プル成功。最新の状態です。".green());
+    } else if strategy == utils::UpdateStrategy::FfOnly {
+        refuse_non_fast_forward(&format!("{}/{}", utils::remote(), current_branch));
+    } else {
+        handle_conflict_and_offer_new_branch("プル", current_branch);
+    }
+}
+
+// HEAD が既にリモートの同名ブランチに含まれているか (同期済み、またはリモートの方が進んでいる)
+fn is_head_pushed() -> bool {
+    let branch = get_current_branch_name();
+    let mut head_id = String::new();
+    handle_command_result(GitCommand::rev_parse_commit_id("HEAD"), |id| head_id = id);
+    if branch.is_empty() || head_id.is_empty() { return false; }
+    matches!(get_branch_display_status(&branch, &head_id).0, BranchDisplayStatus::Synced | BranchDisplayStatus::Behind { .. })
+}
+
+// プッシュ前にリモートの同名ブランチと比べ、遅れている/分岐している場合は先にプルするか強制プッシュするかを選ばせる。
+// キャンセル時は None、プッシュした場合は先にプルしたかどうかを返す
+fn push_with_upstream_check(args: &[String], branch: &str) -> Option<bool> {
+    if let Err(e) = GitCommand::fetch_prune_quiet(utils::remote()) {
+        log::warn!("警告: リモート '{}' の取得に失敗しました ({})。前回取得時の状態で確認します。", utils::remote(), e);
+    }
+    let mut local_id = String::new();
+    handle_command_result(GitCommand::rev_parse_commit_id(branch), |id| local_id = id);
+    let (status, _) = get_branch_display_status(branch, &local_id);
+    let (ahead, behind) = match status {
+        BranchDisplayStatus::Behind { behind } => (0, behind),
+        BranchDisplayStatus::Diverged { ahead, behind } => (ahead, behind),
+        _ => {
+            handle_command_result_void(GitCommand::push_u(utils::remote(), branch));
+            return Some(false);
+        }
+    };
+    println!("{}", format!("警告: '{}/{}' にローカルに無いコミットがあります (進み {} / 遅れ {})。このままではプッシュが拒否されます。",
+        utils::remote(), branch, ahead, behind).yellow());
+    let options = vec![
+        SelectOption::new("先にプルして取り込んでからプッシュする".to_string(), "pull".to_string()),
+        SelectOption::new("--force-with-lease で強制プッシュする (リモートのコミットは失われます)".to_string(), "force".to_string()),
+        SelectOption::new("キャンセル".to_string(), "cancel".to_string()),
+    ];
+    match prompt_select("どうしますか？", options).as_str() {
+        "pull" => {
+            pull_current_branch(args, branch);
+            handle_command_result_void(GitCommand::push_u(utils::remote(), branch));
+            Some(true)
+        }
+        "force" => {
+            handle_command_result_void(GitCommand::push_force_with_lease(utils::remote(), branch));
+            Some(false)
+        }
+        _ => None,
+    }
+}
+
+// 設定の commit_prefix を件名の前に付ける (既に付いている場合や --no-commit-prefix 指定時はそのまま)
+fn apply_commit_prefix(args: &[String], message: String) -> String {
+    match utils::config().commit_prefix.as_deref() {
+        Some(prefix) if !prefix.is_empty() && !message.starts_with(prefix) && !has_flag(args, &["--no-commit-prefix"]) => format!("{}{}", prefix, message),
+        _ => message,
+    }
+}
+
+// 回答順 (パイプ入力時): (--amend でプッシュ済みの場合: 書き換え確認 →) (サブモジュール変更時: 含めるか確認 →) (--review/--review-full 時: 除外するファイル → コミット確認 →) コミットメッセージ (-m/--amend/--edit 時は不要。--fixup/--squash 時: 対象のコミット (→ squash で -m 未指定時: 追記するメッセージ)) → プッシュ確認 (--push/--no-push や設定の auto_push 指定時は不要) → (リモートより遅れている/分岐している場合: プル/強制プッシュ/キャンセル →) (--check-base で古い場合: 更新方法) → プル確認 (→ コンフリクト時: (rerere 無効時: rerere 有効化確認 →) ブランチ作成確認 → ブランチ名)
+pub fn git_save(args: &[String]) {
+    let date = flag_value(args, &["--date"]);
+    if date.as_deref().is_some_and(|d| d.trim().is_empty()) {
+        eprintln!("{}", "エラー: --date に日付を指定してください。".red()); exit(1);
+    }
+    let fixup_target = ["fixup", "squash"].into_iter()
+        .find(|kind| has_flag(args, &[&format!("--{}", kind)]) || flag_value(args, &[&format!("--{}", kind)]).is_some())
+        .map(|kind| (kind, flag_value(args, &[&format!("--{}", kind)]).filter(|v| !v.starts_with('-'))));
+    if fixup_target.is_some() && (date.is_some() || has_flag(args, &["--edit", "-e"])) {
+        eprintln!("{}", "エラー: --fixup/--squash は --date や --edit と併用できません。".red()); exit(1);
+    }
+    let message = flag_value(args, &["--message", "-m"]);
+    if message.as_deref().is_some_and(|m| m.trim().is_empty()) {
+        eprintln!("{}", "エラー: メッセージ必須。".red()); exit(1);
+    }
+    if message.is_some() && (fixup_target.as_ref().is_some_and(|(kind, _)| *kind == "fixup") || has_flag(args, &["--edit", "-e"])) {
+        eprintln!("{}", "エラー: -m は --fixup や --edit と併用できません。".red()); exit(1);
+    }
+    let amend = has_flag(args, &["--amend"]);
+    if amend && (fixup_target.is_some() || date.is_some() || has_flag(args, &["--edit", "-e"])) {
+        eprintln!("{}", "エラー: --amend は --fixup/--squash, --date, --edit と併用できません。".red()); exit(1);
+    }
+    // プッシュ済みのコミットを書き換えると、プッシュに強制プッシュが必要になる
+    let amending_pushed = amend && is_head_pushed();
+    if amending_pushed {
+        println!("{}", "警告: 直前のコミットは既にリモートにプッシュされています。".red().bold());
+        println!("{}", "書き換えると強制プッシュが必要になり、同じブランチで作業している人に影響します。".red());
+        if !confirm("それでも直前のコミットを書き換えますか？") { println!("キャンセルしました。"); return; }
+    }
+    if has_flag(args, &["--check"]) {
+        run_pre_save_check();
+    }
+
+    // サブモジュールの参照変更は意図せずコミットされやすいので、含めるか確認する
+    let submodule_paths: Vec<String> = get_status_entries().into_iter()
+        .filter(|e| e.is_submodule).map(|e| e.path).collect();
+    let include_submodules = submodule_paths.is_empty() || {
+        println!("{}", "サブモジュールに変更があります:".yellow());
+        submodule_paths.iter().for_each(|p| println!("  {} {}", p.yellow(), "(サブモジュール)".dimmed()));
+        confirm("サブモジュールの参照の変更もコミットに含めますか？")
+    };
 
-pub fn git_save(_args: &[String]) {
-    handle_command_result_void(GitCommand::add("."));
-    let msg = prompt_input("コミットメッセージ: ");
-    if msg.is_empty() { eprintln!("{}", "エラー: メッセージ必須。".red()); exit(1); }
-    handle_command_result_void(GitCommand::commit(&msg));
-    println!("ローカルにコミットしました。");
+    let add_target = utils::path_scope().unwrap_or(".");
+    handle_command_result_void(GitCommand::add(add_target));
+    if !include_submodules {
+        submodule_paths.iter().for_each(|p| handle_command_result_void(GitCommand::unstage(p)));
+        println!("サブモジュールの変更はコミットから除外しました。");
+    }
+    let review_full = has_flag(args, &["--review-full"]);
+    if (review_full || has_flag(args, &["--review"])) && !review_staged_files(review_full) {
+        return;
+    }
+    // ステージした変更が無いと git commit が失敗するため、先に知らせて終える (--amend はメッセージだけの変更もできる)
+    if !amend && get_working_tree_status().staged == 0 {
+        println!("{}", "コミットする変更がありません。".yellow());
+        return;
+    }
+    let fixup_target = fixup_target.map(|(kind, target)| {
+        let target = target.unwrap_or_else(|| {
+            let options = get_commit_select_options("HEAD", 20);
+            if options.is_empty() { eprintln!("{}", "エラー: コミットがありません。".red()); exit(1); }
+            prompt_select(&format!("{} の対象のコミット", kind), options)
+        });
+        let mut exists = false;
+        handle_command_result(GitCommand::rev_parse_verify(&format!("{}^{{commit}}", target)), |e| exists = e);
+        if !exists { eprintln!("エラー: コミット '{}' が見つかりません。", target.red()); exit(1); }
+        (kind.to_string(), target)
+    });
+    if let Some((kind, target)) = &fixup_target {
+        // squash! の場合だけ、対象のメッセージに追記する内容を入力する
+        let message = if kind == "squash" { Some(message.unwrap_or_else(|| prompt_input("追記するメッセージ (空行で件名のみ): "))).filter(|m| !m.is_empty()) } else { None };
+        handle_command_result_void(GitCommand::commit_fixup(kind, target, message.as_deref()));
+    } else if amend {
+        let message = message.map(|m| apply_commit_prefix(args, m));
+        if let Some(msg) = &message { check_commit_subject(msg); }
+        handle_command_result_void(GitCommand::commit_amend(message.as_deref()));
+    } else if has_flag(args, &["--edit", "-e"]) {
+        let mut committed = false;
+        handle_command_result(GitCommand::commit_edit(date.as_deref()), |success| committed = success);
+        if !committed {
+            println!("{}", "コミットを中止しました。変更はステージされたままです。".yellow());
+            return;
+        }
+    } else {
+        let msg = message.unwrap_or_else(|| prompt_input("コミットメッセージ: "));
+        if msg.trim().is_empty() { eprintln!("{}", "エラー: メッセージ必須。".red()); exit(1); }
+        let msg = apply_commit_prefix(args, msg);
+        check_commit_subject(&msg);
+        match &date {
+            // 日付の形式チェックは git に任せ、不正な場合は git のエラーをそのまま表示する
+            Some(d) => handle_command_result_void(GitCommand::commit_with_date(&msg, d)),
+            None => handle_command_result_void(GitCommand::commit(&msg)),
+        }
+    }
+    if let Some(d) = &date {
+        println!("{}", format!("注意: コミット日時として '{}' を使用しました。", d).yellow());
+    }
+    println!("{}", if amend { "直前のコミットを書き換えました。" } else { "ローカルにコミットしました。" });
 
     let current_branch = get_current_branch_name();
     if current_branch.is_empty() { eprintln!("{}", "エラー: 現在のブランチ不明。プッシュをスキップ。".yellow()); return; }
+    if amending_pushed {
+        println!("{}", format!("リモートへの反映には強制プッシュが必要です: git push --force-with-lease {} {}", utils::remote(), current_branch).yellow());
+        return;
+    }
 
     let mut remote_url = String::new();
     // remote_get_url は失敗する可能性があるので、エラーハンドリングする
-    match GitCommand::remote_get_url("origin") {
+    match GitCommand::remote_get_url(utils::remote()) {
         Ok(url) => remote_url = url,
         Err(_) => { /* origin がなければ空のまま */ }
     }
 
 
     if !remote_url.is_empty() {
-        if confirm(&format!("リモート 'origin/{}' にもプッシュしますか？", current_branch)) {
-            handle_command_result_void(GitCommand::push_u("origin", &current_branch));
-            println!("'origin/{}' へプッシュしました。", current_branch.cyan());
-            if confirm("リモートの最新の変更をプルしますか？ (コンフリクトの可能性あり)") {
-                let mut pull_success = false;
-                handle_command_result(GitCommand::pull("origin", &current_branch), |success| pull_success = success);
-                if pull_success {
-                    println!("{}", "プル成功。最新の状態です。".green());
-                } else {
-                    handle_conflict_and_offer_new_branch("プル", &current_branch);
-                }
+        let push = if has_flag(args, &["--push"]) {
+            true
+        } else if has_flag(args, &["--no-push"]) {
+            false
+        } else {
+            utils::config().auto_push.unwrap_or_else(|| confirm_with_default(&format!("リモート '{}/{}' にもプッシュしますか？", utils::remote(), current_branch), true))
+        };
+        if push {
+            if has_flag(args, &["--check-base"]) || utils::config().require_updated_base {
+                ensure_updated_base(&current_branch, selected_update_strategy(args));
+            }
+            let Some(pulled) = push_with_upstream_check(args, &current_branch) else {
+                println!("プッシュを中止しました。コミットはローカルに残っています。");
+                return;
+            };
+            println!("'{}/{}' へプッシュしました。", utils::remote(), current_branch.cyan());
+            if !pulled && confirm_with_default("リモートの最新の変更をプルしますか？ (コンフリクトの可能性あり)", true) {
+                pull_current_branch(args, &current_branch);
             }
         } else {
             println!("リモートへのプッシュはスキップしました。");
         }
     } else {
-        println!("{}", "リモート 'origin' が未設定のため、プッシュはスキップしました。".yellow());
+        println!("{}", format!("リモート '{}' が未設定のため、プッシュはスキップしました。", utils::remote()).yellow());
     }
     println!("{}", "保存処理が完了しました。".green());
 }
 
-pub fn git_setup(_args: &[String]) {
-    if !std::path::Path::new(".git").exists() {
-        handle_command_result_void(GitCommand::init());
-        println!("Gitリポジトリを初期化しました。");
+// 回答順 (パイプ入力時): URL設定確認 → 新しいURL (URLを設定しない場合: 削除確認)
+pub fn git_setup(args: &[String]) {
+    let initial_branch = flag_value(args, &["--initial-branch", "-b"]);
+    if let Some(name) = &initial_branch
+        && !GitCommand::check_ref_format_branch(name).unwrap_or(false) {
+        eprintln!("エラー: '{}' はブランチ名として無効です。", name.red()); exit(1);
+    }
+    // サブディレクトリやワークツリー (.git がファイル) でも正しく判定できるよう git に問い合わせる
+    match GitCommand::git_dir() {
+        Ok(git_dir) => {
+            if initial_branch.is_some() { log::warn!("警告: 既にリポジトリがあるため、--initial-branch は無視しました。"); }
+            if let Ok(common_dir) = GitCommand::git_common_dir()
+                && common_dir != git_dir {
+                println!("{}", format!("注意: ここはリンクされたワークツリーです (本体: {})。", common_dir).yellow());
+            }
+        }
+        Err(_) => {
+            match &initial_branch {
+                Some(name) => handle_command_result_void(GitCommand::init_with_branch(name)),
+                None => handle_command_result_void(GitCommand::init()),
+            }
+            println!("Gitリポジトリを初期化しました。");
+        }
     }
     
     let mut current_url = String::new();
-    match GitCommand::remote_get_url("origin") {
+    match GitCommand::remote_get_url(utils::remote()) {
         Ok(url) if !url.is_empty() => {
-            println!("現在のリモート 'origin' URL: {}", url.cyan());
+            println!("現在のリモート '{}' URL: {}", utils::remote(), url.cyan());
             current_url = url;
         }
-        _ => println!("リモート 'origin' は現在設定されていません。"),
+        _ => println!("リモート '{}' は現在設定されていません。", utils::remote()),
     }
 
-    if confirm("リモート 'origin' のURLを設定または変更しますか？") {
+    if confirm(&format!("リモート '{}' のURLを設定または変更しますか？", utils::remote())) {
         let new_url = prompt_input("新しいリモートURL (空でスキップ): ");
         if !new_url.is_empty() {
             if current_url.is_empty() {
-                handle_command_result_void(GitCommand::remote_add("origin", &new_url));
+                handle_command_result_void(GitCommand::remote_add(utils::remote(), &new_url));
             } else {
-                handle_command_result_void(GitCommand::remote_set_url("origin", &new_url));
+                handle_command_result_void(GitCommand::remote_set_url(utils::remote(), &new_url));
             }
-            println!("リモート 'origin' URLを '{}' に設定/変更しました。", new_url.cyan());
+            println!("リモート '{}' URLを '{}' に設定/変更しました。", utils::remote(), new_url.cyan());
         }
-    } else if !current_url.is_empty() && confirm("リモート 'origin' を削除 (追跡を解除) しますか？") {
-        handle_command_result_void(GitCommand::remote_remove("origin"));
-        println!("リモート 'origin' を削除しました。");
+    } else if !current_url.is_empty() && confirm(&format!("リモート '{}' を削除 (追跡を解除) しますか？", utils::remote())) {
+        handle_command_result_void(GitCommand::remote_remove(utils::remote()));
+        println!("リモート '{}' を削除しました。", utils::remote());
     }
     println!("{}", "セットアップ処理を終了します。".green());
 }
 
 #[derive(PartialEq, Debug)]
-enum BranchDisplayStatus { Synced, LocalOnly, Ahead, Behind, Diverged }
+enum BranchDisplayStatus { Synced, LocalOnly, Ahead { ahead: usize }, Behind { behind: usize }, Diverged { ahead: usize, behind: usize } }
+
+// リモートとの差分コミット数。例: "↑3 ↓1"
+fn format_ahead_behind(ahead: usize, behind: usize) -> String {
+    let mut parts = Vec::new();
+    if ahead > 0 { parts.push(format!("↑{}", ahead).green().to_string()); }
+    if behind > 0 { parts.push(format!("↓{}", behind).red().to_string()); }
+    parts.join(" ")
+}
 
 fn get_branch_display_status(local_branch: &str, local_id: &str) -> (BranchDisplayStatus, String) {
-    let remote_tracking_branch = format!("origin/{}", local_branch);
-    let mut note = String::new();
+    let status = branch_display_status_with(&GitCommand, utils::remote(), local_branch, local_id);
+    let note = branch_status_note(&status);
+    (status, note)
+}
 
-    let remote_id_res = GitCommand::rev_parse_verify(&remote_tracking_branch)
-        .and_then(|exists| if exists { GitCommand::rev_parse_commit_id(&remote_tracking_branch)} else { Ok(String::new()) });
+fn branch_display_status_with(git: &dyn GitCommandTrait, remote: &str, local_branch: &str, local_id: &str) -> BranchDisplayStatus {
+    let remote_tracking_branch = format!("{}/{}", remote, local_branch);
 
-    let status = match remote_id_res {
+    let remote_id_res = git.rev_parse_verify(&remote_tracking_branch)
+        .and_then(|exists| if exists { git.rev_parse_commit_id(&remote_tracking_branch)} else { Ok(String::new()) });
+
+    match remote_id_res {
         Ok(remote_id) if !remote_id.is_empty() => {
             if local_id == remote_id {
                 BranchDisplayStatus::Synced
             } else {
-                match GitCommand::merge_base(local_id, &remote_id) {
-                    Ok(base_id) => {
-                        if base_id == remote_id { note = "(要プッシュ)".dimmed().to_string(); BranchDisplayStatus::Ahead }
-                        else if base_id == local_id { note = "(要プル)".dimmed().to_string(); BranchDisplayStatus::Behind }
-                        else { note = "(分岐)".dimmed().to_string(); BranchDisplayStatus::Diverged }
-                    }
-                    Err(_) => BranchDisplayStatus::LocalOnly, // merge-base失敗は判定不能->LocalOnly
+                let ahead = git.rev_list_count(&format!("{}..{}", remote_id, local_id));
+                let behind = git.rev_list_count(&format!("{}..{}", local_id, remote_id));
+                match (ahead, behind) {
+                    (Ok(ahead), Ok(0)) => BranchDisplayStatus::Ahead { ahead },
+                    (Ok(0), Ok(behind)) => BranchDisplayStatus::Behind { behind },
+                    (Ok(ahead), Ok(behind)) => BranchDisplayStatus::Diverged { ahead, behind },
+                    _ => BranchDisplayStatus::LocalOnly, // rev-list失敗は判定不能->LocalOnly
                 }
             }
         }
         _ => BranchDisplayStatus::LocalOnly,
-    };
-    (status, note)
+    }
+}
+
+fn branch_status_note(status: &BranchDisplayStatus) -> String {
+    match *status {
+        BranchDisplayStatus::Ahead { ahead } => format!("{} {}{}", "(要プッシュ".dimmed(), format_ahead_behind(ahead, 0), ")".dimmed()),
+        BranchDisplayStatus::Behind { behind } => format!("{} {}{}", "(要プル".dimmed(), format_ahead_behind(0, behind), ")".dimmed()),
+        BranchDisplayStatus::Diverged { ahead, behind } => format!("{} {}{}", "(分岐".dimmed(), format_ahead_behind(ahead, behind), ")".dimmed()),
+        _ => String::new(),
+    }
+}
+
+// for-each-ref の %(upstream:track) ("[ahead 3, behind 1]" や "[gone]") から状態を求める
+fn parse_upstream_track(track: &str) -> BranchDisplayStatus {
+    if track.contains("gone") { return BranchDisplayStatus::LocalOnly; }
+    let count = |key: &str| track.trim_matches(|c| c == '[' || c == ']').split(", ")
+        .find_map(|part| part.strip_prefix(key)?.parse().ok())
+        .unwrap_or(0);
+    match (count("ahead "), count("behind ")) {
+        (0, 0) => BranchDisplayStatus::Synced,
+        (ahead, 0) => BranchDisplayStatus::Ahead { ahead },
+        (0, behind) => BranchDisplayStatus::Behind { behind },
+        (ahead, behind) => BranchDisplayStatus::Diverged { ahead, behind },
+    }
+}
+
+// ローカルブランチごとの (コミットID, 上流, 差分)。for-each-ref 1回で取得し、失敗時は None (ブランチごとの確認に戻す)
+fn get_local_tracking_info() -> Option<std::collections::HashMap<String, (String, String, String)>> {
+    let output = GitCommand::for_each_ref_with_tracking().ok()?;
+    Some(output.lines().filter_map(|line| {
+        // 出力全体が trim されるため、最終行の末尾の空フィールドは欠けることがある
+        let fields: Vec<&str> = line.split('\t').collect();
+        let field = |i: usize| fields.get(i).copied().unwrap_or("").to_string();
+        if fields.len() < 2 { return None; }
+        Some((field(0), (field(1), field(2), field(3))))
+    }).collect())
+}
+
+// 現在のブランチの横に出す未コミットの変更の印。例: "(+2 ~1 ?3)" (ステージ済み/未ステージ/未追跡の数)
+fn get_change_marker(plain: bool) -> String {
+    let status = utils::WorkingTreeStatus::from_entries(&get_status_entries());
+    if status.is_clean() { return String::new(); }
+    if plain { return "*".yellow().bold().to_string(); }
+    let counts = [
+        (status.conflicted, "!"),
+        (status.staged, "+"),
+        (status.unstaged, "~"),
+        (status.untracked, "?"),
+    ];
+    let parts: Vec<String> = counts.iter()
+        .filter(|(n, _)| *n > 0)
+        .map(|(n, mark)| {
+            let text = format!("{}{}", mark, n);
+            match *mark { "!" => text.red(), "+" => text.green(), "~" => text.yellow(), _ => text.dimmed() }.to_string()
+        })
+        .collect();
+    format!("({})", parts.join(" "))
+}
+
+impl BranchDisplayStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            BranchDisplayStatus::Synced => "synced",
+            BranchDisplayStatus::LocalOnly => "local_only",
+            BranchDisplayStatus::Ahead { .. } => "ahead",
+            BranchDisplayStatus::Behind { .. } => "behind",
+            BranchDisplayStatus::Diverged { .. } => "diverged",
+        }
+    }
+    fn counts(&self) -> (usize, usize) {
+        match *self {
+            BranchDisplayStatus::Ahead { ahead } => (ahead, 0),
+            BranchDisplayStatus::Behind { behind } => (0, behind),
+            BranchDisplayStatus::Diverged { ahead, behind } => (ahead, behind),
+            _ => (0, 0),
+        }
+    }
 }
 
+// branch --json の1要素
+#[derive(serde::Serialize)]
+struct BranchJson {
+    name: String,
+    is_current: bool,
+    is_remote_only: bool,
+    status: &'static str,
+    ahead: usize,
+    behind: usize,
+}
 
-pub fn git_branch(_args: &[String]) {
+pub fn git_branch(args: &[String]) {
+    let json = has_flag(args, &["--json"]);
+    let no_fetch = has_flag(args, &["--no-fetch"])
+        || (!has_flag(args, &["--fetch"]) && utils::config().auto_fetch == Some(false));
+    let local_only = has_flag(args, &["--local"]);
     let mut remote_url = String::new();
-    handle_command_result(GitCommand::remote_get_url("origin"), |url| remote_url = url);
+    handle_command_result(GitCommand::remote_get_url(utils::remote()), |url| remote_url = url);
 
     if !remote_url.is_empty() {
-        handle_command_result_void(GitCommand::fetch_prune("origin"));
-        println!("ブランチ一覧 (リモート 'origin' を含む):");
-    } else {
-        println!("ローカルブランチ一覧 (リモート 'origin' 未設定):");
+        if no_fetch {
+            // 取得は省略し、前回取得したリモートの状態と比較する
+        } else if json {
+            handle_command_result_void(GitCommand::fetch_prune_quiet(utils::remote()));
+        } else {
+            let mut pruned = Vec::new();
+            handle_command_result(GitCommand::fetch_prune_report(utils::remote()), |p| pruned = p);
+            if !pruned.is_empty() { println!("{}", format!("削除された追跡ブランチ: {}", pruned.join(", ")).dimmed()); }
+        }
+        if !json {
+            if local_only {
+                println!("ローカルブランチ一覧:");
+            } else {
+                println!("ブランチ一覧 (リモート '{}' を含む):", utils::remote());
+            }
+        }
+    } else if !json {
+        println!("ローカルブランチ一覧 (リモート '{}' 未設定):", utils::remote());
     }
 
     let mut branches_all_str = String::new();
     handle_command_result(GitCommand::branch_list_all_str(), |s| branches_all_str = s);
     
-    let _current_branch_name = get_current_branch_name();
-    let mut uncommitted_changes = false;
-    handle_command_result(GitCommand::status_porcelain_v1(), |s| uncommitted_changes = !s.is_empty());
+    let change_marker = if json { String::new() } else { get_change_marker(has_flag(args, &["--no-count"])) };
 
     let mut displayed_locals = std::collections::HashSet::new();
+    let mut json_entries = Vec::new();
+    let mut rows: Vec<(String, String)> = Vec::new(); // (ブランチ名の列, 状態の注記)
+    let tracking_info = get_local_tracking_info();
+    let remote_prefix = format!("{}/", utils::remote());
+    let remote_branches: std::collections::HashSet<&str> = branches_all_str.lines()
+        .filter_map(|line| line.trim().strip_prefix("remotes/")?.strip_prefix(&remote_prefix))
+        .collect();
 
     for line in branches_all_str.lines() {
         let trimmed_line = line.trim();
@@ -227,44 +1029,107 @@ pub fn git_branch(_args: &[String]) {
         
         if branch_name_raw.is_empty() || branch_name_raw.ends_with("/HEAD") || branch_name_raw.contains("->") { continue; }
 
-        let display_name = if branch_name_raw.starts_with("origin/") {
-            branch_name_raw.trim_start_matches("origin/").to_string()
-        } else {
-            branch_name_raw.to_string()
-        };
+        let display_name = branch_name_raw.strip_prefix(&remote_prefix).unwrap_or(branch_name_raw).to_string();
+        if trimmed_line.starts_with("remotes/") && !branch_name_raw.starts_with(&remote_prefix) { continue; }
 
-        if trimmed_line.starts_with("remotes/origin/") {
-            if !displayed_locals.contains(&display_name) {
-                 println!("  {} {}", display_name.blue(), "(リモートのみ)".dimmed());
+        if trimmed_line.starts_with("remotes/") {
+            if local_only || displayed_locals.contains(&display_name) { continue; }
+            if json {
+                json_entries.push(BranchJson { name: display_name, is_current: false, is_remote_only: true, status: "remote_only", ahead: 0, behind: 0 });
+            } else {
+                rows.push((format!("  {}", display_name.blue()), "(リモートのみ)".dimmed().to_string()));
             }
         } else {
             displayed_locals.insert(display_name.clone());
-            let mut local_id = String::new();
-            handle_command_result(GitCommand::rev_parse_commit_id(&display_name), |id| local_id = id);
+            let tracking = tracking_info.as_ref().and_then(|info| info.get(&display_name));
+            let mut local_id = tracking.map(|(id, _, _)| id.clone()).unwrap_or_default();
+            if tracking.is_none() {
+                handle_command_result(GitCommand::rev_parse_commit_id(&display_name), |id| local_id = id);
+            }
 
-            let (status, note) = if !remote_url.is_empty() && !local_id.is_empty() {
-                get_branch_display_status(&display_name, &local_id)
-            } else {
-                (BranchDisplayStatus::LocalOnly, String::new())
+            let (status, note) = match tracking {
+                _ if remote_url.is_empty() || local_id.is_empty() => (BranchDisplayStatus::LocalOnly, String::new()),
+                // 対象のリモートを上流にしていれば、for-each-ref の差分をそのまま使う
+                Some((_, upstream, track)) if upstream.starts_with(&remote_prefix) => {
+                    let status = parse_upstream_track(track);
+                    let note = branch_status_note(&status);
+                    (status, note)
+                }
+                Some(_) if !remote_branches.contains(display_name.as_str()) => (BranchDisplayStatus::LocalOnly, String::new()),
+                _ => get_branch_display_status(&display_name, &local_id),
             };
+            if json {
+                let (ahead, behind) = status.counts();
+                json_entries.push(BranchJson { name: display_name, is_current, is_remote_only: false, status: status.as_str(), ahead, behind });
+                continue;
+            }
             
             let display_str = match status {
                 BranchDisplayStatus::Synced => format!("  {}", display_name.blue()),
-                BranchDisplayStatus::LocalOnly | BranchDisplayStatus::Ahead | BranchDisplayStatus::Behind | BranchDisplayStatus::Diverged => {
+                BranchDisplayStatus::LocalOnly | BranchDisplayStatus::Ahead { .. } | BranchDisplayStatus::Behind { .. } | BranchDisplayStatus::Diverged { .. } => {
                     format!("  {}", display_name.truecolor(255,165,0)) // オレンジ (colored)
                 }
             };
             if is_current {
-                println!("* {} {}", display_name.cyan().bold(), if uncommitted_changes { "*".yellow().bold() } else { "".normal() });
+                rows.push((format!("* {}", display_name.cyan().bold()), change_marker.clone()));
             } else {
-                println!("{} {}", display_str, note);
+                rows.push((display_str, note));
             }
         }
     }
+    // 状態の注記が縦に揃うよう、ブランチ名の列を最も長い名前の表示幅に合わせる
+    let name_width = rows.iter().map(|(name, _)| utils::visible_width(name)).max().unwrap_or(0);
+    for (name, note) in &rows {
+        if note.is_empty() {
+            println!("{}", name);
+        } else {
+            println!("{}{} {}", name, " ".repeat(name_width - utils::visible_width(name)), note);
+        }
+    }
+    if json {
+        match serde_json::to_string_pretty(&json_entries) {
+            Ok(text) => println!("{}", text),
+            Err(e) => { eprintln!("エラー: JSON の出力に失敗しました: {}", e); exit(1); }
+        }
+    }
+}
+
+// ブランチが実際に切り替わった場合に、設定ファイルの post_checkout_command (例: npm install) を実行する
+fn run_post_checkout(args: &[String], previous_branch: &str) {
+    let Some(command_line) = utils::config().post_checkout_command.as_deref() else { return };
+    if has_flag(args, &["--no-post"]) || get_current_branch_name() == previous_branch { return; }
+    log::info!("切り替え後のコマンドを実行します: {}", command_line);
+    let mut success = false;
+    handle_command_result(utils::run_shell_command(command_line), |s| success = s);
+    if !success { log::warn!("警告: 切り替え後のコマンド \"{}\" が失敗しました。", command_line); }
+}
+
+// switch で入力された名前の種類
+#[derive(Debug, PartialEq)]
+enum BranchInput {
+    Branch,  // ローカルブランチ、またはリモートのみのブランチ (checkout が追跡ブランチを作る)
+    Commit,  // ブランチではないが、タグやコミットIDとして解決できる
+    Unknown,
 }
 
+fn resolve_branch_input(git: &dyn GitCommandTrait, remote: &str, name: &str) -> BranchInput {
+    let ref_exists = |ref_name: String| {
+        let mut exists = false;
+        handle_command_result(git.rev_parse_verify(&ref_name), |e| exists = e);
+        exists
+    };
+    if ref_exists(format!("refs/heads/{}", name)) || ref_exists(format!("refs/remotes/{}/{}", remote, name)) {
+        BranchInput::Branch
+    } else if ref_exists(format!("{}^{{commit}}", name)) {
+        BranchInput::Commit
+    } else {
+        BranchInput::Unknown
+    }
+}
 
-pub fn git_switch(_args: &[String]) {
+// 回答順 (パイプ入力時): 切り替えるブランチ名 (空行で前回のブランチ) (→ タグ/コミット指定時: 作成確認 → 新しいブランチ名) (→ 未コミットの変更がある場合: handle_uncommitted_changes_before_action を参照)
+pub fn git_switch(args: &[String]) {
+    let previous_branch = get_current_branch_name();
     println!("ローカルブランチ一覧:"); 
     let mut branches_str = String::new();
     handle_command_result(GitCommand::branch_list_local_str(), |s| branches_str = s);
@@ -275,17 +1140,48 @@ pub fn git_switch(_args: &[String]) {
         else { println!("  {}", name.truecolor(255,165,0)); } // オレンジ
     });
 
-    let name = prompt_input("切り替えるブランチ名: ");
+    // 前回選んだブランチ (今いるブランチなら、その前に選んだもの) を既定値にする
+    let repo = GitCommand::show_toplevel().unwrap_or_default();
+    let last_branch = utils::recent_branches(&repo).into_iter()
+        .find(|b| *b != previous_branch && GitCommand::rev_parse_verify(&format!("refs/heads/{}", b)).unwrap_or(false));
+    let name = match &last_branch {
+        Some(last) => prompt_input_with_default("切り替えるブランチ名 (空行で前回のブランチ): ", last),
+        None => prompt_input("切り替えるブランチ名: "),
+    };
     if name.is_empty() { eprintln!("{}", "エラー: ブランチ名必須。".red()); exit(1); }
-    let mut exists = false;
-    handle_command_result(GitCommand::rev_parse_verify(&name), |e| exists = e);
-    if !exists { eprintln!("エラー: ブランチ '{}' はローカルに存在せず。", name.red()); exit(1); }
-    
-    handle_command_result_void(GitCommand::checkout(&name)); 
-    println!("ブランチ '{}' へ切り替えました。", name.cyan());
+    let branch_input = resolve_branch_input(&GitCommand, utils::remote(), &name);
+
+    if branch_input == BranchInput::Branch {
+        // 入力したブランチが存在すると分かってから、未コミットの変更を扱う (打ち間違いで変更を失わないように)
+        if name != previous_branch && !handle_uncommitted_changes_before_action("切り替え") { return; }
+        // リモートのみのブランチは git checkout が追跡ブランチを自動作成する
+        handle_command_result_void(GitCommand::checkout(&name));
+        println!("ブランチ '{}' へ切り替えました。", name.cyan());
+        if !repo.is_empty() { utils::remember_switched_branch(&repo, &name, &previous_branch); }
+        run_post_checkout(args, &previous_branch);
+        return;
+    }
+    if branch_input == BranchInput::Unknown {
+        eprintln!("エラー: ブランチ '{}' はローカルに存在せず。", name.red()); exit(1);
+    }
+
+    // タグやコミットIDが入力された場合は、そこから新しいブランチを作るか確認する
+    println!("'{}' はブランチではありませんが、有効なコミット (タグ/コミットID) です。", name.yellow());
+    if !confirm(&format!("'{}' から新しいブランチを作成して切り替えますか？", name)) {
+        println!("切り替えをキャンセルしました。");
+        return;
+    }
+    let new_branch = prompt_input("新しいブランチ名: ");
+    if new_branch.is_empty() { eprintln!("{}", "エラー: ブランチ名必須。".red()); exit(1); }
+    if GitCommand::rev_parse_verify(&format!("refs/heads/{}", new_branch)).unwrap_or(false) { eprintln!("エラー: ブランチ '{}' は既に存在します。", new_branch.red()); exit(1); }
+    if !handle_uncommitted_changes_before_action("切り替え") { return; }
+    handle_command_result_void(GitCommand::checkout_b_from(&new_branch, &name));
+    println!("'{}' から新しいブランチ '{}' を作成し切り替えました。", name.cyan(), new_branch.cyan());
+    run_post_checkout(args, &previous_branch);
 }
 
-pub fn git_merge(_args: &[String]) {
+// 回答順 (パイプ入力時): マージ元ブランチ名 (→ 未コミットの変更がある場合: handle_uncommitted_changes_before_action を参照) → 削除確認 (→ コンフリクト時: (rerere 無効時: rerere 有効化確認 →) ブランチ作成確認 → ブランチ名)
+pub fn git_merge(args: &[String]) {
     let cur_b = get_current_branch_name();
     if cur_b.is_empty() { eprintln!("{}", "エラー: 現在のブランチ不明。".red()); exit(1); }
     let target = prompt_input(&format!("ブランチ '{}' にマージするブランチ名: ", cur_b.cyan()));
@@ -293,12 +1189,17 @@ pub fn git_merge(_args: &[String]) {
     let mut target_exists = false;
     handle_command_result(GitCommand::rev_parse_verify(&target), |e| target_exists = e);
     if !target_exists { eprintln!("エラー: ブランチ '{}' は存在せず。", target.red()); exit(1); }
+    if !autostash_enabled(args) && !handle_uncommitted_changes_before_action("マージ") { return; }
     
+    // 未コミットの変更がある場合のみ自動スタッシュが実際に働く
+    let autostashed = autostash_enabled(args) && has_uncommitted_changes();
     let mut merge_success = false;
-    handle_command_result(GitCommand::merge(&target), |success| merge_success = success);
+    let merge_result = if autostash_enabled(args) { GitCommand::merge_autostash(&target) } else { GitCommand::merge(&target) };
+    handle_command_result(merge_result, |success| merge_success = success);
 
     if merge_success {
         println!("{}", "マージ成功。".green());
+        if autostashed { log::info!("未コミットの変更を自動スタッシュし、マージ後に戻しました。"); }
         if confirm(&format!("マージ元のローカルブランチ '{}' を削除しますか？", target)) {
             handle_command_result_void(GitCommand::branch_delete_local_d(&target)); 
             println!("ローカルブランチ '{}' を削除しました。", target.cyan());
@@ -308,8 +1209,15 @@ pub fn git_merge(_args: &[String]) {
     }
 }
 
-pub fn git_copy(_args: &[String]) {
-    let source = prompt_input("コピー元ブランチ名: ");
+// 回答順 (パイプ入力時): コピー元ブランチ名 (空行で提案された既定値) → 新しいブランチ名 → プッシュ確認
+pub fn git_copy(args: &[String]) {
+    let source = match suggest_new_branch_base() {
+        Some(suggested) => {
+            println!("注意: フィーチャーブランチ上のため、コピー元の既定値は最新の既定ブランチ '{}' です。", suggested.cyan());
+            prompt_input_with_default("コピー元ブランチ名: ", &suggested)
+        }
+        None => prompt_input("コピー元ブランチ名: "),
+    };
     if source.is_empty() { eprintln!("{}", "エラー: コピー元ブランチ名必須。".red()); exit(1); }
     let mut source_exists = false;
     handle_command_result(GitCommand::rev_parse_verify(&source), |e| source_exists = e);
@@ -317,32 +1225,75 @@ pub fn git_copy(_args: &[String]) {
 
     let new_name = prompt_input("新しいブランチ名: ");
     if new_name.is_empty() { eprintln!("{}", "エラー: 新ブランチ名必須。".red()); exit(1); }
-    let mut new_exists = false;
-    handle_command_result(GitCommand::rev_parse_verify(&new_name), |e| new_exists = e);
-    if new_exists { eprintln!("エラー: ブランチ '{}' は既に存在。", new_name.red()); exit(1); }
+    let new_name = apply_branch_prefix(args, &new_name);
+    ensure_branch_not_exists(&new_name);
 
     handle_command_result_void(GitCommand::branch_create_local_from(&new_name, &source));
     println!("ローカルブランチ '{}' を '{}' からコピーしました。", new_name.cyan(), source.cyan());
 
     let mut remote_url = String::new();
-    handle_command_result(GitCommand::remote_get_url("origin"), |url| remote_url = url);
-    if !remote_url.is_empty() && confirm(&format!("コピーしたブランチ '{}' をリモート 'origin' にプッシュし追跡設定しますか？", new_name)) {
+    handle_command_result(GitCommand::remote_get_url(utils::remote()), |url| remote_url = url);
+    if !remote_url.is_empty() && confirm_with_default(&format!("コピーしたブランチ '{}' をリモート '{}' にプッシュし追跡設定しますか？", new_name, utils::remote()), true) {
+        let previous_branch = get_current_branch_name();
         handle_command_result_void(GitCommand::checkout(&new_name)); 
-        handle_command_result_void(GitCommand::push_u("origin", &new_name)); 
-        println!("ブランチ '{}' を 'origin/{}' へプッシュし追跡設定しました。", new_name.cyan(), new_name.blue());
+        handle_command_result_void(GitCommand::push_u(utils::remote(), &new_name)); 
+        println!("ブランチ '{}' を '{}/{}' へプッシュし追跡設定しました。", new_name.cyan(), utils::remote(), new_name.blue());
+        run_post_checkout(args, &previous_branch);
     }
 }
 
-pub fn git_delete(_args: &[String]) {
-    let mut remote_url = String::new();
-    handle_command_result(GitCommand::remote_get_url("origin"), |url| remote_url = url);
-    if !remote_url.is_empty() { handle_command_result_void(GitCommand::fetch_prune("origin")); }
+// --dry-run 時は確認せずに y とみなし、実行した場合の流れをすべて表示する
+fn confirm_with(git: &dyn GitCommandTrait, message: &str) -> bool {
+    if git.is_dry_run() {
+        eprintln!("{} (y/N): {}", message, "y (--dry-run)".dimmed());
+        return true;
+    }
+    confirm(message)
+}
 
-    println!("現在のブランチ (ローカルとリモート origin):");
-    git_branch(&[]); 
+// 未マージのブランチは git branch -d が拒否するので、その場合だけ強制削除するか尋ねる
+fn delete_local_branch(git: &dyn GitCommandTrait, name: &str, force: bool) -> bool {
+    if force {
+        handle_command_result_void(git.branch_delete_local_force(name));
+        return true;
+    }
+    // git のメッセージは言語設定で変わるため、未マージかどうかは -d の前に自分で判定する
+    if !is_branch_merged_with(git, name) {
+        println!("{}", format!("ブランチ '{}' はマージされていない変更を含んでいます。", name).yellow());
+        if !confirm_with(git, "マージされていないコミットを破棄して強制削除しますか？") {
+            println!("削除を中止しました。");
+            return false;
+        }
+        handle_command_result_void(git.branch_delete_local_force(name));
+        return true;
+    }
+    match git.branch_delete_local_d(name) {
+        Ok(()) => true,
+        Err(err_msg) => { eprintln!("{}", err_msg); exit(1); }
+    }
+}
 
-    let name_input = prompt_input("削除するブランチ名 (ローカル名 or origin/リモート名): ");
-    if name_input.is_empty() { eprintln!("{}", "エラー: 削除ブランチ名必須。".red()); exit(1); }
+// git branch -d で削除できるか。git と同じく、上流があれば上流に、無ければ HEAD にマージ済みかで判定する
+fn is_branch_merged_with(git: &dyn GitCommandTrait, name: &str) -> bool {
+    let target = git.upstream_of(name).unwrap_or_else(|_| "HEAD".to_string());
+    git.is_ancestor(name, &target).unwrap_or(false)
+}
+
+// 回答順 (パイプ入力時): 削除するブランチ名 → (--dry-run 時は以降の確認はすべて y とみなす) 削除確認 (→ 未マージで --force なしの場合: 強制削除確認) (ローカル名の場合: → リモート削除確認)
+pub fn git_delete(args: &[String]) {
+    let force = has_flag(args, &["--force", "-f"]);
+    // --dry-run では取得 (追跡ブランチの prune を含む) も行わず、削除は表示だけにする
+    let dry_run = has_flag(args, &["--dry-run"]);
+    let git: &dyn GitCommandTrait = if dry_run { &DryRunGitCommand } else { &GitCommand };
+    let mut remote_url = String::new();
+    handle_command_result(GitCommand::remote_get_url(utils::remote()), |url| remote_url = url);
+    if !remote_url.is_empty() && !dry_run { handle_command_result_void(GitCommand::fetch_prune(utils::remote())); }
+
+    println!("現在のブランチ (ローカルとリモート {}):", utils::remote());
+    git_branch(&["--no-fetch".to_string()]);
+
+    let name_input = prompt_input(&format!("削除するブランチ名 (ローカル名 or {}/リモート名): ", utils::remote()));
+    if name_input.is_empty() { eprintln!("{}", "エラー: 削除ブランチ名必須。".red()); exit(1); }
 
     let current_branch = get_current_branch_name();
     if current_branch == name_input {
@@ -350,46 +1301,2869 @@ pub fn git_delete(_args: &[String]) {
         exit(1);
     }
 
-    if name_input.starts_with("origin/") {
-        if remote_url.is_empty() { eprintln!("{}", "エラー: リモート 'origin' が未設定。".red()); exit(1); }
-        let remote_branch_name = name_input.trim_start_matches("origin/");
-        if confirm(&format!("リモートブランチ 'origin/{}' を削除しますか？", remote_branch_name)) {
-            handle_command_result_void(GitCommand::push_delete("origin", remote_branch_name));
-            println!("リモートブランチ 'origin/{}' の削除を試みました。", remote_branch_name.blue());
+    if let Some(remote_branch_name) = name_input.strip_prefix(&format!("{}/", utils::remote())) {
+        if remote_url.is_empty() { eprintln!("{}", format!("エラー: リモート '{}' が未設定。", utils::remote()).red()); exit(1); }
+        if confirm_with(git, &format!("リモートブランチ '{}/{}' を削除しますか？", utils::remote(), remote_branch_name)) {
+            handle_command_result_void(git.push_delete(utils::remote(), remote_branch_name));
+            if !dry_run { println!("リモートブランチ '{}/{}' の削除を試みました。", utils::remote(), remote_branch_name.blue()); }
         }
     } else {
         let mut local_exists = false;
-        handle_command_result(GitCommand::rev_parse_verify(&name_input), |e| local_exists = e);
+        handle_command_result(git.rev_parse_verify(&format!("refs/heads/{}", name_input)), |e| local_exists = e);
         if local_exists {
-            if confirm(&format!("ローカルブランチ '{}' を削除しますか？", name_input)) {
-                handle_command_result_void(GitCommand::branch_delete_local_d(&name_input));
+            let message = if force { format!("ローカルブランチ '{}' を強制削除しますか？ (未マージのコミットも失われます)", name_input) } else { format!("ローカルブランチ '{}' を削除しますか？", name_input) };
+            if confirm_with(git, &message) && delete_local_branch(git, &name_input, force) && !dry_run {
                 println!("ローカルブランチ '{}' を削除しました。", name_input.truecolor(255,165,0)); // オレンジ
             }
         } else {
             println!("ローカルブランチ '{}' は見つかりませんでした。", name_input.yellow());
         }
-        if !remote_url.is_empty() && confirm(&format!("(もし存在すれば) リモートブランチ 'origin/{}' も削除しますか？", name_input)) {
-             handle_command_result_void(GitCommand::push_delete("origin", &name_input));
-             println!("リモートブランチ 'origin/{}' の削除を試みました。", name_input.blue());
+        if !remote_url.is_empty() && confirm_with(git, &format!("(もし存在すれば) リモートブランチ '{}/{}' も削除しますか？", utils::remote(), name_input)) {
+             handle_command_result_void(git.push_delete(utils::remote(), &name_input));
+             if !dry_run { println!("リモートブランチ '{}/{}' の削除を試みました。", utils::remote(), name_input.blue()); }
         }
     }
+    if dry_run { println!("{}", "--dry-run のため、何も変更していません。".dimmed()); }
+}
+
+// フィーチャーブランチ上にいる場合は、無関係なブランチから派生しないよう最新の既定ブランチを基点として提案する
+// (既定ブランチ上、または既定ブランチが無い場合は None = 現在の位置)
+fn suggest_new_branch_base() -> Option<String> {
+    let current_branch = get_current_branch_name();
+    let default_branch = get_default_branch()?;
+    if current_branch.is_empty() || current_branch == default_branch { return None; }
+    if GitCommand::remote_get_url(utils::remote()).is_ok() && GitCommand::fetch_branch(utils::remote(), &default_branch).is_err() {
+        log::warn!("警告: '{}/{}' を取得できませんでした。手元の情報を使います。", utils::remote(), default_branch);
+    }
+    get_default_base_ref()
+}
+
+// --prefix (無ければ設定の branch_prefix) を入力したブランチ名の前に付ける。既に付いていれば重ねない
+fn apply_branch_prefix(args: &[String], name: &str) -> String {
+    if has_flag(args, &["--no-prefix"]) { return name.to_string(); }
+    let prefix = flag_value(args, &["--prefix"]).or_else(|| utils::config().branch_prefix.clone()).unwrap_or_default();
+    if prefix.is_empty() || name.starts_with(&prefix) { return name.to_string(); }
+    let prefixed = format!("{}{}", prefix, name);
+    println!("ブランチ名: {}", prefixed.cyan());
+    prefixed
 }
 
-pub fn git_create(_args: &[String]) {
+// 回答順 (パイプ入力時): 新しいブランチ名 → (フィーチャーブランチ上で --from 未指定時: 基点) → プッシュ確認
+pub fn git_create(args: &[String]) {
     let name = prompt_input("作成する新しいローカルブランチ名: ");
     if name.is_empty() { eprintln!("{}", "エラー: ブランチ名必須。".red()); exit(1); }
-    let mut exists = false;
-    handle_command_result(GitCommand::rev_parse_verify(&name), |e| exists = e);
-    if exists { eprintln!("エラー: ブランチ '{}' は既にローカルに存在します。", name.red()); exit(1); }
-    
-    handle_command_result_void(GitCommand::branch_create_local(&name));
-    println!("ローカルブランチ '{}' を作成しました。", name.truecolor(255,165,0)); // オレンジ
+    let name = apply_branch_prefix(args, &name);
+    ensure_branch_not_exists(&name);
+
+    let base = match flag_value(args, &["--from"]) {
+        Some(base) => Some(base),
+        None => suggest_new_branch_base().and_then(|suggested| {
+            let current_branch = get_current_branch_name();
+            println!("注意: 現在はフィーチャーブランチ '{}' 上です。新しいブランチは通常 '{}' から作成します。", current_branch.yellow(), suggested.cyan());
+            let selected = prompt_select("新しいブランチの基点", vec![
+                SelectOption::new(format!("{} (最新の既定ブランチ)", suggested), suggested.clone()),
+                SelectOption::new(format!("{} (現在のブランチ)", current_branch), String::new()),
+            ]);
+            Some(selected).filter(|s| !s.is_empty())
+        }),
+    };
+    match &base {
+        Some(base) => {
+            let mut base_exists = false;
+            handle_command_result(GitCommand::rev_parse_verify(&format!("{}^{{commit}}", base)), |e| base_exists = e);
+            if !base_exists { eprintln!("エラー: 基点 '{}' が無効です。", base.red()); exit(1); }
+            handle_command_result_void(GitCommand::branch_create_local_from(&name, base));
+            println!("ローカルブランチ '{}' を '{}' から作成しました。", name.truecolor(255,165,0), base.cyan()); // オレンジ
+        }
+        None => {
+            handle_command_result_void(GitCommand::branch_create_local(&name));
+            println!("ローカルブランチ '{}' を作成しました。", name.truecolor(255,165,0)); // オレンジ
+        }
+    }
 
     let mut remote_url = String::new();
-    handle_command_result(GitCommand::remote_get_url("origin"), |url| remote_url = url);
-    if !remote_url.is_empty() && confirm(&format!("作成したブランチ '{}' をリモート 'origin' にプッシュし追跡設定しますか？", name)) {
+    handle_command_result(GitCommand::remote_get_url(utils::remote()), |url| remote_url = url);
+    if !remote_url.is_empty() && confirm_with_default(&format!("作成したブランチ '{}' をリモート '{}' にプッシュし追跡設定しますか？", name, utils::remote()), true) {
+        let previous_branch = get_current_branch_name();
         handle_command_result_void(GitCommand::checkout(&name));
-        handle_command_result_void(GitCommand::push_u("origin", &name));
-        println!("ブランチ '{}' を 'origin/{}' へプッシュし追跡設定しました。", name.cyan(), name.blue());
+        handle_command_result_void(GitCommand::push_u(utils::remote(), &name));
+        println!("ブランチ '{}' を '{}/{}' へプッシュし追跡設定しました。", name.cyan(), utils::remote(), name.blue());
+        run_post_checkout(args, &previous_branch);
+    }
+}
+// 選択肢のうち「新しいブランチを作成」を表す値 (ブランチ名としては使えない文字列)
+const NEW_BRANCH_OPTION: &str = "";
+
+// 回答順 (パイプ入力時): 移動先ブランチ名 (空行で新規作成) → (新規作成時: 新しいブランチ名)
+// 最新のスタッシュのコミットID (スタッシュが無ければ None)
+fn latest_stash_id() -> Option<String> {
+    if !GitCommand::rev_parse_verify("refs/stash").unwrap_or(false) { return None; }
+    GitCommand::rev_parse_commit_id("refs/stash").ok()
+}
+
+// 自分で作ったスタッシュだけを戻す。その後に別のスタッシュが積まれていれば、取り違えないよう失敗にする
+fn pop_own_stash(stash_id: &str) -> CommandResult<bool> {
+    if latest_stash_id().as_deref() != Some(stash_id) {
+        return Err(format!("エラー: 移動用のスタッシュ ({}) が最新のスタッシュではありません。'git stash list' で確認してください。", &stash_id[..7.min(stash_id.len())]));
+    }
+    GitCommand::stash_pop_ref("stash@{0}")
+}
+
+pub fn git_move_changes(_args: &[String]) {
+    // git stash push は未追跡ファイルを含めないため、追跡中のファイルの変更だけを対象にする
+    if !has_tracked_changes() {
+        println!("{}", "移動する未コミットの変更はありません。".yellow());
+        if has_uncommitted_changes() { println!("未追跡ファイルはブランチを切り替えてもそのまま残ります。"); }
+        return;
+    }
+    let current_branch = get_current_branch_name();
+
+    let mut options: Vec<SelectOption<String>> = get_local_branch_names().into_iter()
+        .filter(|name| *name != current_branch)
+        .map(|name| SelectOption::new(name.clone(), name))
+        .collect();
+    options.push(SelectOption::new("+ 新しいブランチを作成", NEW_BRANCH_OPTION.to_string()));
+    let mut target = prompt_select("変更の移動先ブランチ", options);
+
+    let create_new = target == NEW_BRANCH_OPTION;
+    if create_new {
+        target = prompt_input("新しいブランチ名: ");
+        if target.is_empty() { eprintln!("{}", "エラー: ブランチ名必須。".red()); exit(1); }
+        let mut exists = false;
+        handle_command_result(GitCommand::rev_parse_verify(&target), |e| exists = e);
+        if exists { eprintln!("エラー: ブランチ '{}' は既に存在します。", target.red()); exit(1); }
+    }
+
+    let from = if current_branch.is_empty() { "HEAD".to_string() } else { current_branch.clone() };
+    let before = latest_stash_id();
+    handle_command_result_void(GitCommand::stash_push(Some(&format!("mygit move: {} -> {}", from, target))));
+    let Some(stash_id) = latest_stash_id().filter(|id| before.as_ref() != Some(id)) else {
+        eprintln!("{}", "エラー: スタッシュが作成されなかったため、移動を中止しました。".red()); exit(1);
+    };
+    if create_new {
+        handle_command_result_void(GitCommand::checkout_b(&target));
+    } else if let Err(err_msg) = GitCommand::checkout(&target) {
+        // 切り替えに失敗した場合は元のブランチに変更を戻す
+        eprintln!("{}", err_msg.red());
+        handle_command_result(pop_own_stash(&stash_id), |_| {});
+        exit(1);
+    }
+
+    let mut pop_success = false;
+    handle_command_result(pop_own_stash(&stash_id), |success| pop_success = success);
+    if pop_success {
+        println!("変更を '{}' から '{}' へ移動しました。", from.cyan(), target.cyan());
+    } else {
+        log::warn!("警告: ブランチ '{}' で変更を戻す際にコンフリクトが発生しました。", target);
+        log::warn!("コンフリクトを解決してください。変更はスタッシュにも残っているため、解決後に 'git stash drop' で削除してください。");
+        exit(1);
+    }
+}
+
+// `git ls-remote --tags` の出力 ("<sha>\trefs/tags/<name>") からタグ名を取り出す。注釈付きタグの "^{}" 行は除外
+fn parse_ls_remote_tags(output: &str) -> std::collections::BTreeSet<String> {
+    output.lines()
+        .filter_map(|line| line.split('\t').nth(1))
+        .filter_map(|refname| refname.strip_prefix("refs/tags/"))
+        .filter(|name| !name.ends_with("^{}"))
+        .map(|name| name.to_string())
+        .collect()
+}
+
+// 回答順 (パイプ入力時): ローカルのみのタグへの操作 (push / delete / 空行で何もしない)
+pub fn git_tags(_args: &[String]) {
+    let mut remote_url = String::new();
+    handle_command_result(GitCommand::remote_get_url(utils::remote()), |url| remote_url = url);
+    if remote_url.is_empty() { eprintln!("{}", format!("エラー: リモート '{}' が未設定。", utils::remote()).red()); exit(1); }
+
+    handle_command_result_void(GitCommand::fetch_tags(utils::remote()));
+
+    let mut local_tags = std::collections::BTreeSet::new();
+    handle_command_result(GitCommand::for_each_ref_tags(), |s| local_tags = s.lines().map(|l| l.to_string()).collect());
+    let mut remote_tags = std::collections::BTreeSet::new();
+    handle_command_result(GitCommand::ls_remote_tags(utils::remote()), |s| remote_tags = parse_ls_remote_tags(&s));
+
+    let local_only: Vec<&String> = local_tags.difference(&remote_tags).collect();
+    let remote_only: Vec<&String> = remote_tags.difference(&local_tags).collect();
+
+    if local_only.is_empty() && remote_only.is_empty() {
+        println!("{}", format!("ローカルとリモート '{}' のタグは一致しています。({} 個)", utils::remote(), local_tags.len()).green());
+        return;
+    }
+    if !remote_only.is_empty() {
+        println!("リモートのみに存在するタグ:");
+        remote_only.iter().for_each(|t| println!("  {}", t.blue()));
+    }
+    if local_only.is_empty() { return; }
+
+    println!("ローカルのみに存在するタグ:");
+    local_only.iter().for_each(|t| println!("  {}", t.truecolor(255,165,0))); // オレンジ
+
+    let action = prompt_select("ローカルのみのタグをどうしますか？", vec![
+        SelectOption::new(format!("リモート '{}' にプッシュする", utils::remote()), "push".to_string()),
+        SelectOption::new("ローカルから削除する", "delete".to_string()),
+        SelectOption::new("何もしない", String::new()),
+    ]);
+    match action.as_str() {
+        "push" => {
+            for tag in &local_only {
+                handle_command_result_void(GitCommand::push(utils::remote(), &format!("refs/tags/{}", tag)));
+            }
+            println!("{}", format!("{} 個のタグをプッシュしました。", local_only.len()).green());
+        }
+        "delete" if confirm(&format!("{} 個のローカルタグを削除しますか？", local_only.len())) => {
+            for tag in &local_only {
+                handle_command_result_void(GitCommand::tag_delete(tag));
+            }
+            println!("{}", format!("{} 個のローカルタグを削除しました。", local_only.len()).green());
+        }
+        _ => println!("タグは変更しませんでした。"),
+    }
+}
+
+const CHERRY_PICK_COMMIT_COUNT: usize = 20;
+
+// 回答順 (パイプ入力時): (コミット未指定時: コミットの sha)
+pub fn git_contains(args: &[String]) {
+    let commit = match args.get(2) {
+        Some(commit) => commit.clone(),
+        None => {
+            let options = get_commit_select_options("HEAD", 20);
+            if options.is_empty() { eprintln!("{}", "エラー: コミットがありません。".red()); exit(1); }
+            prompt_select("確認するコミット", options)
+        }
+    };
+    let mut exists = false;
+    handle_command_result(GitCommand::rev_parse_verify(&format!("{}^{{commit}}", commit)), |e| exists = e);
+    if !exists { eprintln!("エラー: コミット '{}' が見つかりません。", commit.red()); exit(1); }
+
+    let mut branches_str = String::new();
+    handle_command_result(GitCommand::branch_contains(&commit), |s| branches_str = s);
+
+    println!("コミット '{}' を含むブランチ:", commit.cyan());
+    let mut found = false;
+    for line in branches_str.lines() {
+        let trimmed_line = line.trim();
+        let is_current = trimmed_line.starts_with("* ");
+        let name = trimmed_line.trim_start_matches("* ");
+        if name.is_empty() || name.contains("->") || name.starts_with('(') { continue; }
+        found = true;
+        if let Some(remote_name) = name.strip_prefix("remotes/") {
+            println!("  {}", remote_name.blue());
+        } else if is_current {
+            println!("* {}", name.cyan().bold());
+        } else {
+            println!("  {}", name.truecolor(255,165,0)); // オレンジ
+        }
+    }
+    if !found { println!("{}", "  (このコミットを含むブランチはありません)".yellow()); }
+}
+
+pub fn git_status(_args: &[String]) {
+    let entries = get_status_entries();
+    if entries.is_empty() { println!("{}", "変更はありません。".green()); return; }
+
+    let label = |e: &utils::StatusEntry| if e.is_submodule { format!("{} {}", e.path, "(サブモジュール)".dimmed()) } else { e.path.clone() };
+    let sections: [(&str, Vec<&utils::StatusEntry>); 4] = [
+        ("コンフリクト:", entries.iter().filter(|e| e.is_conflicted).collect()),
+        ("ステージ済み:", entries.iter().filter(|e| e.is_staged()).collect()),
+        ("未ステージ:", entries.iter().filter(|e| e.is_unstaged()).collect()),
+        ("未追跡:", entries.iter().filter(|e| e.is_untracked()).collect()),
+    ];
+    for (title, section) in sections.iter() {
+        if section.is_empty() { continue; }
+        println!("{}", title.bold());
+        for e in section {
+            match *title {
+                "コンフリクト:" => println!("  {} {}", "U".red().bold(), label(e).red()),
+                "ステージ済み:" => println!("  {} {}", e.index.to_string().green(), label(e)),
+                "未ステージ:" => println!("  {} {}", e.worktree.to_string().yellow(), label(e)),
+                _ => println!("  {} {}", "?".dimmed(), label(e).dimmed()),
+            }
+        }
+    }
+    if entries.iter().any(|e| e.is_submodule) {
+        log::info!("注意: (サブモジュール) の項目はサブモジュール内の変更または参照コミットの変更です。");
+    }
+}
+
+// 作業ツリーの変更をハンク単位で破棄する (git checkout -p)
+fn restore_patch(args: &[String]) {
+    if flag_value(args, &["--from"]).is_some() {
+        eprintln!("{}", "エラー: --patch は --from と併用できません。".red()); exit(1);
+    }
+    let path = match positional_args(args, &[]).into_iter().next() {
+        Some(path) => path,
+        None => {
+            let paths: Vec<String> = get_status_entries().into_iter()
+                .filter(|e| e.is_unstaged() && !e.is_submodule).map(|e| e.path).collect();
+            if paths.is_empty() { println!("{}", "破棄できる変更はありません。".yellow()); return; }
+            prompt_select("変更を破棄するファイル", paths.into_iter().map(|p| SelectOption::new(p.clone(), p)).collect())
+        }
+    };
+    if !confirm(&format!("'{}' の変更をハンクごとに選んで破棄します。続行しますか？", path)) {
+        println!("復元をキャンセルしました。");
+        return;
+    }
+    handle_command_result_void(GitCommand::checkout_patch(&path));
+}
+
+// 回答順 (パイプ入力時): (--from 未指定時: 戻す元) → (パス未指定時: ファイル) → (ローカル変更がある場合: 上書き確認)
+//   --patch 時: (パス未指定時: ファイル) → 続行確認、以降は git の対話 UI
+pub fn git_restore(args: &[String]) {
+    if has_flag(args, &["--patch", "-p"]) {
+        restore_patch(args);
+        return;
+    }
+    let source = match flag_value(args, &["--from"]) {
+        Some(source) => source,
+        None => {
+            let current_branch = get_current_branch_name();
+            let mut options = vec![SelectOption::new("HEAD (最後のコミット)", "HEAD".to_string())];
+            options.extend(get_local_branch_names().into_iter()
+                .filter(|name| *name != current_branch)
+                .map(|name| SelectOption::new(format!("ブランチ: {}", name), name)));
+            options.extend(get_commit_select_options("HEAD", 10).into_iter()
+                .map(|o| SelectOption::new(format!("コミット: {}", o.label), o.value)));
+            prompt_select("ファイルを戻す元", options)
+        }
+    };
+    let mut source_exists = false;
+    handle_command_result(GitCommand::rev_parse_verify(&format!("{}^{{commit}}", source)), |e| source_exists = e);
+    if !source_exists { eprintln!("エラー: '{}' はブランチまたはコミットとして無効です。", source.red()); exit(1); }
+
+    let entries = get_status_entries();
+    let path = match positional_args(args, &["--from"]).into_iter().next() {
+        Some(path) => path,
+        None => {
+            // HEAD からの復元は変更済みファイル、それ以外は戻す元のファイル一覧から選ぶ
+            let paths: Vec<String> = if source == "HEAD" {
+                entries.iter().filter(|e| !e.is_untracked() && !e.is_submodule).map(|e| e.path.clone()).collect()
+            } else {
+                let mut files = String::new();
+                handle_command_result(GitCommand::ls_tree_files(&source), |s| files = s);
+                files.lines().map(|l| l.to_string()).collect()
+            };
+            if paths.is_empty() { println!("{}", "戻せるファイルはありません。".yellow()); return; }
+            prompt_select("戻すファイル", paths.into_iter().map(|p| SelectOption::new(p.clone(), p)).collect())
+        }
+    };
+
+    if entries.iter().any(|e| e.path == path && !e.is_untracked())
+        && !confirm(&format!("'{}' のローカルの変更は失われます。続行しますか？", path)) {
+        println!("復元をキャンセルしました。");
+        return;
+    }
+    handle_command_result_void(GitCommand::checkout_file_from(&source, &path));
+    println!("'{}' を '{}' の内容に戻しました。", path.cyan(), source.cyan());
+}
+
+// (stash@{n}, メッセージ) の一覧 (新しい順)
+fn stash_entries_with(git: &dyn GitCommandTrait) -> Vec<(String, String)> {
+    let mut list_str = String::new();
+    handle_command_result(git.stash_list(), |s| list_str = s);
+    list_str.lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(stash_ref, message)| (stash_ref.to_string(), message.to_string()))
+        .collect()
+}
+
+fn get_stash_select_options() -> Vec<SelectOption<String>> {
+    stash_entries_with(&GitCommand).into_iter()
+        .map(|(stash_ref, message)| SelectOption::new(format!("{} {}", stash_ref, message), stash_ref))
+        .collect()
+}
+
+// 古いスタッシュが今の状態とどれだけ違うかを、適用前に確認する
+// 回答順 (パイプ入力時): スタッシュ (stash@{n})
+fn git_stash_diff(args: &[String]) {
+    let options = get_stash_select_options();
+    if options.is_empty() { println!("{}", "スタッシュはありません。".yellow()); return; }
+    let stash = prompt_select("比較するスタッシュ", options);
+    let base = has_flag(args, &["--head"]).then_some("HEAD");
+    // git diff <stash> はスタッシュ → 作業ツリー、git diff HEAD <stash> は HEAD → スタッシュの向きの差分
+    let label = match base {
+        Some(base) => format!("{} → {}", base, stash),
+        None => format!("{} → 作業ツリー", stash),
+    };
+    println!("{}", format!("差分 ({}):", label).dimmed());
+    handle_command_result_void(GitCommand::diff_stash(&stash, base));
+}
+
+// 新しいブランチ名として使えるか (形式が正しく、まだ存在しないか) を確認する
+fn ensure_branch_not_exists(name: &str) {
+    if !GitCommand::check_ref_format_branch(name).unwrap_or(false) {
+        eprintln!("エラー: '{}' はブランチ名として無効です。", name.red()); exit(1);
+    }
+    if GitCommand::rev_parse_verify(&format!("refs/heads/{}", name)).unwrap_or(false) {
+        eprintln!("エラー: ブランチ '{}' は既にローカルに存在します。", name.red()); exit(1);
+    }
+}
+
+// stash list の表示行。スタッシュが無ければエラーにせず、その旨のメッセージだけを返す
+fn stash_list_lines_with(git: &dyn GitCommandTrait) -> Vec<String> {
+    let entries = stash_entries_with(git);
+    if entries.is_empty() { return vec!["スタッシュはありません。".yellow().to_string()]; }
+    std::iter::once("スタッシュ一覧 (新しい順):".bold().to_string())
+        .chain(entries.iter().map(|(stash_ref, message)| format!("  {} {}", stash_ref.cyan(), message)))
+        .collect()
+}
+
+fn git_stash_list() {
+    for line in stash_list_lines_with(&GitCommand) { println!("{}", line); }
+}
+
+// 回答順 (パイプ入力時): (メッセージ未指定時: メッセージ)
+fn git_stash_push(git: &dyn GitCommandTrait, message: Option<String>) {
+    // git stash push は未追跡ファイルを退避しない
+    if !has_tracked_changes_with(git) { println!("{}", "退避する変更はありません。(未追跡ファイルは対象外です)".yellow()); return; }
+    let message = message.unwrap_or_else(|| prompt_input("スタッシュのメッセージ (空でスキップ): "));
+    handle_command_result_void(git.stash_push(Some(&message).filter(|m| !m.is_empty()).map(|m| m.as_str())));
+    println!("{}", "変更を退避しました。'mygit stash pop' で戻せます。".green());
+}
+
+// 回答順 (パイプ入力時): スタッシュ (stash@{n}) → 削除確認
+fn git_stash_drop() {
+    let options = get_stash_select_options();
+    if options.is_empty() { println!("{}", "スタッシュはありません。".yellow()); return; }
+    let stash = prompt_select("削除するスタッシュ", options);
+    if !confirm(&format!("スタッシュ '{}' を削除しますか？ (元に戻せません)", stash)) { println!("キャンセルしました。"); return; }
+    handle_command_result_void(GitCommand::stash_drop(&stash));
+    println!("{}", format!("スタッシュ '{}' を削除しました。", stash).green());
+}
+
+// 回答順 (パイプ入力時): (branch でブランチ名未指定時: 新しいブランチ名 →) スタッシュ (stash@{n}) → (--show 時: 適用確認)
+pub fn git_stash(args: &[String]) {
+    let git: &dyn GitCommandTrait = &GitCommand;
+    let positionals = positional_args(args, &[]);
+    let subcommand = positionals.first().cloned().unwrap_or_else(|| "list".to_string());
+    match subcommand.as_str() {
+        "list" => return git_stash_list(),
+        "push" | "save" => return git_stash_push(git, positionals.get(1).cloned()),
+        "drop" => return git_stash_drop(),
+        "diff" => return git_stash_diff(args),
+        "pop" | "apply" | "branch" => {}
+        _ => {
+            eprintln!("{} {} stash {{push [<message>]|list|pop|apply|drop|branch [<name>]|diff [--head]}} [--show]", "Usage:".bold(), "mygit".green());
+            exit(1);
+        }
+    }
+    let new_branch = (subcommand == "branch").then(|| {
+        let name = positionals.get(1).cloned().unwrap_or_else(|| prompt_input("作成するブランチ名: "));
+        if name.is_empty() { eprintln!("{}", "エラー: ブランチ名必須。".red()); exit(1); }
+        ensure_branch_not_exists(&name);
+        name
+    });
+
+    let options = get_stash_select_options();
+    if options.is_empty() { println!("{}", "スタッシュはありません。".yellow()); return; }
+    let stash = prompt_select(&format!("{} するスタッシュ", subcommand), options);
+
+    if has_flag(args, &["--show"]) {
+        handle_command_result_void(GitCommand::stash_show(&stash));
+        if !confirm(&format!("このスタッシュを {} しますか？", subcommand)) {
+            println!("スタッシュは適用しませんでした。");
+            return;
+        }
+    }
+
+    let mut success = false;
+    let result = match &new_branch {
+        Some(name) => GitCommand::stash_branch(name, &stash),
+        None if subcommand == "pop" => git.stash_pop(&stash),
+        None => GitCommand::stash_apply(&stash),
+    };
+    handle_command_result(result, |s| success = s);
+    if let Some(name) = new_branch.filter(|_| success) {
+        println!("{}", format!("スタッシュ作成時のコミットからブランチ '{}' を作成し、スタッシュ '{}' を適用しました。", name, stash).green());
+    } else if success {
+        println!("{}", format!("スタッシュ '{}' を適用しました。", stash).green());
+    } else {
+        log::warn!("警告: スタッシュ '{}' の適用でコンフリクトが発生しました。", stash);
+        log::warn!("コンフリクトを解決してください。スタッシュは一覧に残っています。");
+        exit(1);
+    }
+}
+
+// これを超える数のルーズオブジェクトがある場合に git gc を提案する
+const LOOSE_OBJECT_GC_THRESHOLD: u64 = 1000;
+
+// 回答順 (パイプ入力時): (ルーズオブジェクトが多い場合: gc 実行確認)
+pub fn git_size(_args: &[String]) {
+    let mut output = String::new();
+    handle_command_result(GitCommand::count_objects(), |s| output = s);
+    let values: std::collections::HashMap<&str, &str> = output.lines()
+        .filter_map(|line| line.split_once(": "))
+        .collect();
+    let get = |key: &str| values.get(key).copied().unwrap_or("0");
+
+    println!("{}", "リポジトリのサイズ:".bold());
+    println!("  ルーズオブジェクト: {} 個 ({})", get("count").cyan(), get("size"));
+    println!("  パック済みオブジェクト: {} 個 ({}, パック {} 個)", get("in-pack").cyan(), get("size-pack"), get("packs"));
+    if get("prune-packable") != "0" {
+        println!("  パック済みの重複: {} 個", get("prune-packable").yellow());
+    }
+    if get("garbage") != "0" {
+        println!("  不要ファイル: {} 個 ({})", get("garbage").yellow(), get("size-garbage"));
+    }
+
+    let loose_count: u64 = get("count").parse().unwrap_or(0);
+    if loose_count > LOOSE_OBJECT_GC_THRESHOLD {
+        println!("{}", format!("ルーズオブジェクトが {} 個あります。git gc で圧縮できます。", loose_count).yellow());
+        if confirm("git gc を実行しますか？") {
+            handle_command_result_void(GitCommand::gc());
+            println!("{}", "git gc が完了しました。".green());
+        }
+    }
+}
+
+pub fn git_unpushed(_args: &[String]) {
+    let mut refs_str = String::new();
+    handle_command_result(GitCommand::for_each_ref_local_upstreams(), |s| refs_str = s);
+
+    // 上流が無いブランチは既定ブランチ (リモート側があればそちら) との差分で数える
+    let default_base = get_default_base_ref();
+
+    let mut found = false;
+    for line in refs_str.lines() {
+        let (branch, upstream) = line.split_once('\t').unwrap_or((line, ""));
+        let (base, note) = if !upstream.is_empty() {
+            (upstream.to_string(), format!("({} に未プッシュ)", upstream).dimmed().to_string())
+        } else {
+            match &default_base {
+                Some(base) if base != branch => (base.clone(), format!("(上流なし、{} との比較)", base).yellow().to_string()),
+                _ => continue,
+            }
+        };
+        let count = GitCommand::rev_list_count(&format!("{}..{}", base, branch)).unwrap_or(0);
+        if count == 0 { continue; }
+        found = true;
+        println!("  {} {} {}", branch.truecolor(255,165,0), format!("↑{}", count).bold(), note); // オレンジ
+    }
+    if !found { println!("{}", "未プッシュのコミットはありません。".green()); }
+}
+
+pub fn git_repo(args: &[String]) {
+    let sub: Vec<String> = positional_args(args, &[]);
+    match sub.iter().map(|s| s.as_str()).collect::<Vec<_>>().as_slice() {
+        ["remote", "test"] => git_repo_remote_test(),
+        ["remote", "fix-head"] => git_repo_remote_fix_head(),
+        ["remote", "logout"] => git_repo_remote_logout(),
+        ["config", "pull-mode", rest @ ..] => git_repo_config_pull_mode(rest.first().copied(), has_flag(args, &["--global"])),
+        ["sparse", rest @ ..] => git_repo_sparse(rest),
+        // 削除は repo では扱わない (--dry-run 付きの削除は delete コマンドで行う)
+        ["delete", ..] => {
+            eprintln!("{}", "エラー: repo delete はありません。ブランチの削除は 'mygit delete' (--dry-run で内容の確認のみ) を使ってください。".red());
+            exit(1);
+        }
+        _ => {
+            eprintln!("{} {} repo {{remote test|remote fix-head|remote logout|config pull-mode [merge|rebase|ff-only] [--global]|sparse [list|add|remove|disable] [<dir>...]}}", "Usage:".bold(), "mygit".green());
+            exit(1);
+        }
+    }
+}
+
+fn sparse_checkout_enabled() -> bool {
+    GitCommand::config_get("core.sparseCheckout").is_ok_and(|v| v == "true")
+}
+
+fn get_sparse_dirs() -> Vec<String> {
+    let mut list_str = String::new();
+    handle_command_result(GitCommand::sparse_checkout_list(), |s| list_str = s);
+    list_str.lines().map(|l| l.to_string()).collect()
+}
+
+fn print_sparse_dirs() {
+    let dirs = get_sparse_dirs();
+    println!("{}", "展開中のディレクトリ (ルート直下のファイルは常に展開されます):".bold());
+    if dirs.is_empty() { println!("  {}", "(ルートのみ)".dimmed()); }
+    dirs.iter().for_each(|d| println!("  {}", d.cyan()));
+}
+
+// 回答順 (パイプ入力時): (無効の場合: 有効化確認) → (add/remove でディレクトリ未指定時: ディレクトリ (空白区切り))
+fn git_repo_sparse(rest: &[&str]) {
+    let action = rest.first().copied().unwrap_or("list");
+    let dirs: Vec<String> = rest.iter().skip(1).map(|d| d.trim_end_matches('/').to_string()).collect();
+    if action == "disable" {
+        if !sparse_checkout_enabled() { println!("スパースチェックアウトは有効ではありません。"); return; }
+        handle_command_result_void(GitCommand::sparse_checkout_disable());
+        println!("{}", "スパースチェックアウトを無効にし、すべてのファイルを展開しました。".green());
+        return;
+    }
+    if !["list", "add", "remove"].contains(&action) {
+        eprintln!("エラー: 不明なサブコマンド '{}'", action.red());
+        eprintln!("{} {} repo sparse [list|add|remove|disable] [<dir>...]", "Usage:".bold(), "mygit".green());
+        exit(1);
+    }
+
+    if !sparse_checkout_enabled() {
+        println!("スパースチェックアウトは有効ではありません (全ファイルを展開中)。");
+        if !confirm("スパースチェックアウト (cone モード) を有効にしますか？ ルート直下のファイル以外は作業ツリーから外れます") {
+            return;
+        }
+        handle_command_result_void(GitCommand::sparse_checkout_init());
+        println!("{}", "スパースチェックアウトを有効にしました。".green());
+    }
+
+    match action {
+        "add" => {
+            let dirs = if !dirs.is_empty() { dirs } else {
+                let current = get_sparse_dirs();
+                let mut tree_dirs = String::new();
+                handle_command_result(GitCommand::ls_tree_dirs("HEAD"), |s| tree_dirs = s);
+                let options: Vec<MultiSelectOption<String>> = tree_dirs.lines()
+                    .filter(|d| !current.iter().any(|c| c == d))
+                    .map(|d| MultiSelectOption::new(d, d.to_string()))
+                    .collect();
+                if options.is_empty() { println!("{}", "追加できるディレクトリはありません。".yellow()); return; }
+                prompt_multi_select("展開するディレクトリ", options)
+            };
+            if dirs.is_empty() { println!("ディレクトリは追加しませんでした。"); return; }
+            handle_command_result_void(GitCommand::sparse_checkout_add(&dirs));
+            println!("{}", format!("{} 個のディレクトリを展開しました。", dirs.len()).green());
+        }
+        "remove" => {
+            let current = get_sparse_dirs();
+            let dirs = if !dirs.is_empty() { dirs } else {
+                if current.is_empty() { println!("{}", "外せるディレクトリはありません。".yellow()); return; }
+                prompt_multi_select("作業ツリーから外すディレクトリ", current.iter().map(|d| MultiSelectOption::new(d, d.clone())).collect())
+            };
+            if let Some(unknown) = dirs.iter().find(|d| !current.contains(d)) {
+                eprintln!("エラー: '{}' は展開中のディレクトリではありません。", unknown.red()); exit(1);
+            }
+            if dirs.is_empty() { println!("ディレクトリは外しませんでした。"); return; }
+            let remaining: Vec<String> = current.into_iter().filter(|d| !dirs.contains(d)).collect();
+            handle_command_result_void(GitCommand::sparse_checkout_set(&remaining));
+            println!("{}", format!("{} 個のディレクトリを作業ツリーから外しました。", dirs.len()).green());
+        }
+        _ => {}
+    }
+    print_sparse_dirs();
+}
+
+fn git_repo_remote_fix_head() {
+    if GitCommand::remote_get_url(utils::remote()).is_err() {
+        eprintln!("{}", format!("エラー: リモート '{}' が未設定です。setup コマンドで設定してください。", utils::remote()).red()); exit(1);
+    }
+    let before = GitCommand::symbolic_ref_remote_head(utils::remote()).ok();
+    let broken = before.as_ref().is_some_and(|head| !GitCommand::rev_parse_verify(&format!("refs/remotes/{}", head)).unwrap_or(false));
+    match &before {
+        Some(head) if broken => println!("現在の {}/HEAD: {} {}", utils::remote(), head.red(), "(存在しないブランチ)".red()),
+        Some(head) => println!("現在の {}/HEAD: {}", utils::remote(), head.cyan()),
+        None => println!("現在の {}/HEAD: {}", utils::remote(), "未設定".yellow()),
+    }
+    log::info!("リモートの既定ブランチを問い合わせています...");
+    handle_command_result_void(GitCommand::remote_set_head_auto(utils::remote()));
+    let mut after = String::new();
+    handle_command_result(GitCommand::symbolic_ref_remote_head(utils::remote()), |head| after = head);
+    if before.as_deref() == Some(after.as_str()) && !broken {
+        println!("{}", format!("{}/HEAD は既に正しく '{}' を指しています。", utils::remote(), after).green());
+    } else {
+        println!("{}", format!("{}/HEAD を '{}' に設定しました。", utils::remote(), after).green());
+    }
+    println!("既定ブランチ: {}", after.trim_start_matches(&format!("{}/", utils::remote())).cyan().bold());
+}
+
+// git pull の動作 → (pull.rebase, pull.ff, 説明)。名前は update_strategy と共通
+fn pull_mode_settings(mode: utils::UpdateStrategy) -> (&'static str, &'static str, &'static str) {
+    match mode {
+        utils::UpdateStrategy::Merge => ("false", "true", "分岐していればマージコミットを作って取り込みます"),
+        utils::UpdateStrategy::Rebase => ("true", "true", "ローカルのコミットをリモートの先端の上に積み直します"),
+        utils::UpdateStrategy::FfOnly => ("false", "only", "fast-forward できる場合のみ取り込み、分岐していれば失敗します"),
+    }
+}
+
+// 回答順 (パイプ入力時): (モード未指定時: モード)
+fn git_repo_config_pull_mode(mode: Option<&str>, global: bool) {
+    let pull_rebase = GitCommand::config_get("pull.rebase").ok();
+    let pull_ff = GitCommand::config_get("pull.ff").ok();
+    let current = match (pull_rebase.as_deref(), pull_ff.as_deref()) {
+        (_, Some("only")) => Some(utils::UpdateStrategy::FfOnly),
+        (Some("true" | "merges" | "interactive" | "i" | "m"), _) => Some(utils::UpdateStrategy::Rebase),
+        (Some(_), _) | (_, Some(_)) => Some(utils::UpdateStrategy::Merge),
+        (None, None) => None,
+    };
+    println!("pull.rebase = {}, pull.ff = {}", pull_rebase.as_deref().unwrap_or("(未設定)").cyan(), pull_ff.as_deref().unwrap_or("(未設定)").cyan());
+    match current {
+        Some(mode) => println!("現在の git pull の動作: {} ({})", mode.as_str().bold(), pull_mode_settings(mode).2),
+        None => println!("現在の git pull の動作: {}", "未設定 (分岐している場合、git はマージかリベースかを選ぶよう求めて失敗します)".yellow()),
+    }
+
+    let mode = match mode {
+        Some(value) => value.to_string(),
+        None => {
+            let options = utils::UpdateStrategy::ALL.iter()
+                .map(|m| SelectOption::new(m.as_str(), m.as_str().to_string()).with_hint(pull_mode_settings(*m).2))
+                .collect();
+            prompt_select("設定する git pull の動作", options)
+        }
+    };
+    let Some(mode) = utils::UpdateStrategy::parse(&mode) else {
+        eprintln!("エラー: 不明なモード '{}' (merge / rebase / ff-only)", mode.red()); exit(1);
+    };
+    let (rebase, ff, description) = pull_mode_settings(mode);
+    handle_command_result_void(GitCommand::config_set("pull.rebase", rebase, global));
+    handle_command_result_void(GitCommand::config_set("pull.ff", ff, global));
+    let scope = if global { "全体 (--global) の" } else { "このリポジトリの" };
+    println!("{}", format!("{} git pull を '{}' に設定しました (pull.rebase = {}, pull.ff = {})。", scope, mode.as_str(), rebase, ff).green());
+    println!("  {}", description);
+    if utils::config().update_strategy.is_some_and(|s| s != mode) {
+        println!("{}", format!("mygit の取り込み方法 (update_strategy) は別の設定です。揃えるには 'mygit strategy {}' を実行してください。", mode.as_str()).dimmed());
+    }
+}
+
+fn git_repo_remote_logout() {
+    let url = match GitCommand::remote_get_url(utils::remote()) {
+        Ok(url) if !url.is_empty() => url,
+        _ => { eprintln!("{}", format!("エラー: リモート '{}' が未設定です。setup コマンドで設定してください。", utils::remote()).red()); exit(1); }
+    };
+    let Some(remote) = utils::parse_remote_url(&url) else {
+        println!("{}", format!("{} はローカルパスのため、資格情報は使われていません。", utils::remote()).yellow()); return;
+    };
+    if remote.protocol == utils::RemoteProtocol::Ssh {
+        println!("{}", format!("{} ({}) は SSH 接続のため、git の資格情報キャッシュは使われていません。", utils::remote(), remote.host).yellow());
+        println!("  - 使う鍵を切り替えるには ~/.ssh/config の Host {} の IdentityFile を変更してください。", remote.host);
+        println!("  - エージェントに登録済みの鍵は 'ssh-add -l' で確認、'ssh-add -D' で全て削除できます。");
+        return;
+    }
+
+    let helpers = GitCommand::config_get_all("credential.helper").unwrap_or_default();
+    if helpers.is_empty() {
+        println!("{}", "資格情報ヘルパー (credential.helper) は未設定です。保存された資格情報はありません。".yellow());
+        return;
+    }
+    println!("資格情報ヘルパー: {}", helpers.join(", ").cyan());
+    handle_command_result_void(GitCommand::credential_reject(&url));
+    println!("{}", format!("'{}' の資格情報を各ヘルパーから削除しました。", remote.host).green());
+    if helpers.iter().any(|h| h.split_whitespace().next() == Some("cache")) {
+        handle_command_result_void(GitCommand::credential_cache_exit());
+        println!("{}", "credential-cache を終了し、メモリ上の資格情報をすべて破棄しました。".green());
+    }
+    println!("次回のフェッチ/プッシュ時に、改めて認証情報を入力してください。");
+}
+
+fn git_repo_remote_test() {
+    let url = match GitCommand::remote_get_url(utils::remote()) {
+        Ok(url) if !url.is_empty() => url,
+        _ => { eprintln!("{}", format!("エラー: リモート '{}' が未設定です。setup コマンドで設定してください。", utils::remote()).red()); exit(1); }
+    };
+    println!("リモート '{}': {}", utils::remote(), url.cyan());
+
+    match utils::parse_remote_url(&url) {
+        Some(remote) if remote.protocol == utils::RemoteProtocol::Ssh => {
+            log::info!("SSH 接続を確認しています ({})...", remote.host);
+            let mut result = (false, String::new());
+            handle_command_result(utils::test_ssh_connection(&remote), |r| result = r);
+            let (authenticated, message) = result;
+            if !message.is_empty() { println!("{}", message.dimmed()); }
+            if authenticated {
+                println!("{}", format!("SSH 認証に成功しました ({})。", remote.host).green());
+            } else {
+                eprintln!("{}", format!("SSH 認証に失敗しました ({})。", remote.host).red());
+                eprintln!("  - 'ssh-add -l' で SSH 鍵がエージェントに登録されているか確認してください。");
+                eprintln!("  - 鍵が無い場合は 'ssh-keygen -t ed25519' で作成し、公開鍵を {} に登録してください。", remote.host);
+                eprintln!("  - ~/.ssh/config の Host 設定 (IdentityFile など) を確認してください。");
+                exit(1);
+            }
+        }
+        parsed => {
+            // HTTPS などは ls-remote で実際に参照を取得できるか確認する
+            if let Some(remote) = &parsed { log::info!("接続を確認しています ({})...", remote.host); }
+            match GitCommand::ls_remote_heads(utils::remote()) {
+                Ok(_) => println!("{}", "リモートへの接続と認証に成功しました。".green()),
+                Err(err_msg) => {
+                    eprintln!("{}", err_msg.red());
+                    if parsed.as_ref().is_some_and(|r| r.protocol == utils::RemoteProtocol::Https) {
+                        eprintln!("  - パスワードの代わりにパーソナルアクセストークンが必要なホストがあります。");
+                        eprintln!("  - 'git config --get credential.helper' で認証情報ヘルパーの設定を確認してください。");
+                    }
+                    eprintln!("  - URL が正しいか、リポジトリへのアクセス権があるか確認してください。");
+                    exit(1);
+                }
+            }
+        }
+    }
+}
+
+// 回答順 (パイプ入力時): (プッシュ済みの場合: 続行確認) → まとめる確認 → コミットメッセージ (空行で最初のコミットの件名)
+pub fn git_collapse(_args: &[String]) {
+    let current_branch = get_current_branch_name();
+    if current_branch.is_empty() { eprintln!("{}", "エラー: 現在のブランチ不明。".red()); exit(1); }
+    let Some(default_base) = get_default_base_ref() else {
+        exit_no_default_branch("");
+    };
+    if default_base.trim_start_matches(&format!("{}/", utils::remote())) == current_branch {
+        eprintln!("エラー: 既定ブランチ '{}' 上ではまとめられません。", current_branch.red()); exit(1);
+    }
+    if has_uncommitted_changes() {
+        eprintln!("{}", "エラー: 未コミットの変更があります。先に save するか退避してください。".red()); exit(1);
+    }
+
+    let mut base = String::new();
+    handle_command_result(GitCommand::merge_base("HEAD", &default_base), |b| base = b);
+    let mut count = 0;
+    handle_command_result(GitCommand::rev_list_count(&format!("{}..HEAD", base)), |c| count = c);
+    if count < 2 { println!("{}", format!("'{}' 以降のコミットが {} 個のため、まとめる必要はありません。", default_base, count).yellow()); return; }
+
+    let mut pushed = false;
+    handle_command_result(GitCommand::rev_parse_verify(&format!("refs/remotes/{}/{}", utils::remote(), current_branch)), |e| pushed = e);
+    if pushed && !confirm(&format!("警告: '{}' は既にプッシュされています。まとめた後は force push が必要です。続行しますか？", current_branch).yellow().to_string()) {
+        println!("キャンセルしました。");
+        return;
+    }
+    if !confirm(&format!("'{}' の {} 個のコミット ('{}' からの分岐以降) を1つにまとめますか？", current_branch, count, default_base)) {
+        println!("キャンセルしました。");
+        return;
     }
-}
\ No newline at end of file
+
+    let mut subjects_str = String::new();
+    handle_command_result(GitCommand::log_subjects(&format!("{}..HEAD", base)), |s| subjects_str = s);
+    let subjects: Vec<&str> = subjects_str.lines().collect();
+    let body = subjects.iter().map(|s| format!("* {}", s)).collect::<Vec<_>>().join("\n");
+    println!("まとめるコミット:\n{}", body.dimmed());
+    let subject = prompt_input_with_default("コミットメッセージ: ", subjects.first().copied().unwrap_or(""));
+    if subject.trim().is_empty() { eprintln!("{}", "エラー: メッセージ必須。".red()); exit(1); }
+
+    let mut original_head = String::new();
+    handle_command_result(GitCommand::rev_parse_commit_id("HEAD"), |id| original_head = id);
+    handle_command_result_void(GitCommand::reset_soft(&base));
+    if let Err(err_msg) = GitCommand::commit_with_body(&subject, &body) {
+        // コミットに失敗した場合は元の状態に戻す
+        eprintln!("{}", err_msg.red());
+        handle_command_result_void(GitCommand::reset_soft(&original_head));
+        log::warn!("コミットに失敗したため、ブランチを元の状態に戻しました。");
+        exit(1);
+    }
+    println!("{}", format!("{} 個のコミットを1つにまとめました。", count).green());
+    if pushed { println!("{}", "リモートへの反映には 'git push --force-with-lease' が必要です。".yellow()); }
+}
+
+// 回答順 (パイプ入力時): (コミット未指定時: 取り込み元ブランチ → コミット)
+pub fn git_cherry_pick(args: &[String]) {
+    let commit = match positional_args(args, &[]).into_iter().next() {
+        Some(commit) => commit,
+        None => {
+            let current_branch = get_current_branch_name();
+            let branches: Vec<SelectOption<String>> = get_local_branch_names().into_iter()
+                .filter(|name| *name != current_branch)
+                .map(|name| SelectOption::new(name.clone(), name))
+                .collect();
+            if branches.is_empty() { println!("{}", "取り込み元にできる他のブランチがありません。".yellow()); return; }
+            let source = prompt_select("取り込み元のブランチ", branches);
+            let commits = get_commit_select_options(&source, CHERRY_PICK_COMMIT_COUNT);
+            if commits.is_empty() { println!("{}", format!("'{}' にコミットがありません。", source).yellow()); return; }
+            prompt_select("取り込むコミット", commits)
+        }
+    };
+    let mut exists = false;
+    handle_command_result(GitCommand::rev_parse_verify(&format!("{}^{{commit}}", commit)), |e| exists = e);
+    if !exists { eprintln!("エラー: コミット '{}' が見つかりません。", commit.red()); exit(1); }
+
+    let no_commit = has_flag(args, &["--no-commit", "-n"]);
+    let mut success = false;
+    let result = if no_commit { GitCommand::cherry_pick_no_commit(&commit) } else { GitCommand::cherry_pick(&commit) };
+    handle_command_result(result, |s| success = s);
+
+    match (success, no_commit) {
+        (true, true) => {
+            println!("{}", format!("'{}' の変更を作業ツリーに適用しました (未コミット)。", commit).green());
+            println!("内容を編集したら save でコミットしてください。");
+        }
+        (true, false) => println!("{}", format!("'{}' を取り込みました。", commit).green()),
+        (false, true) => {
+            log::warn!("警告: '{}' の適用でコンフリクトが発生しました。", commit);
+            log::warn!("コンフリクトを解決してから save でコミットしてください。取り消す場合は 'git reset --merge' を実行してください。");
+            exit(1);
+        }
+        (false, false) => {
+            log::warn!("警告: '{}' の取り込みでコンフリクトが発生しました。", commit);
+            report_rerere();
+            log::warn!("解決後に 'git cherry-pick --continue'、中止する場合は 'git cherry-pick --abort' を実行してください。");
+            exit(1);
+        }
+    }
+}
+
+// 回答順 (パイプ入力時): (ブランチ未指定時: 比較するブランチ)
+pub fn git_conflict_check(args: &[String]) {
+    let current_branch = get_current_branch_name();
+    let target = match positional_args(args, &[]).into_iter().next() {
+        Some(target) => target,
+        None => {
+            let options: Vec<SelectOption<String>> = get_local_branch_names().into_iter()
+                .filter(|name| *name != current_branch)
+                .map(|name| SelectOption::new(name.clone(), name))
+                .collect();
+            if options.is_empty() { println!("{}", "比較できる他のブランチがありません。".yellow()); return; }
+            prompt_select("マージを試すブランチ", options)
+        }
+    };
+    let mut exists = false;
+    handle_command_result(GitCommand::rev_parse_verify(&format!("{}^{{commit}}", target)), |e| exists = e);
+    if !exists { eprintln!("エラー: ブランチ '{}' は存在せず。", target.red()); exit(1); }
+
+    let mut result = (true, Vec::new());
+    handle_command_result(GitCommand::merge_tree("HEAD", &target), |r| result = r);
+    let (clean, files) = result;
+    if clean {
+        println!("{}", format!("'{}' をマージしてもコンフリクトなし。", target).green());
+    } else {
+        println!("{}", format!("'{}' をマージすると、次のファイルでコンフリクトします:", target).yellow());
+        files.iter().for_each(|f| println!("  {}", f.red()));
+    }
+}
+
+// config get/set でキーを省略した場合に選択肢として出す、よく使う設定キー
+const COMMON_CONFIG_KEYS: &[(&str, &str)] = &[
+    ("user.name", "コミットの作成者名"),
+    ("user.email", "コミットの作成者メールアドレス"),
+    ("core.editor", "コミットメッセージなどを編集するエディタ"),
+    ("init.defaultBranch", "git init で作られる最初のブランチ名"),
+    ("pull.rebase", "pull 時にマージではなくリベースする (true/false)"),
+    ("pull.ff", "pull 時の fast-forward の扱い (only など)"),
+    ("push.autoSetupRemote", "初回 push 時に自動で追跡設定する (true/false)"),
+    ("fetch.prune", "fetch 時に削除されたリモートブランチを整理する (true/false)"),
+    ("rerere.enabled", "コンフリクトの解決を記録して再利用する (true/false)"),
+    ("merge.conflictStyle", "コンフリクト表示の形式 (merge/diff3/zdiff3)"),
+    ("core.autocrlf", "改行コードの自動変換 (true/false/input)"),
+    ("commit.gpgsign", "コミットに署名する (true/false)"),
+];
+
+fn prompt_config_key() -> String {
+    let options = COMMON_CONFIG_KEYS.iter()
+        .map(|(key, hint)| SelectOption::new(*key, key.to_string()).with_hint(*hint))
+        .collect();
+    prompt_select("設定キー", options)
+}
+
+// 回答順 (パイプ入力時): (キー省略時: 設定キー) → (set で値省略時: 新しい値)
+pub fn git_config(args: &[String]) {
+    let global = has_flag(args, &["--global"]);
+    let scope = if global { "グローバル" } else { "このリポジトリ" };
+    let positionals = positional_args(args, &[]);
+    match positionals.first().map(|s| s.as_str()) {
+        None | Some("list") => {
+            let mut list_str = String::new();
+            handle_command_result(GitCommand::config_list(global), |s| list_str = s);
+            // 同じキーは後の行 (優先度の高い設定) で上書きし、有効な値だけを表示する
+            let entries: std::collections::BTreeMap<&str, &str> = list_str.lines()
+                .map(|line| line.split_once('=').unwrap_or((line, "")))
+                .collect();
+            if entries.is_empty() { println!("{}", "設定はありません。".yellow()); return; }
+            let mut last_section = "";
+            for (key, value) in entries {
+                let section = key.split('.').next().unwrap_or("");
+                if section != last_section { println!("{}", format!("[{}]", section).bold()); last_section = section; }
+                println!("  {} = {}", key.cyan(), value);
+            }
+        }
+        Some("get") => {
+            let key = positionals.get(1).cloned().unwrap_or_else(prompt_config_key);
+            let value = if global { GitCommand::config_get_global(&key) } else { GitCommand::config_get(&key) };
+            match value {
+                Ok(value) => println!("{} = {}", key.cyan(), value),
+                Err(_) => println!("{} {}", key.cyan(), "(未設定)".dimmed()),
+            }
+        }
+        Some("set") => {
+            let key = positionals.get(1).cloned().unwrap_or_else(prompt_config_key);
+            let current = if global { GitCommand::config_get_global(&key) } else { GitCommand::config_get(&key) };
+            if let Ok(current) = &current { println!("現在の値: {}", current.cyan()); }
+            let value = match positionals.get(2) {
+                Some(value) => value.clone(),
+                None => prompt_input(&format!("{} の新しい値: ", key)),
+            };
+            if value.is_empty() { eprintln!("{}", "エラー: 値必須。".red()); exit(1); }
+            handle_command_result_void(GitCommand::config_set(&key, &value, global));
+            println!("{}の設定 '{}' を '{}' にしました。", scope, key.cyan(), value.green());
+        }
+        Some(other) => {
+            eprintln!("エラー: 不明なサブコマンド '{}'", other.red());
+            eprintln!("{} {} config {{list|get|set}} [<key> [<value>]] [--global]", "Usage:".bold(), "mygit".green());
+            exit(1);
+        }
+    }
+}
+
+// Conventional Commits の種類と見出し (この順に出力する)
+const CHANGELOG_SECTIONS: &[(&str, &str)] = &[
+    ("feat", "新機能"),
+    ("fix", "バグ修正"),
+    ("perf", "パフォーマンス改善"),
+    ("refactor", "リファクタリング"),
+    ("docs", "ドキュメント"),
+    ("test", "テスト"),
+    ("build", "ビルド"),
+    ("ci", "CI"),
+    ("chore", "その他の変更"),
+];
+
+pub fn git_changelog(args: &[String]) {
+    let positionals = positional_args(args, &[]);
+    let to = positionals.get(1).cloned().unwrap_or_else(|| "HEAD".to_string());
+    let from = positionals.first().cloned().or_else(|| GitCommand::last_tag().ok());
+    let range = match &from {
+        Some(from) => format!("{}..{}", from, to),
+        None => to.clone(), // タグが無い場合は履歴全体
+    };
+
+    let mut log_str = String::new();
+    handle_command_result(GitCommand::log_range_oneline(&range), |s| log_str = s);
+    let commits: Vec<(&str, &str)> = log_str.lines()
+        .filter_map(|line| { let mut parts = line.splitn(3, '\t'); parts.next()?; Some((parts.next()?, parts.next().unwrap_or(""))) })
+        .collect();
+
+    println!("## {}", match &from { Some(from) => format!("{}...{}", from, to), None => to.clone() });
+    if commits.is_empty() { println!("\n変更はありません。"); return; }
+
+    let parsed: Vec<_> = commits.iter().map(|(sha, subject)| (sha, subject, utils::parse_conventional_commit(subject))).collect();
+    if parsed.iter().all(|(_, _, c)| c.is_none()) {
+        // 規約に沿っていない履歴は一覧のみ
+        println!();
+        commits.iter().for_each(|(sha, subject)| println!("- {} ({})", subject, sha));
+        return;
+    }
+
+    let format_entry = |sha: &str, c: &utils::ConventionalCommit| {
+        let scope = c.scope.map(|s| format!("**{}:** ", s)).unwrap_or_default();
+        let breaking = if c.breaking { "**BREAKING** " } else { "" };
+        format!("- {}{}{} ({})", breaking, scope, c.description, sha)
+    };
+    for (kind, title) in CHANGELOG_SECTIONS {
+        let entries: Vec<String> = parsed.iter()
+            .filter_map(|(sha, _, c)| c.as_ref().filter(|c| c.kind == *kind).map(|c| format_entry(sha, c)))
+            .collect();
+        if entries.is_empty() { continue; }
+        println!("\n### {}\n", title);
+        entries.iter().for_each(|e| println!("{}", e));
+    }
+    // 未知の種類や規約外のコミット
+    let others: Vec<String> = parsed.iter()
+        .filter(|(_, _, c)| c.as_ref().is_none_or(|c| !CHANGELOG_SECTIONS.iter().any(|(k, _)| *k == c.kind)))
+        .map(|(sha, subject, _)| format!("- {} ({})", subject, sha))
+        .collect();
+    if !others.is_empty() {
+        println!("\n### その他\n");
+        others.iter().for_each(|e| println!("{}", e));
+    }
+}
+
+// 回答順 (パイプ入力時): 削除するブランチ (空白区切り)
+pub fn git_cleanup(_args: &[String]) {
+    let Some(default_branch) = get_default_branch() else {
+        exit_no_default_branch("");
+    };
+    let default_base = get_default_base_ref().unwrap_or_else(|| default_branch.clone());
+    let current_branch = get_current_branch_name();
+
+    let mut merged_str = String::new();
+    handle_command_result(GitCommand::branch_list_merged(&default_base), |s| merged_str = s);
+    let mut upstreams_str = String::new();
+    handle_command_result(GitCommand::for_each_ref_local_upstreams(), |s| upstreams_str = s);
+    let upstreams: std::collections::HashMap<&str, &str> = upstreams_str.lines()
+        .map(|line| line.split_once('\t').unwrap_or((line, "")))
+        .collect();
+
+    // マージ済みでも、上流にまだ無いコミットがあるブランチは残す (上流が消えている場合は対象)
+    let mut options = Vec::new();
+    for branch in merged_str.lines().filter(|b| *b != current_branch && *b != default_branch) {
+        let upstream = upstreams.get(branch).copied().unwrap_or("");
+        let note = if upstream.is_empty() {
+            "上流なし".to_string()
+        } else if !GitCommand::rev_parse_verify(&format!("refs/remotes/{}", upstream)).unwrap_or(false) {
+            format!("{} は削除済み", upstream)
+        } else if GitCommand::rev_list_count(&format!("{}..{}", upstream, branch)).unwrap_or(1) == 0 {
+            format!("{} と同期済み", upstream)
+        } else {
+            continue;
+        };
+        let mut option = MultiSelectOption::new(branch, branch.to_string()).with_hint(note);
+        option.selected = true;
+        options.push(option);
+    }
+    if options.is_empty() { println!("{}", "削除できるブランチはありません。".green()); return; }
+
+    println!("'{}' にマージ済みで、未プッシュのコミットが無いブランチ:", default_base.cyan());
+    options.iter().for_each(|o| println!("  {} {}", o.label.truecolor(255,165,0), format!("({})", o.hint.as_deref().unwrap_or("")).dimmed())); // オレンジ
+    let selected = prompt_multi_select("削除するブランチ", options);
+    if selected.is_empty() { println!("ブランチは削除しませんでした。"); return; }
+    // git branch -d は現在のブランチ (または上流) へのマージも確認するため、失敗しても残りの削除は続ける
+    let deleted = selected.iter().filter(|branch| match GitCommand::branch_delete_local_d(branch) {
+        Ok(()) => true,
+        Err(err_msg) => { log::warn!("{}", err_msg); false }
+    }).count();
+    println!("{}", format!("{} 個のブランチを削除しました。", deleted).green());
+}
+
+// プロンプトに埋め込むため、出力は1行のみ。リポジトリ外では何も表示しない
+pub fn git_prompt(args: &[String]) {
+    let Ok(output) = GitCommand::status_porcelain_v2_branch() else { return };
+    let header = utils::parse_branch_header(&output);
+    let dirty = !utils::parse_status(&output).is_empty();
+
+    let name = if header.head == "(detached)" {
+        header.oid.chars().take(7).collect::<String>()
+    } else {
+        header.head.clone()
+    };
+    let mut parts = vec![(name, "branch")];
+    if dirty { parts.push(("*".to_string(), "dirty")); }
+    if header.ahead > 0 { parts.push((format!("↑{}", header.ahead), "ahead")); }
+    if header.behind > 0 { parts.push((format!("↓{}", header.behind), "behind")); }
+
+    // colored は端末かどうかで色を自動判定するため、既定では明示的に無効化する
+    colored::control::set_override(has_flag(args, &["--color"]));
+    let line: String = parts.into_iter().map(|(text, kind)| match kind {
+        "branch" if header.head == "(detached)" => text.yellow().to_string(),
+        "branch" => text.cyan().to_string(),
+        "dirty" => text.yellow().bold().to_string(),
+        _ => text.truecolor(255,165,0).to_string(), // オレンジ
+    }).collect();
+    println!("{}", line);
+}
+
+// この件数までの候補を表示する
+const RECOVER_MAX_CANDIDATES: usize = 20;
+
+// HEAD の reflog の "checkout: moving from <A> to <B>" から、現在は存在しないブランチ A とその最後の位置を探す
+fn find_deleted_branches() -> Vec<(String, String)> {
+    let mut reflog = String::new();
+    handle_command_result(GitCommand::reflog_head(), |s| reflog = s);
+    let entries: Vec<(&str, &str)> = reflog.lines().filter_map(|line| line.split_once('\t')).collect();
+
+    let mut found: Vec<(String, String)> = Vec::new();
+    for (i, (_, message)) in entries.iter().enumerate() {
+        let Some(rest) = message.strip_prefix("checkout: moving from ") else { continue };
+        let Some((from, _)) = rest.split_once(" to ") else { continue };
+        // 1つ古いエントリーの sha が、ブランチを離れた時点の先端
+        let Some((tip, _)) = entries.get(i + 1) else { continue };
+        if found.iter().any(|(name, _)| name == from) { continue; } // 新しい方を優先
+        if GitCommand::rev_parse_verify(&format!("refs/heads/{}", from)).unwrap_or(true) { continue; }
+        if GitCommand::rev_parse_verify(&format!("{}^{{commit}}", from)).unwrap_or(false) { continue; } // 移動元がコミットIDやタグの場合
+        found.push((from.to_string(), tip.to_string()));
+    }
+    found
+}
+
+// 回答順 (パイプ入力時): 復元するコミットの sha → ブランチ名 (空行で元の名前)
+pub fn git_recover(args: &[String]) {
+    let mut candidates = find_deleted_branches();
+    if has_flag(args, &["--lost"]) {
+        let mut dangling = String::new();
+        handle_command_result(GitCommand::fsck_dangling_commits(), |s| dangling = s);
+        candidates.extend(dangling.lines()
+            .filter(|sha| !candidates.iter().any(|(_, tip)| tip == sha))
+            .map(|sha| (String::new(), sha.to_string()))
+            .collect::<Vec<_>>());
+    }
+    if candidates.is_empty() {
+        println!("{}", "復元できるブランチは見つかりませんでした。".yellow());
+        if !has_flag(args, &["--lost"]) { log::info!("--lost を指定すると、どこからも辿れないコミットも探します。"); }
+        return;
+    }
+
+    let options: Vec<SelectOption<String>> = candidates.iter().take(RECOVER_MAX_CANDIDATES)
+        .filter_map(|(name, sha)| {
+            let summary = GitCommand::commit_summary(sha).ok()?; // gc 済みで消えたコミットは除外
+            let mut parts = summary.splitn(3, '\t');
+            let (short_sha, subject, date) = (parts.next()?, parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+            let label = if name.is_empty() { format!("(ブランチ名不明) {} {}", short_sha, subject) } else { format!("{} {} {}", name, short_sha, subject) };
+            Some(SelectOption::new(label, sha.clone()).with_hint(date))
+        })
+        .collect();
+    if options.is_empty() { println!("{}", "復元できるブランチは見つかりませんでした。".yellow()); return; }
+    let sha = prompt_select("復元するブランチ", options);
+
+    let original_name = candidates.iter().find(|(_, tip)| *tip == sha).map(|(name, _)| name.clone()).unwrap_or_default();
+    let name = if original_name.is_empty() { prompt_input("ブランチ名: ") } else { prompt_input_with_default("ブランチ名: ", &original_name) };
+    if name.is_empty() { eprintln!("{}", "エラー: ブランチ名必須。".red()); exit(1); }
+    let mut exists = false;
+    handle_command_result(GitCommand::rev_parse_verify(&format!("refs/heads/{}", name)), |e| exists = e);
+    if exists { eprintln!("エラー: ブランチ '{}' は既に存在します。", name.red()); exit(1); }
+
+    handle_command_result_void(GitCommand::branch_create_local_from(&name, &sha));
+    println!("{}", format!("ブランチ '{}' を {} の位置に復元しました。", name, &sha[..sha.len().min(7)]).green());
+}
+
+// 対話的リベースの各コミットに対する操作 (git rebase -i の todo のコマンド名)
+const REBASE_ACTIONS: &[(&str, &str)] = &[
+    ("pick", "そのまま残す"),
+    ("squash", "1つ前のコミットにまとめる (メッセージを編集)"),
+    ("fixup", "1つ前のコミットにまとめる (このメッセージは破棄)"),
+    ("drop", "削除する"),
+];
+
+struct RebaseTodoItem {
+    action: &'static str,
+    sha: String,
+    short_sha: String,
+    subject: String,
+}
+
+fn print_rebase_plan(items: &[RebaseTodoItem]) {
+    println!("{}", "リベースの計画 (上が古いコミット):".bold());
+    for (i, item) in items.iter().enumerate() {
+        let action = match item.action {
+            "pick" => item.action.green(),
+            "drop" => item.action.red(),
+            _ => item.action.yellow(),
+        };
+        println!("  {:>2}. {:<6} {} {}", i + 1, action, item.short_sha.dimmed(), item.subject);
+    }
+}
+
+// 件名が "fixup! <件名>" / "squash! <件名>" のコミットを、対象のコミットの直後に移して fixup/squash にする (git rebase --autosquash と同じ並べ方)
+fn arrange_autosquash(items: &mut Vec<RebaseTodoItem>) {
+    let mut i = 0;
+    while i < items.len() {
+        let marker = [("fixup! ", "fixup"), ("squash! ", "squash")].into_iter()
+            .find_map(|(prefix, action)| items[i].subject.strip_prefix(prefix).map(|target| (target.to_string(), action)));
+        let Some((target_subject, action)) = marker else { i += 1; continue };
+        // 対象は自分より前にある、件名が一致するコミット (またはコミットIDの前方一致)
+        let target = items[..i].iter().position(|item| item.subject == target_subject || item.sha.starts_with(&target_subject));
+        let Some(target) = target else { i += 1; continue };
+        let mut item = items.remove(i);
+        item.action = action;
+        // 対象の後ろに既に並んでいる fixup/squash の後ろに入れる
+        let mut insert_at = target + 1;
+        while insert_at < items.len() && insert_at <= i && ["fixup", "squash"].contains(&items[insert_at].action) { insert_at += 1; }
+        items.insert(insert_at, item);
+        i += 1;
+    }
+}
+
+// 回答順 (パイプ入力時): (コミットの番号 → 操作 (pick/squash/fixup/drop/up/down)) を繰り返し → run (実行) または abort (中止)
+fn rebase_interactive(base: &str, autosquash: bool) {
+    let mut log_str = String::new();
+    handle_command_result(GitCommand::log_range_oneline_reverse(&format!("{}..HEAD", base)), |s| log_str = s);
+    let mut items: Vec<RebaseTodoItem> = log_str.lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, '\t');
+            Some(RebaseTodoItem { action: "pick", sha: parts.next()?.to_string(), short_sha: parts.next()?.to_string(), subject: parts.next().unwrap_or("").to_string() })
+        })
+        .collect();
+    if items.is_empty() { println!("{}", format!("'{}' 以降のコミットはありません。", base).yellow()); return; }
+    if autosquash { arrange_autosquash(&mut items); }
+
+    loop {
+        print_rebase_plan(&items);
+        let mut options: Vec<SelectOption<String>> = items.iter().enumerate()
+            .map(|(i, item)| SelectOption::new(format!("{}. {} {} {}", i + 1, item.action, item.short_sha, item.subject), (i + 1).to_string()))
+            .collect();
+        options.push(SelectOption::new("▶ この計画でリベースを実行", "run".to_string()));
+        options.push(SelectOption::new("✕ 中止", "abort".to_string()));
+        let choice = prompt_select("変更するコミット", options);
+        match choice.as_str() {
+            "abort" => { println!("リベースを中止しました。"); return; }
+            "run" => {
+                // 最初に残るコミットは、まとめる先が無いので squash/fixup にできない
+                match items.iter().find(|item| item.action != "drop") {
+                    None => { eprintln!("{}", "エラー: すべてのコミットを削除することはできません。".red()); continue; }
+                    Some(first) if first.action != "pick" => {
+                        eprintln!("{}", format!("エラー: 最初のコミット '{}' は {} できません。", first.short_sha, first.action).red());
+                        continue;
+                    }
+                    _ => break,
+                }
+            }
+            _ => {}
+        }
+        let Some(index) = choice.parse::<usize>().ok().and_then(|n| n.checked_sub(1)) else { continue };
+        let mut actions: Vec<SelectOption<String>> = REBASE_ACTIONS.iter()
+            .map(|(action, hint)| SelectOption::new(*action, action.to_string()).with_hint(*hint))
+            .collect();
+        if index > 0 { actions.push(SelectOption::new("↑ 上へ移動 (より古く)", "up".to_string())); }
+        if index + 1 < items.len() { actions.push(SelectOption::new("↓ 下へ移動 (より新しく)", "down".to_string())); }
+        match prompt_select(&format!("'{}' の操作", items[index].short_sha), actions).as_str() {
+            "up" => items.swap(index, index - 1),
+            "down" => items.swap(index, index + 1),
+            action => {
+                if let Some((name, _)) = REBASE_ACTIONS.iter().find(|(name, _)| *name == action) { items[index].action = name; }
+            }
+        }
+    }
+
+    let todo: String = items.iter().map(|item| format!("{} {} {}\n", item.action, item.sha, item.subject)).collect();
+    let todo_path = std::env::temp_dir().join(format!("mygit-rebase-todo-{}", std::process::id()));
+    if let Err(e) = std::fs::write(&todo_path, todo) {
+        eprintln!("エラー: 一時ファイル {} を作成できません。詳細: {}", todo_path.display(), e); exit(1);
+    }
+    let mut success = false;
+    let result = GitCommand::rebase_interactive_with_todo(base, &todo_path.to_string_lossy());
+    let _ = std::fs::remove_file(&todo_path);
+    handle_command_result(result, |s| success = s);
+    if !success {
+        exit_rebase_stopped("リベースが途中で停止しました (コンフリクトなど)。");
+    }
+    println!("{}", "リベースが完了しました。".green());
+}
+
+// 途中の操作や未コミットの変更があるとリベースやブランチの切り替えができないため、先に止める
+fn ensure_no_operation_in_progress() {
+    if let Some(operation) = operation_in_progress() {
+        eprintln!("エラー: 途中の {} があります。完了するか中止してから実行してください。", operation.red()); exit(1);
+    }
+}
+
+fn ensure_clean_state() {
+    ensure_no_operation_in_progress();
+    if has_uncommitted_changes() {
+        eprintln!("{}", "エラー: 未コミットの変更があります。先に save するか退避してください。".red()); exit(1);
+    }
+}
+
+// 回答順 (パイプ入力時): (--select 時: リベース先のブランチ →) (未コミットの変更がある場合: handle_uncommitted_changes_before_action を参照 →) (--interactive 時: rebase_interactive を参照) (→ コンフリクト時、rerere 無効なら: rerere 有効化確認)
+pub fn git_rebase(args: &[String]) {
+    // --autostash は通常のリベースのみ。--interactive は todo を編集するため従来どおり変更が無い状態を求める
+    let autostash = autostash_enabled(args) && !has_flag(args, &["--interactive", "-i", "--autosquash"]);
+    ensure_no_operation_in_progress();
+    let base = match positional_args(args, &[]).into_iter().next() {
+        Some(base) => base,
+        None if has_flag(args, &["--select", "-s"]) => {
+            let current = get_current_branch_name();
+            let options: Vec<SelectOption<String>> = get_local_branch_names().into_iter()
+                .filter(|name| *name != current)
+                .map(|name| SelectOption::new(name.clone(), name))
+                .collect();
+            if options.is_empty() { eprintln!("{}", "エラー: リベース先にできる他のブランチがありません。".red()); exit(1); }
+            prompt_select(&format!("'{}' のリベース先", current), options)
+        }
+        None => match get_default_base_ref() {
+            Some(base) => base,
+            None => exit_no_default_branch("リベース先を指定してください。"),
+        },
+    };
+    let mut exists = false;
+    handle_command_result(GitCommand::rev_parse_verify(&format!("{}^{{commit}}", base)), |e| exists = e);
+    if !exists { eprintln!("エラー: '{}' はブランチまたはコミットとして無効です。", base.red()); exit(1); }
+
+    if !autostash && !handle_uncommitted_changes_before_action("リベース") { return; }
+
+    let autosquash = has_flag(args, &["--autosquash"]);
+    if autosquash || has_flag(args, &["--interactive", "-i"]) {
+        rebase_interactive(&base, autosquash);
+        return;
+    }
+    let autostashed = autostash && has_uncommitted_changes();
+    let mut success = false;
+    let rebase_result = if autostash { GitCommand::rebase_autostash(&base) } else { GitCommand::rebase(&base) };
+    handle_command_result(rebase_result, |s| success = s);
+    if !success {
+        if autostashed { log::warn!("退避した未コミットの変更は、リベースの完了 (または中止) 時に戻されます。"); }
+        exit_rebase_stopped(&format!("'{}' へのリベース中にコンフリクトが発生しました。", base));
+    }
+    println!("{}", format!("'{}' の上にリベースしました。", base).green());
+    if autostashed { log::info!("未コミットの変更を自動スタッシュし、リベース後に戻しました。"); }
+}
+
+// ブランチ一覧から選ぶか、空の値の選択肢で手動入力する
+fn prompt_ref(message: &str) -> String {
+    let mut options: Vec<SelectOption<String>> = get_local_branch_names().into_iter()
+        .map(|name| SelectOption::new(name.clone(), name))
+        .collect();
+    options.push(SelectOption::new("+ 手動で入力 (タグ/コミットID/リモートブランチ)", String::new()));
+    let selected = prompt_select(message, options);
+    if !selected.is_empty() { return selected; }
+    let entered = prompt_input(&format!("{}: ", message));
+    if entered.is_empty() { eprintln!("{}", "エラー: 参照名必須。".red()); exit(1); }
+    entered
+}
+
+// 回答順 (パイプ入力時): (未指定時: 1つ目の参照 (→ 手動入力時: 参照名) → 2つ目の参照 (→ 手動入力時: 参照名))
+pub fn git_ancestry(args: &[String]) {
+    let positionals = positional_args(args, &[]);
+    let a = positionals.first().cloned().unwrap_or_else(|| prompt_ref("1つ目のブランチ"));
+    let b = positionals.get(1).cloned().unwrap_or_else(|| prompt_ref("2つ目のブランチ"));
+    for r in [&a, &b] {
+        let mut exists = false;
+        handle_command_result(GitCommand::rev_parse_verify(&format!("{}^{{commit}}", r)), |e| exists = e);
+        if !exists { eprintln!("エラー: '{}' はブランチまたはコミットとして無効です。", r.red()); exit(1); }
+    }
+
+    let mut a_in_b = false;
+    handle_command_result(GitCommand::is_ancestor(&a, &b), |r| a_in_b = r);
+    let mut b_in_a = false;
+    handle_command_result(GitCommand::is_ancestor(&b, &a), |r| b_in_a = r);
+    let count = |range: String| GitCommand::rev_list_count(&range).unwrap_or(0);
+
+    match (a_in_b, b_in_a) {
+        (true, true) => println!("{}", format!("'{}' と '{}' は同じコミットです。", a, b).green()),
+        (true, false) => println!("'{}' は '{}' の祖先です。('{}' は {} コミット先に進んでいます)", a.cyan(), b.cyan(), b, count(format!("{}..{}", a, b)).to_string().bold()),
+        (false, true) => println!("'{}' は '{}' の祖先です。('{}' は {} コミット先に進んでいます)", b.cyan(), a.cyan(), a, count(format!("{}..{}", b, a)).to_string().bold()),
+        (false, false) => {
+            println!("{}", format!("'{}' と '{}' は分岐しています。", a, b).yellow());
+            match GitCommand::merge_base(&a, &b) {
+                Ok(base) => {
+                    let summary = GitCommand::commit_summary(&base).unwrap_or_default();
+                    let mut parts = summary.splitn(3, '\t');
+                    let (short_sha, subject) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+                    println!("  分岐点: {} {}", short_sha.cyan(), subject);
+                    println!("  '{}' のみのコミット: {}", a, count(format!("{}..{}", b, a)).to_string().bold());
+                    println!("  '{}' のみのコミット: {}", b, count(format!("{}..{}", a, b)).to_string().bold());
+                }
+                Err(_) => println!("  共通の祖先はありません (無関係な履歴です)。"),
+            }
+        }
+    }
+}
+
+pub fn git_strategy(args: &[String]) {
+    let Some(value) = positional_args(args, &[]).into_iter().next() else {
+        match utils::config().update_strategy {
+            Some(strategy) => println!("取り込み方法: {}", strategy.as_str().cyan()),
+            None => println!("取り込み方法: {} {}", "merge".cyan(), "(未設定のため既定値)".dimmed()),
+        }
+        println!("{}", "コマンドごとに --merge / --rebase / --ff-only で上書きできます。".dimmed());
+        return;
+    };
+    let Some(strategy) = utils::UpdateStrategy::parse(&value) else {
+        eprintln!("エラー: 不明な取り込み方法 '{}'", value.red());
+        eprintln!("{} {} strategy [merge|rebase|ff-only]", "Usage:".bold(), "mygit".green());
+        exit(1);
+    };
+    handle_command_result(utils::set_config_value("update_strategy", &format!("\"{}\"", strategy.as_str())), |path| {
+        println!("{}", format!("取り込み方法を '{}' に設定しました。({})", strategy.as_str(), path.display()).green());
+    });
+}
+
+// 回答順 (パイプ入力時): 1つ目のファイル (空白区切り) → 1つ目のメッセージ → 2つ目のファイル → 2つ目のメッセージ → (残りがある場合: 残す確認)
+pub fn git_split(_args: &[String]) {
+    let entries = get_status_entries();
+    if entries.iter().any(|e| e.is_conflicted) {
+        eprintln!("{}", "エラー: コンフリクト中のファイルがあります。先に解決してください。".red()); exit(1);
+    }
+    let paths: Vec<String> = entries.into_iter().map(|e| e.path).collect();
+    if paths.len() < 2 { println!("{}", "分割するには2つ以上の変更ファイルが必要です。".yellow()); return; }
+
+    let as_options = |paths: &[String]| paths.iter().map(|p| MultiSelectOption::new(p, p.clone())).collect::<Vec<_>>();
+    let first = prompt_multi_select("1つ目のコミットに含めるファイル", as_options(&paths));
+    if first.is_empty() { eprintln!("{}", "エラー: 1つ目のコミットのファイルを選んでください。".red()); exit(1); }
+    let first_message = prompt_input("1つ目のコミットメッセージ: ");
+    if first_message.is_empty() { eprintln!("{}", "エラー: メッセージ必須。".red()); exit(1); }
+    check_commit_subject(&first_message);
+
+    let rest: Vec<String> = paths.iter().filter(|p| !first.contains(p)).cloned().collect();
+    let second = prompt_multi_select("2つ目のコミットに含めるファイル", as_options(&rest));
+    if second.is_empty() { eprintln!("{}", "エラー: 2つ目のコミットのファイルを選んでください。".red()); exit(1); }
+    let second_message = prompt_input("2つ目のコミットメッセージ: ");
+    if second_message.is_empty() { eprintln!("{}", "エラー: メッセージ必須。".red()); exit(1); }
+    check_commit_subject(&second_message);
+
+    // どちらにも選ばれなかったファイルは、明示的に確認してから未コミットのまま残す
+    let left: Vec<&String> = rest.iter().filter(|p| !second.contains(p)).collect();
+    if !left.is_empty() {
+        println!("どちらのコミットにも含まれないファイル:");
+        left.iter().for_each(|p| println!("  {}", p.yellow()));
+        if !confirm(&format!("{} 個のファイルを未コミットのまま残しますか？", left.len())) {
+            println!("分割をキャンセルしました。");
+            return;
+        }
+    }
+
+    // 既にステージされている変更が混ざらないよう、インデックスを空にしてからグループごとにステージする
+    handle_command_result_void(GitCommand::unstage("."));
+    for (files, message) in [(&first, &first_message), (&second, &second_message)] {
+        handle_command_result_void(GitCommand::add_paths(files));
+        handle_command_result_void(GitCommand::commit(message));
+    }
+    println!("{}", "変更を2つのコミットに分けて記録しました。".green());
+}
+
+// よく使われる属性の説明 (attributes コマンドで値と一緒に表示する)
+const ATTRIBUTE_DESCRIPTIONS: &[(&str, &str)] = &[
+    ("text", "改行コードの正規化 (set: テキスト扱い, unset: 正規化しない, auto: git が判定)"),
+    ("eol", "作業ツリーでの改行コード (lf/crlf)"),
+    ("binary", "バイナリとして扱う (差分・マージ・改行変換を行わない)"),
+    ("diff", "差分の表示方法 (unset: バイナリとして扱う, 名前: diff ドライバ)"),
+    ("merge", "マージ方法 (unset: 片方を採用してコンフリクト扱い, binary, union, 名前: マージドライバ)"),
+    ("filter", "チェックアウト/ステージ時に通すフィルタ (例: lfs)"),
+    ("whitespace", "空白のエラー検出の設定"),
+    ("export-ignore", "git archive の出力から除外する"),
+    ("linguist-generated", "ホスティングサービスで生成ファイルとして扱う"),
+    ("linguist-vendored", "ホスティングサービスで外部ライブラリとして扱う"),
+];
+
+// 回答順 (パイプ入力時): (パス未指定時: ファイル)
+pub fn git_attributes(args: &[String]) {
+    let path = match positional_args(args, &[]).into_iter().next() {
+        Some(path) => path,
+        None => {
+            let mut files = String::new();
+            handle_command_result(GitCommand::ls_files(), |s| files = s);
+            if files.is_empty() { println!("{}", "追跡中のファイルはありません。".yellow()); return; }
+            prompt_select("属性を調べるファイル", files.lines().map(|p| SelectOption::new(p, p.to_string())).collect())
+        }
+    };
+
+    let mut output = String::new();
+    handle_command_result(GitCommand::check_attr(&path), |s| output = s);
+    // "<パス>: <属性>: <値>" のパス部分にも ": " が含まれうるので後ろから分ける
+    let attributes: Vec<(&str, &str)> = output.lines()
+        .filter_map(|line| { let (rest, value) = line.rsplit_once(": ")?; Some((rest.rsplit_once(": ")?.1, value)) })
+        .collect();
+
+    println!("{} の属性:", path.cyan());
+    if attributes.is_empty() {
+        println!("  {}", "(属性なし。git の既定の判定でテキスト/バイナリが決まります)".dimmed());
+        return;
+    }
+    for (name, value) in &attributes {
+        let value_str = match *value { "set" => value.green(), "unset" => value.red(), _ => value.yellow() };
+        let description = ATTRIBUTE_DESCRIPTIONS.iter().find(|(n, _)| n == name).map(|(_, d)| *d).unwrap_or("");
+        println!("  {} = {} {}", name.bold(), value_str, description.dimmed());
+    }
+}
+
+pub fn git_bundle(args: &[String]) {
+    let positionals = positional_args(args, &[]);
+    let usage = || {
+        eprintln!("{} {} bundle {{create [<file>]|clone <file> [<dir>]|fetch <file>}}", "Usage:".bold(), "mygit".green());
+        exit(1);
+    };
+    match positionals.first().map(|s| s.as_str()) {
+        Some("create") => {
+            let file = match positionals.get(1) {
+                Some(file) => file.clone(),
+                None => {
+                    let mut toplevel = String::new();
+                    handle_command_result(GitCommand::show_toplevel(), |s| toplevel = s);
+                    let name = std::path::Path::new(&toplevel).file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| "repo".to_string());
+                    format!("{}.bundle", name)
+                }
+            };
+            if std::path::Path::new(&file).exists() && !confirm(&format!("'{}' は既に存在します。上書きしますか？", file)) {
+                println!("バンドルの作成をキャンセルしました。");
+                return;
+            }
+            handle_command_result_void(GitCommand::bundle_create(&file));
+            let path = std::fs::canonicalize(&file).unwrap_or_else(|_| std::path::PathBuf::from(&file));
+            let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+            println!("{}", format!("バンドルを作成しました: {} ({})", path.display(), utils::format_size(size)).green());
+            // refs/stash は最新のスタッシュのみ含まれる (reflog にある古いスタッシュは含まれない)
+            if GitCommand::rev_parse_verify("refs/stash").unwrap_or(false) {
+                log::info!("注意: スタッシュは最新の1件のみバンドルに含まれます。");
+            }
+        }
+        Some("clone") => {
+            let Some(file) = positionals.get(1) else { return usage() };
+            // bundle verify はリポジトリ内でしか使えないので、検証は clone に任せる
+            handle_command_result_void(GitCommand::clone(file, positionals.get(2).map(|s| s.as_str())));
+            println!("{}", format!("'{}' からリポジトリを作成しました。", file).green());
+        }
+        Some("fetch") => {
+            let Some(file) = positionals.get(1) else { return usage() };
+            handle_command_result_void(GitCommand::bundle_verify(file));
+            handle_command_result_void(GitCommand::fetch_bundle(file, "bundle"));
+            println!("{}", format!("'{}' のブランチを bundle/<ブランチ名> として取り込みました。", file).green());
+            println!("必要なブランチは merge や switch で取り込んでください。");
+        }
+        _ => usage(),
+    }
+}
+
+// 回答順 (パイプ入力時): (パス未指定時: ファイル (空白区切り)) → (プッシュ済みの場合: 続行確認)
+pub fn git_addto(args: &[String]) {
+    let mut has_commit = false;
+    handle_command_result(GitCommand::rev_parse_verify("HEAD"), |e| has_commit = e);
+    if !has_commit { eprintln!("{}", "エラー: まだコミットがありません。".red()); exit(1); }
+
+    let paths = {
+        let given = positional_args(args, &[]);
+        if !given.is_empty() { given } else {
+            let options: Vec<MultiSelectOption<String>> = get_status_entries().into_iter()
+                .filter(|e| (e.is_unstaged() || e.is_untracked()) && !e.is_submodule)
+                .map(|e| MultiSelectOption::new(format!("{} {}", if e.is_untracked() { '?' } else { e.worktree }, e.path), e.path))
+                .collect();
+            if options.is_empty() { println!("{}", "追加できる変更はありません。".yellow()); return; }
+            prompt_multi_select("直前のコミットに追加するファイル", options)
+        }
+    };
+    if paths.is_empty() { println!("ファイルは追加しませんでした。"); return; }
+
+    let mut remotes = String::new();
+    handle_command_result(GitCommand::branch_remote_contains("HEAD"), |s| remotes = s);
+    if !remotes.is_empty()
+        && !confirm(&"警告: 直前のコミットは既にプッシュされています。変更後は force push が必要です。続行しますか？".yellow().to_string()) {
+        println!("キャンセルしました。");
+        return;
+    }
+
+    handle_command_result_void(GitCommand::add_paths(&paths));
+    handle_command_result_void(GitCommand::commit_amend(None));
+    println!("{}", format!("{} 個のファイルを直前のコミットに追加しました。", paths.len()).green());
+    if !remotes.is_empty() { println!("{}", "リモートへの反映には 'git push --force-with-lease' が必要です。".yellow()); }
+}
+
+// 回答順 (パイプ入力時): (遅れている場合: fast-forward 確認)
+pub fn git_default_check(_args: &[String]) {
+    let Some(default_branch) = get_default_branch() else {
+        exit_no_default_branch("");
+    };
+    if GitCommand::remote_get_url(utils::remote()).is_err() {
+        eprintln!("{}", format!("エラー: リモート '{}' が未設定。", utils::remote()).red()); exit(1);
+    }
+    handle_command_result_void(GitCommand::fetch_branch(utils::remote(), &default_branch));
+    let remote_ref = format!("{}/{}", utils::remote(), default_branch);
+    if !GitCommand::rev_parse_verify(&format!("refs/heads/{}", default_branch)).unwrap_or(false) {
+        println!("ローカルに '{}' はありません ('{}' のみ)。", default_branch.yellow(), remote_ref);
+        return;
+    }
+
+    let ahead = GitCommand::rev_list_count(&format!("{}..{}", remote_ref, default_branch)).unwrap_or(0);
+    let behind = GitCommand::rev_list_count(&format!("{}..{}", default_branch, remote_ref)).unwrap_or(0);
+    match (ahead, behind) {
+        (0, 0) => println!("{}", format!("ローカルの '{}' は '{}' と同じです。", default_branch, remote_ref).green()),
+        (_, 0) => println!("{}", format!("ローカルの '{}' は '{}' より {} コミット進んでいます (未プッシュ)。", default_branch, remote_ref, ahead).yellow()),
+        (0, _) => {
+            println!("{}", format!("ローカルの '{}' は '{}' より {} コミット遅れています。", default_branch, remote_ref, behind).yellow());
+            if !confirm(&format!("'{}' を fast-forward で最新にしますか？", default_branch)) { return; }
+            if get_current_branch_name() == default_branch {
+                let mut success = false;
+                handle_command_result(GitCommand::merge_ff_only(&remote_ref), |s| success = s);
+                if !success { eprintln!("{}", "エラー: fast-forward できませんでした (未コミットの変更と衝突している可能性があります)。".red()); exit(1); }
+            } else {
+                // チェックアウトしていないブランチは作業ツリーに触れずに更新できる
+                handle_command_result_void(GitCommand::fetch_fast_forward(utils::remote(), &default_branch));
+            }
+            println!("{}", format!("'{}' を最新にしました。", default_branch).green());
+        }
+        _ => {
+            println!("{}", format!("ローカルの '{}' は '{}' と分岐しています (進み {} / 遅れ {})。", default_branch, remote_ref, ahead, behind).red());
+            println!("fast-forward できません。'{}' 上のローカルのコミットを確認し、プッシュするか別ブランチへ移してください。", default_branch);
+        }
+    }
+}
+
+pub fn git_shortlog(args: &[String]) {
+    // 表示用のラベルは範囲とは別に持つ (自動で求めた範囲だけ、分岐点のコミットIDを短縮して表示する)
+    let (range, label) = match positional_args(args, &[]).into_iter().next() {
+        Some(range) => (range.clone(), range),
+        None => {
+            // 既定ブランチ上 (または既定ブランチが無い場合) は履歴全体
+            let current_branch = get_current_branch_name();
+            match get_default_base_ref() {
+                Some(base) if base.trim_start_matches(&format!("{}/", utils::remote())) != current_branch => {
+                    let mut merge_base = String::new();
+                    handle_command_result(GitCommand::merge_base("HEAD", &base), |b| merge_base = b);
+                    let short: String = merge_base.chars().take(7).collect();
+                    (format!("{}..HEAD", merge_base), format!("{}..HEAD", short))
+                }
+                _ => ("HEAD".to_string(), "HEAD".to_string()),
+            }
+        }
+    };
+    let mut output = String::new();
+    handle_command_result(GitCommand::shortlog(&range), |s| output = s);
+    let authors: Vec<(usize, &str)> = output.lines()
+        .filter_map(|line| { let (count, name) = line.trim().split_once('\t')?; Some((count.parse().ok()?, name)) })
+        .collect();
+
+    if authors.is_empty() { println!("{}", format!("'{}' にコミットはありません。", label).yellow()); return; }
+    let total: usize = authors.iter().map(|(count, _)| count).sum();
+    println!("{} ({} コミット, {} 人):", label.bold(), total, authors.len());
+    let width = authors.iter().map(|(count, _)| count.to_string().len()).max().unwrap_or(1);
+    for (count, name) in &authors {
+        println!("  {:>width$} {} {}", count.to_string().green().bold(), name.cyan(), format!("({}%)", count * 100 / total).dimmed(), width = width);
+    }
+}
+
+// 回答順 (パイプ入力時): (コンフリクト時、rerere 無効なら: rerere 有効化確認)
+pub fn git_restack(_args: &[String]) {
+    ensure_clean_state();
+    let Some(default_branch) = get_default_branch() else {
+        exit_no_default_branch("");
+    };
+    let current_branch = get_current_branch_name();
+    if current_branch.is_empty() || current_branch == default_branch {
+        eprintln!("{}", format!("エラー: 既定ブランチ以外の作業ブランチで実行してください (現在: '{}')。", if current_branch.is_empty() { "detached HEAD" } else { &current_branch }).red()); exit(1);
+    }
+    if GitCommand::remote_get_url(utils::remote()).is_err() {
+        eprintln!("{}", format!("エラー: リモート '{}' が未設定。", utils::remote()).red()); exit(1);
+    }
+    handle_command_result_void(GitCommand::fetch_branch(utils::remote(), &default_branch));
+    let base = format!("{}/{}", utils::remote(), default_branch);
+
+    let mut old_base = String::new();
+    handle_command_result(GitCommand::merge_base("HEAD", &base), |b| old_base = b);
+    let mut new_base = String::new();
+    handle_command_result(GitCommand::rev_parse_commit_id(&base), |b| new_base = b);
+    let short = |sha: &str| sha.chars().take(7).collect::<String>();
+    if old_base == new_base {
+        println!("{}", format!("'{}' は既に '{}' の最新 ({}) の上にあります。", current_branch, base, short(&new_base)).green());
+        return;
+    }
+    let mut commits = String::new();
+    handle_command_result(GitCommand::log_range_oneline_reverse(&format!("{}..HEAD", old_base)), |c| commits = c);
+    let incoming = GitCommand::rev_list_count(&format!("{}..{}", old_base, new_base)).unwrap_or(0);
+
+    let mut success = false;
+    handle_command_result(GitCommand::rebase(&base), |s| success = s);
+    if !success {
+        exit_rebase_stopped(&format!("'{}' へのリベース中にコンフリクトが発生しました。", base));
+    }
+    println!("{}", format!("'{}' を '{}' の上に積み直しました。", current_branch, base).green());
+    println!("  基点: {} → {} ({} に {} コミット追加)", short(&old_base).yellow(), short(&new_base).cyan(), base, incoming);
+    let replayed: Vec<&str> = commits.lines().collect();
+    println!("  再適用したコミット: {}", replayed.len().to_string().bold());
+    for line in replayed {
+        let mut parts = line.splitn(3, '\t');
+        let (_, short_sha, subject) = (parts.next(), parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+        println!("    {} {}", short_sha.cyan(), subject);
+    }
+    if GitCommand::rev_parse_verify(&format!("refs/remotes/{}/{}", utils::remote(), current_branch)).unwrap_or(false) {
+        println!("{}", "リモートへの反映には 'git push --force-with-lease' が必要です。".yellow());
+    }
+}
+
+// 無効化したフックはこの拡張子を付けてリネームしておく
+const DISABLED_HOOK_SUFFIX: &str = ".disabled";
+
+fn get_hooks_dir() -> std::path::PathBuf {
+    let mut dir = String::new();
+    handle_command_result(GitCommand::hooks_dir(), |d| dir = d);
+    std::path::PathBuf::from(dir)
+}
+
+#[cfg(unix)]
+fn is_executable(path: &std::path::Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path).is_ok_and(|m| m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(_path: &std::path::Path) -> bool { true }
+
+// (フック名, 有効か) の一覧。*.sample は git が実行しないため除外する
+fn get_installed_hooks(dir: &std::path::Path) -> Vec<(String, bool)> {
+    let Ok(entries) = std::fs::read_dir(dir) else { return Vec::new() };
+    let mut hooks: Vec<(String, bool)> = entries.flatten()
+        .filter(|entry| entry.path().is_file())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| !name.ends_with(".sample"))
+        .map(|name| match name.strip_suffix(DISABLED_HOOK_SUFFIX) {
+            Some(hook) => (hook.to_string(), false),
+            None => (name, true),
+        })
+        .collect();
+    hooks.sort();
+    hooks
+}
+
+// 回答順 (パイプ入力時): (disable/enable でフック名未指定時: フック)
+pub fn git_hook(args: &[String]) {
+    let positionals = positional_args(args, &[]);
+    let subcommand = positionals.first().map(|s| s.as_str()).unwrap_or("list");
+    let dir = get_hooks_dir();
+    let hooks = get_installed_hooks(&dir);
+
+    match subcommand {
+        "list" => {
+            if hooks.is_empty() { println!("{}", format!("フックはインストールされていません ({})。", dir.display()).yellow()); return; }
+            println!("{} ({}):", "フック".bold(), dir.display());
+            for (name, enabled) in &hooks {
+                let state = if !enabled {
+                    "無効".yellow()
+                } else if is_executable(&dir.join(name)) {
+                    "有効".green()
+                } else {
+                    "実行権限なし (実行されません)".red()
+                };
+                println!("  {:<20} {}", name, state);
+            }
+        }
+        "disable" | "enable" => {
+            let enabling = subcommand == "enable";
+            let name = match positionals.get(1) {
+                Some(name) => name.clone(),
+                None => {
+                    let options: Vec<SelectOption<String>> = hooks.iter()
+                        .filter(|(_, enabled)| *enabled != enabling)
+                        .map(|(name, _)| SelectOption::new(name.clone(), name.clone()))
+                        .collect();
+                    if options.is_empty() {
+                        println!("{}", format!("{}できるフックはありません。", if enabling { "有効化" } else { "無効化" }).yellow());
+                        return;
+                    }
+                    prompt_select(&format!("{} するフック", subcommand), options)
+                }
+            };
+            let active = dir.join(&name);
+            let disabled = dir.join(format!("{}{}", name, DISABLED_HOOK_SUFFIX));
+            let (from, to) = if enabling { (&disabled, &active) } else { (&active, &disabled) };
+            if !from.is_file() {
+                let state = if enabling { "無効化されたフック" } else { "有効なフック" };
+                eprintln!("エラー: {} '{}' はありません。", state, name.red()); exit(1);
+            }
+            if to.exists() {
+                eprintln!("エラー: {} が既に存在します。手動で確認してください。", to.display().to_string().red()); exit(1);
+            }
+            if let Err(e) = std::fs::rename(from, to) {
+                eprintln!("エラー: {} をリネームできません。詳細: {}", from.display(), e); exit(1);
+            }
+            if enabling {
+                println!("{}", format!("フック '{}' を有効化しました。", name).green());
+                if !is_executable(to) { log::warn!("警告: '{}' に実行権限がないため、git は実行しません (chmod +x が必要です)。", name); }
+            } else {
+                println!("{}", format!("フック '{}' を無効化しました。'mygit hook enable {}' で元に戻せます。", name, name).green());
+            }
+        }
+        _ => {
+            eprintln!("{} {} hook {{list|disable|enable}} [<name>]", "Usage:".bold(), "mygit".green());
+            exit(1);
+        }
+    }
+}
+
+// 回答順 (パイプ入力時): (未指定時: 検索する文字列)
+pub fn git_pickaxe(args: &[String]) {
+    let positionals = positional_args(args, &[]);
+    let regex = has_flag(args, &["--regex", "-G"]);
+    let term = match positionals.first() {
+        Some(term) => term.clone(),
+        None => prompt_input(if regex { "検索する正規表現: " } else { "検索する文字列: " }),
+    };
+    if term.is_empty() { eprintln!("{}", "エラー: 検索する文字列必須。".red()); exit(1); }
+    let path = positionals.get(1).map(|p| p.as_str());
+
+    let mut output = String::new();
+    handle_command_result(GitCommand::log_pickaxe(&term, regex, path), |s| output = s);
+    let scope = path.map(|p| format!(" ({})", p)).unwrap_or_default();
+    if output.is_empty() { println!("{}", format!("'{}' を変更したコミットはありません{}。", term, scope).yellow()); return; }
+    let commits: Vec<Vec<&str>> = output.lines().map(|line| line.splitn(4, '\t').collect()).collect();
+    println!("'{}' を{}コミット{} ({} 件, 新しい順):", term.cyan(), if regex { "含む行を変更した" } else { "追加/削除した" }, scope, commits.len());
+    for parts in &commits {
+        let field = |i: usize| parts.get(i).copied().unwrap_or("");
+        println!("  {} {} {} {}", field(0).yellow(), field(3), field(1).cyan(), format!("({})", field(2)).dimmed());
+    }
+}
+
+// origin のホストを hosts に含むプロファイル名
+fn identity_for_origin() -> Option<&'static str> {
+    let url = GitCommand::remote_get_url(utils::remote()).ok()?;
+    let host = utils::parse_remote_url(&url)?.host;
+    utils::config().identities.iter()
+        .find(|(_, identity)| identity.hosts.iter().any(|h| h.eq_ignore_ascii_case(&host)))
+        .map(|(name, _)| name.as_str())
+}
+
+// 回答順 (パイプ入力時): (プロファイル未指定時: プロファイル)
+pub fn git_identity(args: &[String]) {
+    let identities = &utils::config().identities;
+    let current_name = GitCommand::config_get("user.name").unwrap_or_default();
+    let current_email = GitCommand::config_get("user.email").unwrap_or_default();
+    if identities.is_empty() {
+        println!("現在のユーザー: {} <{}>", current_name.cyan(), current_email.cyan());
+        println!("{}", "プロファイルが未設定です。設定ファイルに [identities.<名前>] (name, email, signing_key, hosts) を追加してください。".yellow());
+        return;
+    }
+
+    let profile = if has_flag(args, &["--auto"]) {
+        match identity_for_origin() {
+            Some(name) => name.to_string(),
+            None => { eprintln!("{}", format!("エラー: {} のホストに一致する hosts を持つプロファイルがありません。", utils::remote()).red()); exit(1); }
+        }
+    } else if let Some(name) = positional_args(args, &[]).into_iter().next() {
+        name
+    } else {
+        println!("現在のユーザー: {} <{}>", current_name.cyan(), current_email.cyan());
+        let options: Vec<SelectOption<String>> = identities.iter()
+            .map(|(name, identity)| SelectOption::new(name.clone(), name.clone()).with_hint(format!("{} <{}>", identity.name, identity.email)))
+            .collect();
+        prompt_select("このリポジトリに設定するプロファイル", options)
+    };
+    let Some(identity) = identities.get(&profile) else {
+        let names: Vec<&str> = identities.keys().map(|k| k.as_str()).collect();
+        eprintln!("エラー: プロファイル '{}' はありません (利用可能: {})。", profile.red(), names.join(", ")); exit(1);
+    };
+
+    if current_name == identity.name && current_email == identity.email
+        && GitCommand::config_get("user.signingkey").ok() == identity.signing_key {
+        println!("{}", format!("既にプロファイル '{}' ({} <{}>) が設定されています。", profile, identity.name, identity.email).green());
+        return;
+    }
+    handle_command_result_void(GitCommand::config_set("user.name", &identity.name, false));
+    handle_command_result_void(GitCommand::config_set("user.email", &identity.email, false));
+    match &identity.signing_key {
+        Some(key) => handle_command_result_void(GitCommand::config_set("user.signingkey", key, false)),
+        // 前のプロファイルの署名鍵が残らないようにする
+        None => { let _ = GitCommand::config_unset_local("user.signingkey"); }
+    }
+    println!("{}", format!("プロファイル '{}' を設定しました: {} <{}>", profile, identity.name, identity.email).green());
+    if let Some(key) = &identity.signing_key { println!("  署名鍵: {}", key.cyan()); }
+}
+
+pub fn git_diverge(args: &[String]) {
+    if has_flag(args, &["--fetch"]) && GitCommand::remote_get_url(utils::remote()).is_ok() {
+        handle_command_result_void(GitCommand::fetch_prune(utils::remote()));
+    }
+    let positionals = positional_args(args, &[]);
+    if positionals.first().is_some_and(|sub| sub == "detail") {
+        git_diverge_detail(positionals.get(1).cloned().unwrap_or_else(get_current_branch_name));
+        return;
+    }
+    let mut refs_str = String::new();
+    handle_command_result(GitCommand::for_each_ref_local_upstreams(), |s| refs_str = s);
+    let current_branch = get_current_branch_name();
+
+    // (ブランチ, 上流, 進み, 遅れ)。上流が無い、または上流が消えたブランチは数えない
+    let mut rows = refs_str.lines()
+        .map(|line| {
+            let (branch, upstream) = line.split_once('\t').unwrap_or((line, ""));
+            let counts = if upstream.is_empty() { None } else { GitCommand::rev_list_left_right_count(&format!("{}...{}", branch, upstream)).ok() };
+            (branch, upstream, counts)
+        })
+        .collect::<Vec<_>>();
+    if rows.is_empty() { println!("{}", "ローカルブランチはありません。".yellow()); return; }
+    rows.sort_by_key(|(branch, _, counts)| (std::cmp::Reverse(counts.map(|(a, b)| a + b)), *branch));
+
+    // 見出しやブランチ名に全角文字が含まれても揃うよう、文字数ではなく表示幅で埋める
+    let pad_end = |s: &str, width: usize| format!("{}{}", s, " ".repeat(width.saturating_sub(utils::visible_width(s))));
+    let pad_start = |s: &str, width: usize| format!("{}{}", " ".repeat(width.saturating_sub(utils::visible_width(s))), s);
+    let name_width = rows.iter().map(|(branch, _, _)| utils::visible_width(branch)).max().unwrap_or(0).max(utils::visible_width("ブランチ")) + 2;
+    let upstream_width = rows.iter().map(|(_, upstream, _)| utils::visible_width(upstream)).max().unwrap_or(0).max(6);
+    println!("  {} {} {} {}  状態", pad_end("ブランチ", name_width), pad_end("上流", upstream_width), pad_start("進み", 5), pad_start("遅れ", 5));
+    for (branch, upstream, counts) in &rows {
+        let marker = if *branch == current_branch { "*" } else { " " };
+        let name = pad_end(branch, name_width);
+        let upstream_col = pad_end(if upstream.is_empty() { "-" } else { upstream }, upstream_width);
+        let (ahead, behind, state) = match counts {
+            Some((0, 0)) => ("0".to_string(), "0".to_string(), "同期済み".green()),
+            Some((ahead, 0)) => (ahead.to_string(), "0".to_string(), "要プッシュ".yellow()),
+            Some((0, behind)) => ("0".to_string(), behind.to_string(), "要プル".cyan()),
+            Some((ahead, behind)) => (ahead.to_string(), behind.to_string(), "分岐".red().bold()),
+            None if upstream.is_empty() => ("-".to_string(), "-".to_string(), "上流なし".dimmed()),
+            None => ("-".to_string(), "-".to_string(), "上流が削除済み".red()),
+        };
+        let name = if counts.is_some_and(|(a, b)| a + b > 0) { name.bold().to_string() } else { name };
+        println!("{} {} {} {} {}  {}", marker, name, upstream_col.dimmed(), pad_start(&ahead, 5), pad_start(&behind, 5), state);
+    }
+}
+
+// 既定のブランチ名の形式 (設定の branch_name_template で変更できる)
+const DEFAULT_BRANCH_NAME_TEMPLATE: &str = "feature/{ticket}-{slug}";
+
+// 説明が空、または英数字を含まない (日本語のみなど) 場合は slug が空になるので、
+// それによって残る区切り文字を各階層の前後から取り除く。例: "feature/PROJ-1-" → "feature/PROJ-1"
+fn branch_name_from_template(template: &str, ticket: &str, description: &str) -> String {
+    template.replace("{ticket}", ticket).replace("{slug}", &utils::slugify(description))
+        .split('/')
+        .map(|part| part.trim_matches(['-', '_']))
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+// 回答順 (パイプ入力時): (チケット未指定時: チケット番号 → 説明) → (--push 未指定時: プッシュ確認)
+pub fn git_start(args: &[String]) {
+    let positionals = positional_args(args, &[]);
+    let (ticket, description) = match positionals.split_first() {
+        Some((ticket, rest)) => (ticket.clone(), rest.join(" ")),
+        None => (prompt_input("チケット番号 (例: PROJ-123): "), prompt_input("説明 (空でスキップ): ")),
+    };
+    if ticket.is_empty() { eprintln!("{}", "エラー: チケット番号必須。".red()); exit(1); }
+
+    let template = utils::config().branch_name_template.as_deref().unwrap_or(DEFAULT_BRANCH_NAME_TEMPLATE);
+    let name = branch_name_from_template(template, &ticket, &description);
+    if !GitCommand::check_ref_format_branch(&name).unwrap_or(false) {
+        eprintln!("エラー: '{}' はブランチ名として無効です (branch_name_template を確認してください)。", name.red()); exit(1);
+    }
+    if GitCommand::rev_parse_verify(&format!("refs/heads/{}", name)).unwrap_or(false) {
+        eprintln!("エラー: ブランチ '{}' は既にローカルに存在します。'mygit switch' で切り替えてください。", name.red()); exit(1);
+    }
+
+    let Some(default_branch) = get_default_branch() else {
+        exit_no_default_branch("");
+    };
+    let has_remote = GitCommand::remote_get_url(utils::remote()).is_ok();
+    if has_remote && GitCommand::fetch_branch(utils::remote(), &default_branch).is_err() {
+        log::warn!("警告: '{}/{}' を取得できませんでした。手元の情報を使います。", utils::remote(), default_branch);
+    }
+    let base = get_default_base_ref().unwrap_or(default_branch);
+    let previous_branch = get_current_branch_name();
+    handle_command_result_void(GitCommand::branch_create_local_from(&name, &base));
+    handle_command_result_void(GitCommand::checkout(&name));
+    println!("{}", format!("ブランチ '{}' を '{}' から作成し、切り替えました。", name, base).green());
+
+    if has_remote && (has_flag(args, &["--push"]) || confirm_with_default(&format!("ブランチ '{}' をリモート '{}' にプッシュし追跡設定しますか？", name, utils::remote()), true)) {
+        handle_command_result_void(GitCommand::push_u(utils::remote(), &name));
+        println!("ブランチ '{}' を '{}/{}' へプッシュし追跡設定しました。", name.cyan(), utils::remote(), name.blue());
+    }
+    run_post_checkout(args, &previous_branch);
+}
+
+// 回答順 (パイプ入力時): マージ方法 → (squash 時: コミットメッセージ) (→ コンフリクト時、rerere 無効なら: rerere 有効化確認) → プッシュ確認 → ローカルブランチ削除確認 → (リモートにある場合: リモートブランチ削除確認)
+pub fn git_finish(_args: &[String]) {
+    ensure_clean_state();
+    let Some(default_branch) = get_default_branch() else {
+        exit_no_default_branch("");
+    };
+    let feature = get_current_branch_name();
+    if feature.is_empty() || feature == default_branch {
+        eprintln!("{}", format!("エラー: 既定ブランチ以外の作業ブランチで実行してください (現在: '{}')。", if feature.is_empty() { "detached HEAD" } else { &feature }).red()); exit(1);
+    }
+    if !GitCommand::rev_parse_verify(&format!("refs/heads/{}", default_branch)).unwrap_or(false) {
+        eprintln!("エラー: ローカルに '{}' がありません。先に作成してください。", default_branch.red()); exit(1);
+    }
+    let commit_count = GitCommand::rev_list_count(&format!("{}..{}", default_branch, feature)).unwrap_or(0);
+    if commit_count == 0 { println!("{}", format!("'{}' には '{}' に無いコミットがありません。", feature, default_branch).yellow()); return; }
+
+    let method = prompt_select(&format!("'{}' ({} コミット) を '{}' に取り込む方法", feature, commit_count, default_branch), vec![
+        SelectOption::new("merge", "merge".to_string()).with_hint("マージコミットを作成"),
+        SelectOption::new("squash", "squash".to_string()).with_hint("1つのコミットにまとめる"),
+    ]);
+    let has_remote = GitCommand::remote_get_url(utils::remote()).is_ok();
+
+    handle_command_result_void(GitCommand::checkout(&default_branch));
+    if has_remote && GitCommand::fetch_branch(utils::remote(), &default_branch).is_ok()
+        && GitCommand::rev_parse_verify(&format!("refs/remotes/{}/{}", utils::remote(), default_branch)).unwrap_or(false) {
+        let mut updated = false;
+        handle_command_result(GitCommand::merge_ff_only(&format!("{}/{}", utils::remote(), default_branch)), |s| updated = s);
+        if !updated {
+            eprintln!("エラー: '{}' を '{}/{}' に fast-forward できません ('mygit default-check' で確認してください)。'{}' に戻ります。", default_branch.red(), utils::remote(), default_branch, feature);
+            handle_command_result_void(GitCommand::checkout(&feature));
+            exit(1);
+        }
+    }
+
+    let mut merged = false;
+    if method == "squash" {
+        handle_command_result(GitCommand::merge_squash(&feature), |s| merged = s);
+        if merged {
+            let msg = prompt_input("コミットメッセージ: ");
+            if msg.is_empty() { eprintln!("{}", "エラー: メッセージ必須。変更はステージされたままです。".red()); exit(1); }
+            check_commit_subject(&msg);
+            handle_command_result_void(GitCommand::commit(&msg));
+        }
+    } else {
+        handle_command_result(GitCommand::merge_no_ff(&feature), |s| merged = s);
+    }
+    if !merged {
+        log::warn!("警告: '{}' のマージ中にコンフリクトが発生しました。以降の手順 (プッシュ・削除) は中止しました。", feature);
+        report_rerere();
+        log::warn!("解決してコミットするか、'git merge --abort' で中止してください。");
+        exit(1);
+    }
+    println!("{}", format!("'{}' を '{}' に取り込みました。", feature, default_branch).green());
+
+    if has_remote && confirm(&format!("'{}' をリモート '{}' にプッシュしますか？", default_branch, utils::remote())) {
+        handle_command_result_void(GitCommand::push(utils::remote(), &default_branch));
+    }
+    if confirm(&format!("ローカルブランチ '{}' を削除しますか？", feature)) {
+        // 既定ブランチには取り込み済み。squash の場合や上流に未プッシュのコミットがある場合は -d では消せないため強制削除する
+        handle_command_result_void(GitCommand::branch_delete_local_force(&feature));
+        println!("ローカルブランチ '{}' を削除しました。", feature.truecolor(255,165,0)); // オレンジ
+    }
+    if has_remote && GitCommand::rev_parse_verify(&format!("refs/remotes/{}/{}", utils::remote(), feature)).unwrap_or(false)
+        && confirm(&format!("リモートブランチ '{}/{}' も削除しますか？", utils::remote(), feature)) {
+        handle_command_result_void(GitCommand::push_delete(utils::remote(), &feature));
+        println!("リモートブランチ '{}/{}' を削除しました。", utils::remote(), feature.blue());
+    }
+}
+
+// マージの挙動に影響する git の設定 (キー, 説明, 未設定時の git の既定値)
+const MERGE_CONFIG_KEYS: &[(&str, &str, &str)] = &[
+    ("merge.ff", "fast-forward", "true (可能なら fast-forward)"),
+    ("pull.rebase", "プル時のリベース", "false"),
+    ("pull.ff", "プル時の fast-forward", "true"),
+    ("merge.conflictStyle", "コンフリクト表示形式", "merge"),
+    ("rerere.enabled", "コンフリクト解決の記録/再利用 (rerere)", "false"),
+];
+
+// 回答順 (パイプ入力時): (未指定時: マージするブランチ (→ 手動入力時: 参照名))
+pub fn git_merge_info(args: &[String]) {
+    println!("{}", "マージ関連の設定:".bold());
+    for (key, label, default) in MERGE_CONFIG_KEYS {
+        match GitCommand::config_get(key) {
+            Ok(value) => println!("  {:<22} {} {}", key, value.cyan(), format!("({})", label).dimmed()),
+            Err(_) => println!("  {:<22} {} {}", key, format!("未設定 → {}", default).dimmed(), format!("({})", label).dimmed()),
+        }
+    }
+    let strategy = utils::config().update_strategy.map_or("merge (既定値)", |s| s.as_str());
+    println!("  {:<22} {} {}", "mygit update_strategy", strategy.cyan(), "(save などでの取り込み方法)".dimmed());
+
+    let current_branch = get_current_branch_name();
+    let target = positional_args(args, &[]).into_iter().next().unwrap_or_else(|| prompt_ref("マージするブランチ"));
+    let mut exists = false;
+    handle_command_result(GitCommand::rev_parse_verify(&format!("{}^{{commit}}", target)), |e| exists = e);
+    if !exists { eprintln!("エラー: '{}' はブランチまたはコミットとして無効です。", target.red()); exit(1); }
+
+    let head_label = if current_branch.is_empty() { "HEAD".to_string() } else { current_branch };
+    println!("\n'{}' を '{}' にマージする場合:", target.cyan(), head_label.cyan());
+    if GitCommand::is_ancestor(&target, "HEAD").unwrap_or(false) {
+        println!("{}", "  既に取り込み済みです (マージしても変化はありません)。".green());
+    } else if GitCommand::is_ancestor("HEAD", &target).unwrap_or(false) {
+        let count = GitCommand::rev_list_count(&format!("HEAD..{}", target)).unwrap_or(0);
+        if GitCommand::config_get("merge.ff").is_ok_and(|v| v == "false") {
+            println!("  fast-forward 可能ですが、merge.ff = false のためマージコミットが作られます ({} コミット)。", count);
+        } else {
+            println!("{}", format!("  fast-forward されます ({} コミット)。", count).green());
+        }
+    } else {
+        let mut base = String::new();
+        handle_command_result(GitCommand::merge_base("HEAD", &target), |b| base = b);
+        let (ours, theirs) = GitCommand::rev_list_left_right_count(&format!("HEAD...{}", target)).unwrap_or((0, 0));
+        println!("{}", "  分岐しているため、マージコミットが作られます (3-way マージ)。".yellow());
+        println!("  分岐点: {} ('{}' のみ {} / '{}' のみ {} コミット)", base.chars().take(7).collect::<String>().cyan(), head_label, ours, target, theirs);
+        if GitCommand::config_get("merge.ff").is_ok_and(|v| v == "only") {
+            println!("{}", "  merge.ff = only のため、このマージは拒否されます。".red());
+        }
+    }
+}
+
+// 回答順 (パイプ入力時): (--all 未指定時: 追跡するリモートブランチ (空白区切り))
+pub fn git_track(args: &[String]) {
+    let mut remotes_str = String::new();
+    handle_command_result(GitCommand::remote_list_str(), |s| remotes_str = s);
+    let remotes: Vec<&str> = remotes_str.lines().collect();
+    if remotes.is_empty() { eprintln!("{}", "エラー: リモートが設定されていません。".red()); exit(1); }
+    for remote in &remotes {
+        if GitCommand::fetch_prune(remote).is_err() { log::warn!("警告: リモート '{}' を取得できませんでした。手元の情報を使います。", remote); }
+    }
+
+    let mut refs_str = String::new();
+    handle_command_result(GitCommand::for_each_ref_remote_branches(), |s| refs_str = s);
+    let local_branches: std::collections::HashSet<String> = get_local_branch_names().into_iter().collect();
+    // (リモートブランチ, ローカルブランチ名)。リモート名に "/" を含む場合に備えて最長一致で分ける
+    let candidates: Vec<(String, String)> = refs_str.lines()
+        .filter_map(|remote_branch| {
+            let remote = remotes.iter().filter(|r| remote_branch.starts_with(&format!("{}/", r))).max_by_key(|r| r.len())?;
+            Some((remote_branch.to_string(), remote_branch[remote.len() + 1..].to_string()))
+        })
+        .filter(|(_, name)| !local_branches.contains(name))
+        .collect();
+    if candidates.is_empty() { println!("{}", "ローカルに無いリモートブランチはありません。".green()); return; }
+
+    let selected: Vec<String> = if has_flag(args, &["--all"]) {
+        candidates.iter().map(|(remote_branch, _)| remote_branch.clone()).collect()
+    } else {
+        let options = candidates.iter()
+            .map(|(remote_branch, _)| MultiSelectOption::new(remote_branch.clone(), remote_branch.clone()))
+            .collect();
+        prompt_multi_select("追跡するリモートブランチ", options)
+    };
+    if selected.is_empty() { println!("キャンセルしました。"); return; }
+
+    let (mut created, mut skipped) = (Vec::new(), Vec::new());
+    for remote_branch in &selected {
+        let Some((_, name)) = candidates.iter().find(|(r, _)| r == remote_branch) else { continue };
+        // 別のリモートの同名ブランチを先に作成した場合など
+        if GitCommand::rev_parse_verify(&format!("refs/heads/{}", name)).unwrap_or(false) {
+            skipped.push(format!("{} (ローカルに既に存在)", name));
+            continue;
+        }
+        handle_command_result_void(GitCommand::branch_track(name, remote_branch));
+        created.push(format!("{} → {}", name, remote_branch));
+    }
+    if !created.is_empty() {
+        println!("{}", format!("{} 個のローカルブランチを作成しました:", created.len()).green());
+        created.iter().for_each(|c| println!("  {}", c.cyan()));
+    }
+    if !skipped.is_empty() {
+        println!("{}", "作成しなかったブランチ:".yellow());
+        skipped.iter().for_each(|s| println!("  {}", s));
+    }
+}
+
+// ブランチと上流のそれぞれにしか無いコミットを表示する
+fn git_diverge_detail(branch: String) {
+    if branch.is_empty() { eprintln!("{}", "エラー: 現在のブランチ不明 (detached HEAD)。ブランチ名を指定してください。".red()); exit(1); }
+    let Ok(upstream) = GitCommand::upstream_of(&branch) else {
+        eprintln!("エラー: ブランチ '{}' に上流が設定されていません。", branch.red()); exit(1);
+    };
+    let print_section = |title: String, range: String| -> usize {
+        let mut log_str = String::new();
+        handle_command_result(GitCommand::log_range_oneline(&range), |s| log_str = s);
+        let commits: Vec<&str> = log_str.lines().collect();
+        println!("{} ({} 件):", title.bold(), commits.len());
+        if commits.is_empty() { println!("  {}", "(なし)".dimmed()); }
+        for line in &commits {
+            let mut parts = line.splitn(3, '\t');
+            let (_, short_sha, subject) = (parts.next(), parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+            println!("  {} {}", short_sha.yellow(), subject);
+        }
+        commits.len()
+    };
+    let local_only = print_section(format!("'{}' のみ (未プッシュ)", branch), format!("{}..{}", upstream, branch));
+    println!();
+    let remote_only = print_section(format!("'{}' のみ (未取り込み)", upstream), format!("{}..{}", branch, upstream));
+
+    println!();
+    match (local_only, remote_only) {
+        (0, 0) => println!("{}", format!("'{}' と '{}' は同期しています。", branch, upstream).green()),
+        (_, 0) => println!("{}", "プッシュすれば同期できます。".yellow()),
+        (0, _) => println!("{}", "fast-forward で取り込めます。".cyan()),
+        _ => {
+            let strategy = utils::config().update_strategy.unwrap_or(utils::UpdateStrategy::Merge);
+            println!("{}", format!("分岐しています。マージまたはリベースで統合してください (設定中の取り込み方法: {})。", strategy.as_str()).red());
+        }
+    }
+}
+
+// 回答順 (パイプ入力時): (未指定時: プッシュする最後のコミット) → プッシュ確認
+pub fn git_push_upto(args: &[String]) {
+    let branch = get_current_branch_name();
+    if branch.is_empty() { eprintln!("{}", "エラー: 現在のブランチ不明 (detached HEAD)。".red()); exit(1); }
+    if GitCommand::remote_get_url(utils::remote()).is_err() {
+        eprintln!("{}", format!("エラー: リモート '{}' が未設定。", utils::remote()).red()); exit(1);
+    }
+    let remote_ref = format!("{}/{}", utils::remote(), branch);
+    let pushed = GitCommand::rev_parse_verify(&format!("refs/remotes/{}", remote_ref)).unwrap_or(false);
+    // 未プッシュのコミットだけを候補にする (リモートにまだ無いブランチは既定ブランチからの分岐以降)
+    let base = match (pushed, get_default_base_ref()) {
+        (true, _) => Some(remote_ref.clone()),
+        (false, Some(base)) if base.trim_start_matches(&format!("{}/", utils::remote())) != branch => Some(base),
+        _ => None,
+    };
+    let range = base.as_ref().map_or("HEAD".to_string(), |base| format!("{}..HEAD", base));
+
+    let commit = match positional_args(args, &[]).into_iter().next() {
+        Some(commit) => commit,
+        None => {
+            let options = get_commit_select_options(&range, 50);
+            if options.is_empty() { println!("{}", format!("'{}' に未プッシュのコミットはありません。", branch).green()); return; }
+            prompt_select("どのコミットまでプッシュしますか", options)
+        }
+    };
+    let mut sha = String::new();
+    handle_command_result(GitCommand::rev_parse_commit_id(&format!("{}^{{commit}}", commit)), |id| sha = id);
+    if !GitCommand::is_ancestor(&sha, "HEAD").unwrap_or(false) {
+        eprintln!("エラー: '{}' は現在のブランチの履歴に含まれていません。", commit.red()); exit(1);
+    }
+    if pushed && !GitCommand::is_ancestor(&remote_ref, &sha).unwrap_or(false) {
+        eprintln!("エラー: '{}' は '{}' より新しくないため、fast-forward でプッシュできません。", commit.red(), remote_ref); exit(1);
+    }
+
+    let remaining = GitCommand::rev_list_count(&format!("{}..HEAD", sha)).unwrap_or(0);
+    let publishing_range = base.as_ref().map_or(sha.clone(), |base| format!("{}..{}", base, sha));
+    let publishing = GitCommand::rev_list_count(&publishing_range).unwrap_or(0);
+    println!("'{}' に {} コミットをプッシュします ({} まで)。", remote_ref.cyan(), publishing.to_string().bold(), sha.chars().take(7).collect::<String>().yellow());
+    if remaining > 0 {
+        println!("{}", format!("注意: 途中までの履歴を公開します。より新しい {} コミットはローカルにのみ残ります。", remaining).yellow());
+    }
+    if !confirm("プッシュしますか？") { println!("キャンセルしました。"); return; }
+    handle_command_result_void(GitCommand::push_ref_to_ref(utils::remote(), &format!("{}:refs/heads/{}", sha, branch)));
+    println!("{}", format!("'{}' を '{}' までプッシュしました。", remote_ref, sha.chars().take(7).collect::<String>()).green());
+    if !pushed { println!("上流は設定していません。全体をプッシュする際は 'mygit save' などで追跡設定してください。"); }
+}
+
+// bigfiles で表示する件数の既定値
+const BIGFILES_DEFAULT_TOP: usize = 10;
+
+pub fn git_bigfiles(args: &[String]) {
+    let top = match flag_value(args, &["--top", "-n"]) {
+        Some(value) => match value.parse::<usize>() {
+            Ok(n) if n > 0 => n,
+            _ => { eprintln!("エラー: --top には正の整数を指定してください: {}", value.red()); exit(1); }
+        },
+        None => BIGFILES_DEFAULT_TOP,
+    };
+    log::info!("履歴中のオブジェクトを調べています...");
+    let mut sizes_str = String::new();
+    handle_command_result(GitCommand::cat_file_all_blob_sizes(), |s| sizes_str = s);
+    let mut objects_str = String::new();
+    handle_command_result(GitCommand::rev_list_objects_all(), |s| objects_str = s);
+
+    // 同じ内容が複数のパスにある場合もあるので、blob ごとにパスをまとめる
+    let mut paths: std::collections::HashMap<&str, Vec<&str>> = std::collections::HashMap::new();
+    for line in objects_str.lines() {
+        if let Some((sha, path)) = line.split_once(' ') { paths.entry(sha).or_default().push(path); }
+    }
+    // 到達できない blob (reflog やスタッシュのみ、削除済みブランチなど) は対象外
+    let mut blobs: Vec<(&str, u64)> = sizes_str.lines()
+        .filter_map(|line| { let (sha, size) = line.split_once(' ')?; Some((sha, size.parse().ok()?)) })
+        .filter(|(sha, _)| paths.contains_key(sha))
+        .collect();
+    if blobs.is_empty() { println!("{}", "履歴にファイルはありません。".yellow()); return; }
+    blobs.sort_by_key(|(_, size)| std::cmp::Reverse(*size));
+
+    let mut tracked_str = String::new();
+    handle_command_result(GitCommand::ls_files(), |s| tracked_str = s);
+    let tracked: std::collections::HashSet<&str> = tracked_str.lines().collect();
+    let total: u64 = blobs.iter().map(|(_, size)| size).sum();
+    println!("{} (全 {} 個, 合計 {}):", format!("大きなファイル 上位 {} 件", top.min(blobs.len())).bold(), blobs.len(), utils::format_size(total));
+    for (sha, size) in blobs.iter().take(top) {
+        let blob_paths = &paths[sha];
+        let state = if blob_paths.iter().any(|p| tracked.contains(p)) { String::new() } else { " (現在の作業ツリーには無し)".dimmed().to_string() };
+        println!("  {:>10}  {}{}", utils::format_size(*size).yellow().bold(), blob_paths.join(", ").cyan(), state);
+        let commits = GitCommand::log_find_object(sha).unwrap_or_default();
+        // 新しい順なので最後の行が最初に追加したコミット
+        if let Some((short_sha, subject)) = commits.lines().last().and_then(|l| l.split_once('\t')) {
+            let more = commits.lines().count().saturating_sub(1);
+            let more = if more > 0 { format!(" (他 {} コミットで追加/削除)", more) } else { String::new() };
+            println!("  {:>10}  {} {} {}{}", "", "追加:".dimmed(), short_sha.yellow(), subject, more.dimmed());
+        }
+    }
+    println!("{}", "履歴から削除するには git filter-repo などで履歴を書き換える必要があります。".dimmed());
+}
+
+// 一覧表示するファイル数の上限 (それ以上は件数のみ)
+const UNTRACK_PREVIEW_MAX: usize = 20;
+
+// 回答順 (パイプ入力時): (未指定時: パターン) → 実行確認
+pub fn git_untrack(args: &[String]) {
+    let pattern = positional_args(args, &[]).into_iter().next().unwrap_or_else(|| prompt_input("追跡をやめるファイルのパターン (例: target, *.log): "));
+    if pattern.is_empty() { eprintln!("{}", "エラー: パターン必須。".red()); exit(1); }
+    let mut files_str = String::new();
+    handle_command_result(GitCommand::ls_files_matching(&pattern), |s| files_str = s);
+    let files: Vec<String> = files_str.lines().map(|l| l.to_string()).collect();
+
+    let mut root = String::new();
+    handle_command_result(GitCommand::show_toplevel(), |r| root = r);
+    let gitignore = std::path::Path::new(&root).join(".gitignore");
+    let ignore_content = std::fs::read_to_string(&gitignore).unwrap_or_default();
+    let already_ignored = ignore_content.lines().any(|l| l.trim() == pattern);
+    if files.is_empty() && already_ignored {
+        println!("{}", format!("'{}' に一致する追跡中のファイルは無く、.gitignore にも登録済みです。", pattern).green()); return;
+    }
+
+    if files.is_empty() {
+        println!("{}", format!("'{}' に一致する追跡中のファイルはありません。", pattern).yellow());
+    } else {
+        println!("{}", format!("追跡をやめるファイル ({} 件、作業ツリーには残ります):", files.len()).bold());
+        files.iter().take(UNTRACK_PREVIEW_MAX).for_each(|f| println!("  {}", f.red()));
+        if files.len() > UNTRACK_PREVIEW_MAX { println!("  {}", format!("... 他 {} 件", files.len() - UNTRACK_PREVIEW_MAX).dimmed()); }
+    }
+    if !already_ignored { println!(".gitignore に追加: {}", pattern.cyan()); }
+    if !confirm("実行しますか？") { println!("キャンセルしました。"); return; }
+
+    if !files.is_empty() {
+        handle_command_result_void(GitCommand::rm_cached(&files));
+    }
+    if !already_ignored {
+        let separator = if ignore_content.is_empty() || ignore_content.ends_with('\n') { "" } else { "\n" };
+        if let Err(e) = std::fs::write(&gitignore, format!("{}{}{}\n", ignore_content, separator, pattern)) {
+            eprintln!("エラー: {} に書き込めません。詳細: {}", gitignore.display(), e); exit(1);
+        }
+    }
+    println!("{}", format!("{} 件のファイルの追跡をやめました。次の save で削除と .gitignore の変更がコミットされます。", files.len()).green());
+    if !files.is_empty() {
+        println!("{}", "注意: このコミットを取り込んだ他のクローンでは、これらのファイルが作業ツリーから削除されます。".yellow());
+    }
+}
+
+// 回答順 (パイプ入力時): (未指定時: 比較するブランチ)
+pub fn git_changed_dirs(args: &[String]) {
+    let depth = match flag_value(args, &["--depth"]) {
+        Some(value) => match value.parse::<usize>() {
+            Ok(n) if n > 0 => n,
+            _ => { eprintln!("エラー: --depth には正の整数を指定してください: {}", value.red()); exit(1); }
+        },
+        None => 1,
+    };
+    let current_branch = get_current_branch_name();
+    let target = match positional_args(args, &["--depth"]).into_iter().next() {
+        Some(target) => target,
+        None => {
+            let options: Vec<SelectOption<String>> = get_local_branch_names().into_iter()
+                .filter(|name| *name != current_branch)
+                .map(|name| SelectOption::new(name.clone(), name))
+                .collect();
+            if options.is_empty() { println!("{}", "比較できる他のブランチがありません。".yellow()); return; }
+            prompt_select("比較するブランチ", options)
+        }
+    };
+    let mut exists = false;
+    handle_command_result(GitCommand::rev_parse_verify(&format!("{}^{{commit}}", target)), |e| exists = e);
+    if !exists { eprintln!("エラー: '{}' はブランチまたはコミットとして無効です。", target.red()); exit(1); }
+
+    let mut files_str = String::new();
+    handle_command_result(GitCommand::diff_name_only(&format!("HEAD...{}", target)), |s| files_str = s);
+    let files: Vec<&str> = files_str.lines().collect();
+    if files.is_empty() { println!("{}", format!("'{}' には分岐点以降の変更がありません。", target).green()); return; }
+
+    // ファイルの親ディレクトリを depth 階層までで切る (最上位のファイルは "." にまとめる)
+    let mut counts: std::collections::BTreeMap<String, usize> = std::collections::BTreeMap::new();
+    for file in &files {
+        let dirs: Vec<&str> = file.split('/').collect();
+        let dir = if dirs.len() == 1 { ".".to_string() } else { dirs[..dirs.len().saturating_sub(1).min(depth)].join("/") + "/" };
+        *counts.entry(dir).or_default() += 1;
+    }
+    let mut sorted: Vec<(String, usize)> = counts.into_iter().collect();
+    sorted.sort_by_key(|(dir, count)| (std::cmp::Reverse(*count), dir.clone()));
+
+    let head_label = if current_branch.is_empty() { "HEAD".to_string() } else { current_branch };
+    println!("'{}' で変更されたファイル ('{}' との分岐点以降, {} ファイル / {} ディレクトリ):", target.cyan(), head_label, files.len(), sorted.len());
+    let width = sorted.iter().map(|(_, count)| count.to_string().len()).max().unwrap_or(1);
+    let dir_width = sorted.iter().map(|(dir, _)| dir.chars().count()).max().unwrap_or(1);
+    for (dir, count) in &sorted {
+        let bar = "■".repeat((count * 30).div_ceil(files.len()));
+        println!("  {:>width$}  {:<dir_width$}  {}", count.to_string().bold(), dir.cyan(), bar.dimmed(), width = width, dir_width = dir_width);
+    }
+}
+
+// 回答順 (パイプ入力時): (未指定時: 正規表現 → 置換後の文字列) → 実行確認
+pub fn git_rename_batch(args: &[String]) {
+    let positionals = positional_args(args, &[]);
+    let (pattern, replacement) = match positionals.as_slice() {
+        [pattern, replacement, ..] => (pattern.clone(), replacement.clone()),
+        _ => (prompt_input("対象のブランチ名の正規表現: "), prompt_input("置換後の文字列 ($1 などでグループを参照): ")),
+    };
+    let regex = match regex::Regex::new(&pattern) {
+        Ok(regex) => regex,
+        Err(e) => { eprintln!("エラー: 正規表現 '{}' が無効です。詳細: {}", pattern.red(), e); exit(1); }
+    };
+    let branches = get_local_branch_names();
+    let renames: Vec<(String, String)> = branches.iter()
+        .filter(|name| regex.is_match(name))
+        .map(|name| (name.clone(), regex.replace(name, replacement.as_str()).into_owned()))
+        .filter(|(old, new)| old != new)
+        .collect();
+    if renames.is_empty() { println!("{}", format!("'{}' に一致して名前が変わるブランチはありません。", pattern).yellow()); return; }
+
+    // 改名先が既存のブランチ (改名元を含む)・他の改名先と重なる場合や、無効な名前になる場合は実行しない
+    let mut problems = Vec::new();
+    for (i, (old, new)) in renames.iter().enumerate() {
+        if !GitCommand::check_ref_format_branch(new).unwrap_or(false) {
+            problems.push(format!("{} → {}: ブランチ名として無効です", old, new));
+        } else if branches.contains(new) {
+            problems.push(format!("{} → {}: '{}' は既に存在します", old, new, new));
+        } else if renames[..i].iter().any(|(_, other)| other == new) {
+            problems.push(format!("{} → {}: 他のブランチの改名先と重なります", old, new));
+        }
+    }
+    let width = renames.iter().map(|(old, _)| old.chars().count()).max().unwrap_or(0);
+    println!("{}", format!("改名するブランチ ({} 件):", renames.len()).bold());
+    renames.iter().for_each(|(old, new)| println!("  {:<width$} → {}", old, new.cyan(), width = width));
+    if !problems.is_empty() {
+        eprintln!("{}", "エラー: 次の改名ができないため、何も変更しませんでした:".red());
+        problems.iter().for_each(|p| eprintln!("  {}", p));
+        exit(1);
+    }
+
+    let rename_remote = has_flag(args, &["--push"]);
+    if rename_remote && GitCommand::remote_get_url(utils::remote()).is_err() {
+        eprintln!("{}", format!("エラー: リモート '{}' が未設定。", utils::remote()).red()); exit(1);
+    }
+    let remote_targets: Vec<&(String, String)> = renames.iter()
+        .filter(|(old, _)| rename_remote && GitCommand::rev_parse_verify(&format!("refs/remotes/{}/{}", utils::remote(), old)).unwrap_or(false))
+        .collect();
+    if !remote_targets.is_empty() {
+        println!("{}", format!("{} 上の {} 件のブランチも改名します (他の人の作業に影響する場合があります)。", utils::remote(), remote_targets.len()).yellow());
+    }
+    if !confirm("改名しますか？") { println!("キャンセルしました。"); return; }
+
+    for (old, new) in &renames {
+        handle_command_result_void(GitCommand::branch_rename(old, new));
+    }
+    println!("{}", format!("{} 件のブランチを改名しました。", renames.len()).green());
+    for (old, new) in remote_targets {
+        // 新しい名前でプッシュして追跡設定し直してから、古い名前を消す
+        handle_command_result_void(GitCommand::push_u(utils::remote(), new));
+        handle_command_result_void(GitCommand::push_delete(utils::remote(), old));
+        println!("  {}/{} → {}/{}", utils::remote(), old, utils::remote(), new.blue());
+    }
+}
+
+const LOG_DEFAULT_COUNT: usize = 20;
+
+pub fn git_log(args: &[String]) {
+    let count = match flag_value(args, &["--count", "-n"]) {
+        Some(value) => match value.parse::<usize>() {
+            Ok(n) if n > 0 => n,
+            _ => { eprintln!("エラー: --count には正の整数を指定してください: {}", value.red()); exit(1); }
+        },
+        None => LOG_DEFAULT_COUNT,
+    };
+    handle_command_result_void(GitCommand::log_graph(count, has_flag(args, &["--all"])));
+}
+
+// 補完候補にするコマンド名と組み込みエイリアス、説明 (最初の一文)
+fn completion_words() -> Vec<(&'static str, &'static str)> {
+    crate::COMMAND_DEFINITIONS.iter()
+        .flat_map(|c| std::iter::once(c.name).chain(c.aliases.iter().copied()).map(move |word| (word, c.description)))
+        .map(|(word, description)| (word, description.split('。').next().unwrap_or(description)))
+        .collect()
+}
+
+// "--yes, -y" や "--path <dir>" からオプション名だけを取り出す
+fn completion_global_options() -> Vec<&'static str> {
+    GLOBAL_OPTIONS.iter()
+        .flat_map(|(option, _)| option.split(", "))
+        .filter_map(|option| option.split_whitespace().next())
+        .collect()
+}
+
+// 値を取るグローバルオプション (例: --remote <name>)。補完でコマンド名の位置を探すときに値ごと読み飛ばす
+fn completion_value_options() -> Vec<&'static str> {
+    GLOBAL_OPTIONS.iter()
+        .filter(|(option, _)| option.contains('<'))
+        .filter_map(|(option, _)| option.split_whitespace().next())
+        .collect()
+}
+
+// コマンド名を補完するのは、オプションでもオプションの値でもない最初の単語の位置 (例: mygit --remote up sw<TAB>)
+fn completion_script(shell: &str) -> Option<String> {
+    let words = completion_words();
+    let names = words.iter().map(|(word, _)| *word).collect::<Vec<_>>().join(" ");
+    let options = completion_global_options().join(" ");
+    let value_options = completion_value_options();
+    let script = match shell {
+        "bash" => format!(
+            "_mygit() {{\n    local cur=\"${{COMP_WORDS[COMP_CWORD]}}\" i=1\n    while [ \"$i\" -lt \"$COMP_CWORD\" ]; do\n        case \"${{COMP_WORDS[i]}}\" in\n            {}) i=$((i + 2)); continue ;;\n            -*) ;;\n            *) break ;;\n        esac\n        i=$((i + 1))\n    done\n    if [[ \"$cur\" == -* ]]; then\n        COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))\n    elif [ \"$i\" -eq \"$COMP_CWORD\" ]; then\n        COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))\n    fi\n}}\ncomplete -F _mygit mygit\n",
+            value_options.join("|"), options, names),
+        "zsh" => {
+            let entries: Vec<String> = words.iter()
+                .map(|(word, description)| format!("    '{}:{}'", word, description.replace('\'', "'\\''").replace(':', "\\:")))
+                .collect();
+            format!(
+                "#compdef mygit\n\n_mygit() {{\n  local -a commands\n  commands=(\n{}\n  )\n  local i=2\n  while (( i < CURRENT )); do\n    case $words[i] in\n      ({}) (( i += 2 )); continue ;;\n      (-*) ;;\n      (*) break ;;\n    esac\n    (( i++ ))\n  done\n  if [[ \"$words[CURRENT]\" == -* ]]; then\n    compadd -- {}\n  elif (( i == CURRENT )); then\n    _describe 'command' commands\n  fi\n}}\n\n_mygit \"$@\"\n",
+                entries.join("\n"), value_options.join("|"), options)
+        }
+        "fish" => {
+            let mut lines = vec![
+                "complete -c mygit -f".to_string(),
+                format!("function __mygit_needs_command\n    set -l tokens (commandline -opc)\n    set -e tokens[1]\n    set -l skip 0\n    for token in $tokens\n        if test $skip -eq 1\n            set skip 0\n        else if contains -- $token {}\n            set skip 1\n        else if not string match -q -- '-*' $token\n            return 1\n        end\n    end\n    test $skip -eq 0\nend",
+                    value_options.join(" ")),
+            ];
+            lines.extend(words.iter().map(|(word, description)| {
+                format!("complete -c mygit -n '__mygit_needs_command' -a {} -d '{}'", word, description.replace('\\', "\\\\").replace('\'', "\\'"))
+            }));
+            lines.extend(completion_global_options().iter().map(|option| match option.strip_prefix("--") {
+                Some(long) => format!("complete -c mygit -l {}", long),
+                None => format!("complete -c mygit -s {}", option.trim_start_matches('-')),
+            }));
+            lines.join("\n") + "\n"
+        }
+        "powershell" => format!(
+            "Register-ArgumentCompleter -Native -CommandName mygit -ScriptBlock {{\n    param($wordToComplete, $commandAst, $cursorPosition)\n    $needsCommand = $true\n    $skip = $false\n    foreach ($element in ($commandAst.CommandElements | Select-Object -Skip 1 | Where-Object {{ $_.Extent.EndOffset -lt $cursorPosition }})) {{\n        $token = $element.ToString()\n        if ($skip) {{ $skip = $false; continue }}\n        if (('{}' -split ' ') -contains $token) {{ $skip = $true }}\n        elseif ($token -notlike '-*') {{ $needsCommand = $false; break }}\n    }}\n    if ($skip) {{ $needsCommand = $false }}\n    $candidates = if ($wordToComplete -like '-*') {{ '{}' }} elseif ($needsCommand) {{ '{}' }} else {{ '' }}\n    $candidates -split ' ' | Where-Object {{ $_ -and $_ -like \"$wordToComplete*\" }} | ForEach-Object {{\n        [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)\n    }}\n}}\n",
+            value_options.join(" "), options, names),
+        _ => return None,
+    };
+    Some(script)
+}
+
+// 標準出力にそのまま読み込める補完スクリプトを出す (例: mygit completions zsh > _mygit)
+pub fn git_completions(args: &[String]) {
+    let positionals = positional_args(args, &[]);
+    let shell = positionals.first().map(|s| s.as_str()).unwrap_or("");
+    match completion_script(shell) {
+        Some(script) => print!("{}", script),
+        None => {
+            eprintln!("{} {} completions {{bash|zsh|fish|powershell}}", "Usage:".bold(), "mygit".green());
+            exit(1);
+        }
+    }
+}
+
+// 回答順 (パイプ入力時): (未指定時: 改名するブランチ → 新しいブランチ名) → (リモートに旧名がある場合: リモートも改名するか確認)
+pub fn git_rename(args: &[String]) {
+    let positionals = positional_args(args, &[]);
+    let old = match positionals.first() {
+        Some(name) => name.clone(),
+        None => {
+            let current = get_current_branch_name();
+            let options: Vec<SelectOption<String>> = get_local_branch_names().into_iter()
+                .map(|name| {
+                    let label = if name == current { format!("{} (現在のブランチ)", name) } else { name.clone() };
+                    SelectOption::new(label, name)
+                })
+                .collect();
+            if options.is_empty() { eprintln!("{}", "エラー: ローカルブランチがありません。".red()); exit(1); }
+            prompt_select("改名するブランチ", options)
+        }
+    };
+    if !GitCommand::rev_parse_verify(&format!("refs/heads/{}", old)).unwrap_or(false) {
+        eprintln!("エラー: ローカルブランチ '{}' が見つかりません。", old.red()); exit(1);
+    }
+    let new = match positionals.get(1) {
+        Some(name) => name.clone(),
+        None => prompt_input_with_default("新しいブランチ名: ", &old),
+    };
+    if new.is_empty() { eprintln!("{}", "エラー: 新ブランチ名必須。".red()); exit(1); }
+    if new == old { println!("{}", "ブランチ名が変わらないため、何もしませんでした。".yellow()); return; }
+    ensure_branch_not_exists(&new);
+
+    let old_remote = format!("{}/{}", utils::remote(), old);
+    // 改名しても上流の設定は引き継がれるので、旧名のリモートブランチを追跡していたかを先に確認する
+    let tracks_old_remote = GitCommand::upstream_of(&old).is_ok_and(|upstream| upstream == old_remote)
+        && GitCommand::rev_parse_verify(&format!("refs/remotes/{}", old_remote)).unwrap_or(false);
+
+    handle_command_result_void(GitCommand::branch_rename(&old, &new));
+    println!("{}", format!("ブランチ '{}' を '{}' に改名しました。", old, new).green());
+
+    if tracks_old_remote {
+        println!("{}", format!("リモートには旧名のブランチ '{}' が残っています。", old_remote).yellow());
+        if confirm(&format!("'{}/{}' をプッシュして追跡し、'{}' を削除しますか？", utils::remote(), new, old_remote)) {
+            handle_command_result_void(GitCommand::push_u(utils::remote(), &new));
+            handle_command_result_void(GitCommand::push_delete(utils::remote(), &old));
+            println!("リモートブランチも '{}/{}' に改名しました。", utils::remote(), new.blue());
+        } else {
+            println!("リモートはそのままです。上流は引き続き '{}' を指しています。", old_remote);
+        }
+    }
+}
+
+const RESET_MODES: &[(&str, &str)] = &[
+    ("soft", "変更をステージに残す (--soft)"),
+    ("mixed", "変更を作業ツリーに残す (--mixed)"),
+    ("hard", "変更もすべて破棄する (--hard)"),
+];
+
+// 回答順 (パイプ入力時): (モード未指定時: 取り消し方 (soft/mixed/hard) →) 実行確認 (→ プッシュ済みの場合: 書き換え確認) (--dry-run 時は確認をすべて y とみなす)
+pub fn git_reset(args: &[String]) {
+    ensure_no_operation_in_progress();
+    let count = match flag_value(args, &["--count", "-n"]) {
+        Some(value) => match value.parse::<usize>() {
+            Ok(n) if n > 0 => n,
+            _ => { eprintln!("エラー: --count には正の整数を指定してください: {}", value.red()); exit(1); }
+        },
+        None => 1,
+    };
+    let target = format!("HEAD~{}", count);
+    if !GitCommand::rev_parse_verify(&format!("{}^{{commit}}", target)).unwrap_or(false) {
+        eprintln!("エラー: {} 個前のコミットがありません。", count.to_string().red()); exit(1);
+    }
+
+    let mut commits_str = String::new();
+    handle_command_result(GitCommand::log_oneline("HEAD", count), |s| commits_str = s);
+    println!("{}", format!("取り消すコミット ({} 件):", count).bold());
+    for line in commits_str.lines() {
+        let parts: Vec<&str> = line.splitn(3, '\t').collect();
+        println!("  {} {}", parts.get(1).copied().unwrap_or("").yellow(), parts.get(2).copied().unwrap_or(""));
+    }
+
+    let mode = match RESET_MODES.iter().find(|(mode, _)| has_flag(args, &[&format!("--{}", mode)])) {
+        Some((mode, _)) => mode.to_string(),
+        None => {
+            let options = RESET_MODES.iter().map(|(mode, label)| SelectOption::new(label.to_string(), mode.to_string())).collect();
+            prompt_select("取り消し方", options)
+        }
+    };
+    if mode == "hard" {
+        println!("{}", "警告: --hard は取り消すコミットの内容と、未コミットの変更をすべて破棄します。".red().bold());
+        println!("{}", "破棄した内容は git reflog から探さない限り戻せません。".red());
+    }
+    let dry_run = has_flag(args, &["--dry-run"]);
+    let git: &dyn GitCommandTrait = if dry_run { &DryRunGitCommand } else { &GitCommand };
+    if !confirm_with(git, &format!("{} 件のコミットを --{} で取り消しますか？", count, mode)) { println!("キャンセルしました。"); return; }
+    if is_head_pushed() && !confirm_with(git, &"取り消すコミットは既にリモートにあります。履歴を書き換えて強制プッシュが必要になりますが、続けますか？".red().to_string()) {
+        println!("キャンセルしました。"); return;
+    }
+
+    handle_command_result_void(git.reset_head(&mode, &target));
+    if dry_run {
+        let effect = match mode.as_str() {
+            "soft" => "取り消した変更はステージに残ります",
+            "mixed" => "取り消した変更は作業ツリーに残ります",
+            _ => "取り消した変更と未コミットの変更はすべて破棄されます",
+        };
+        println!("{}", format!("{}。--dry-run のため、何も変更していません。", effect).dimmed());
+        return;
+    }
+    println!("{}", format!("{} 件のコミットを取り消しました (--{})。", count, mode).green());
+}
+
+// 回答順 (パイプ入力時): (未コミットの変更がある場合: handle_uncommitted_changes_before_action を参照 →) (→ コンフリクト時: (rerere 無効時: rerere 有効化確認 →) ブランチ作成確認 → ブランチ名)
+pub fn git_pull(args: &[String]) {
+    ensure_no_operation_in_progress();
+    let current_branch = get_current_branch_name();
+    if current_branch.is_empty() { eprintln!("{}", "エラー: 現在のブランチ不明。".red()); exit(1); }
+    if GitCommand::remote_get_url(utils::remote()).is_err() {
+        eprintln!("{}", format!("エラー: リモート '{}' が未設定。", utils::remote()).red()); exit(1);
+    }
+    if !handle_uncommitted_changes_before_action("プル") { return; }
+    println!("'{}/{}' をプルします。", utils::remote(), current_branch.cyan());
+    pull_current_branch(args, &current_branch);
+}
+
+pub fn git_sync(_args: &[String]) {
+    ensure_no_operation_in_progress();
+    if GitCommand::remote_get_url(utils::remote()).is_err() {
+        eprintln!("{}", format!("エラー: リモート '{}' が未設定。", utils::remote()).red()); exit(1);
+    }
+    let mut pruned = Vec::new();
+    handle_command_result(GitCommand::fetch_prune_report(utils::remote()), |p| pruned = p);
+    if !pruned.is_empty() { println!("{}", format!("削除された追跡ブランチ: {}", pruned.join(", ")).dimmed()); }
+
+    let current_branch = get_current_branch_name();
+    let mut results: Vec<(String, String)> = Vec::new();
+    for branch in get_local_branch_names() {
+        let mut local_id = String::new();
+        handle_command_result(GitCommand::rev_parse_commit_id(&branch), |id| local_id = id);
+        let (status, _) = get_branch_display_status(&branch, &local_id);
+        let result = match status {
+            BranchDisplayStatus::Synced => "最新".green().to_string(),
+            BranchDisplayStatus::Ahead { ahead } => format!("最新 (未プッシュ {} 件)", ahead).green().to_string(),
+            BranchDisplayStatus::Diverged { ahead, behind } => format!("スキップ (分岐 {})", format_ahead_behind(ahead, behind)).red().to_string(),
+            BranchDisplayStatus::Behind { behind } => {
+                let updated = if branch == current_branch {
+                    GitCommand::merge_ff_only(&format!("{}/{}", utils::remote(), branch)).unwrap_or(false)
+                } else {
+                    // チェックアウトしていないブランチは作業ツリーに触れずに更新する (他のワークツリーで使用中なら失敗する)
+                    GitCommand::fetch_fast_forward(utils::remote(), &branch).is_ok()
+                };
+                if updated {
+                    format!("更新 ({} コミット)", behind).cyan().to_string()
+                } else {
+                    "スキップ (fast-forward できません)".yellow().to_string()
+                }
+            }
+            _ => continue, // リモートに同名ブランチが無いものは対象外
+        };
+        results.push((branch, result));
+    }
+
+    if results.is_empty() { println!("リモート '{}' に対応するローカルブランチがありません。", utils::remote()); return; }
+    let width = results.iter().map(|(name, _)| utils::visible_width(name)).max().unwrap_or(0);
+    println!("\n同期結果 (リモート '{}'):", utils::remote());
+    for (name, result) in &results {
+        let marker = if *name == current_branch { "*" } else { " " };
+        println!("{} {}{}  {}", marker, name, " ".repeat(width - utils::visible_width(name)), result);
+    }
+}
+
+// 回答順 (パイプ入力時): 中止確認
+pub fn git_undo(_args: &[String]) {
+    let Some(operation) = operation_in_progress() else {
+        println!("取り消せる途中の操作 (merge/rebase/cherry-pick) はありません。");
+        return;
+    };
+    println!("{}", format!("途中の {} があります。", operation).yellow());
+    if !confirm(&format!("{} を中止して、開始前の状態に戻しますか？", operation)) { println!("キャンセルしました。"); return; }
+    let result = match operation {
+        "merge" => GitCommand::merge_abort(),
+        "rebase" => GitCommand::rebase_abort(),
+        _ => GitCommand::cherry_pick_abort(),
+    };
+    handle_command_result_void(result);
+    println!("{}", format!("{} を中止しました。", operation).green());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::MockGitCommand;
+
+    fn status_of(git: &MockGitCommand) -> BranchDisplayStatus {
+        branch_display_status_with(git, "origin", "main", "a1")
+    }
+
+    #[test]
+    fn branch_status_synced_when_ids_match() {
+        let git = MockGitCommand::new().branch("main", "a1").commit("origin/main", "a1");
+        assert_eq!(status_of(&git), BranchDisplayStatus::Synced);
+    }
+
+    #[test]
+    fn branch_status_counts_ahead_behind_and_diverged() {
+        let base = || MockGitCommand::new().branch("main", "a1").commit("origin/main", "b2");
+        assert_eq!(status_of(&base().count("b2..a1", 2).count("a1..b2", 0)), BranchDisplayStatus::Ahead { ahead: 2 });
+        assert_eq!(status_of(&base().count("b2..a1", 0).count("a1..b2", 3)), BranchDisplayStatus::Behind { behind: 3 });
+        assert_eq!(status_of(&base().count("b2..a1", 1).count("a1..b2", 4)), BranchDisplayStatus::Diverged { ahead: 1, behind: 4 });
+    }
+
+    #[test]
+    fn branch_status_local_only_without_remote_branch_or_counts() {
+        assert_eq!(status_of(&MockGitCommand::new().branch("main", "a1")), BranchDisplayStatus::LocalOnly);
+        // rev-list が失敗した場合は判定できないため LocalOnly
+        let git = MockGitCommand::new().branch("main", "a1").commit("origin/main", "b2");
+        assert_eq!(status_of(&git), BranchDisplayStatus::LocalOnly);
+    }
+
+    #[test]
+    fn branch_merged_checks_upstream_when_set() {
+        // HEAD にはマージ済みでも、上流より進んでいれば git branch -d は拒否する
+        let git = MockGitCommand::new().upstream("feature", "origin/feature").ancestor("feature", "HEAD");
+        assert!(!is_branch_merged_with(&git, "feature"));
+        let git = MockGitCommand::new().upstream("feature", "origin/feature").ancestor("feature", "origin/feature");
+        assert!(is_branch_merged_with(&git, "feature"));
+    }
+
+    #[test]
+    fn branch_merged_checks_head_without_upstream() {
+        assert!(is_branch_merged_with(&MockGitCommand::new().ancestor("feature", "HEAD"), "feature"));
+        assert!(!is_branch_merged_with(&MockGitCommand::new(), "feature"));
+    }
+
+    #[test]
+    fn delete_local_branch_uses_d_for_merged_branch() {
+        let git = MockGitCommand::new().ancestor("feature", "HEAD");
+        assert!(delete_local_branch(&git, "feature", false));
+        assert_eq!(git.calls.borrow().as_slice(), ["branch -d feature"]);
+    }
+
+    #[test]
+    fn delete_local_branch_force_skips_merge_check() {
+        let git = MockGitCommand::new();
+        assert!(delete_local_branch(&git, "feature", true));
+        assert_eq!(git.calls.borrow().as_slice(), ["branch -D feature"]);
+    }
+
+    #[test]
+    fn stash_list_parses_refs_and_messages() {
+        let git = MockGitCommand::new()
+            .stash("stash@{0}", "On main: mygit: switch の前に退避")
+            .stash("stash@{1}", "WIP on feature: 1a2b3c4 ログイン画面");
+        assert_eq!(stash_entries_with(&git), vec![
+            ("stash@{0}".to_string(), "On main: mygit: switch の前に退避".to_string()),
+            ("stash@{1}".to_string(), "WIP on feature: 1a2b3c4 ログイン画面".to_string()),
+        ]);
+        let lines = stash_list_lines_with(&git);
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].contains("stash@{0}") && lines[1].contains("switch の前に退避"));
+    }
+
+    #[test]
+    fn stash_list_empty_prints_message() {
+        let lines = stash_list_lines_with(&MockGitCommand::new());
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].contains("スタッシュはありません。"));
+    }
+
+    #[test]
+    fn stash_push_skips_untracked_only_changes() {
+        let git = MockGitCommand::new().status("?? notes.txt");
+        git_stash_push(&git, Some("wip".to_string()));
+        assert!(git.calls.borrow().is_empty());
+        let git = MockGitCommand::new().status(" M src/main.rs");
+        git_stash_push(&git, Some("wip".to_string()));
+        assert_eq!(git.calls.borrow().as_slice(), ["stash push -m wip"]);
+    }
+
+    #[test]
+    fn branch_input_resolves_local_remote_and_commits() {
+        let git = MockGitCommand::new()
+            .branch("feature", "aaa")
+            .commit("refs/remotes/upstream/team/x", "bbb")
+            .commit("v1.0", "ccc");
+        assert_eq!(resolve_branch_input(&git, "upstream", "feature"), BranchInput::Branch);
+        assert_eq!(resolve_branch_input(&git, "upstream", "team/x"), BranchInput::Branch);
+        // 別のリモートのブランチは対象外
+        assert_eq!(resolve_branch_input(&git, "origin", "team/x"), BranchInput::Unknown);
+        assert_eq!(resolve_branch_input(&git, "upstream", "v1.0"), BranchInput::Commit);
+        assert_eq!(resolve_branch_input(&git, "upstream", "typo"), BranchInput::Unknown);
+    }
+
+    #[test]
+    fn uncommitted_changes_prompt_skipped_when_clean() {
+        // 未追跡ファイルだけなら確認せずに続行し、何も変更しない
+        let git = MockGitCommand::new().status("?? notes.txt").status_v2("? notes.txt");
+        assert!(handle_uncommitted_changes_before_action_with(&git, "切り替え"));
+        assert!(git.calls.borrow().is_empty());
+    }
+
+    #[test]
+    fn upstream_track_parses_counts() {
+        // 上流と一致している場合、%(upstream:track) は空になる
+        assert_eq!(parse_upstream_track(""), BranchDisplayStatus::Synced);
+        assert_eq!(parse_upstream_track("[ahead 2]"), BranchDisplayStatus::Ahead { ahead: 2 });
+        assert_eq!(parse_upstream_track("[behind 3]"), BranchDisplayStatus::Behind { behind: 3 });
+        assert_eq!(parse_upstream_track("[ahead 1, behind 4]"), BranchDisplayStatus::Diverged { ahead: 1, behind: 4 });
+        assert_eq!(parse_upstream_track("[gone]"), BranchDisplayStatus::LocalOnly);
+    }
+
+    #[test]
+    fn branch_name_without_slug_has_no_trailing_separator() {
+        assert_eq!(branch_name_from_template(DEFAULT_BRANCH_NAME_TEMPLATE, "PROJ-1", "Fix login bug!"), "feature/PROJ-1-fix-login-bug");
+        assert_eq!(branch_name_from_template(DEFAULT_BRANCH_NAME_TEMPLATE, "PROJ-1", "ログイン画面の修正"), "feature/PROJ-1");
+        assert_eq!(branch_name_from_template(DEFAULT_BRANCH_NAME_TEMPLATE, "PROJ-1", "!!! ???"), "feature/PROJ-1");
+        assert_eq!(branch_name_from_template("{slug}_{ticket}/wip", "PROJ-1", ""), "PROJ-1/wip");
+    }
+
+    #[test]
+    fn tracked_changes_ignore_untracked_files() {
+        assert!(!has_tracked_changes_with(&MockGitCommand::new()));
+        assert!(!has_tracked_changes_with(&MockGitCommand::new().status("?? notes.txt")));
+        assert!(has_tracked_changes_with(&MockGitCommand::new().status("?? notes.txt\n M src/main.rs")));
+    }
+}
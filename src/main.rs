@@ -1,7 +1,10 @@
 // main.rs
 
+use colored::Colorize;
+use std::io::Write;
 use std::process::{Command, Stdio};
 use std::str;
+use std::time::Instant;
 
 // --- 型定義 ---
 // CommandResult は main.rs で定義し、cmds.rs から crate::CommandResult として参照
@@ -11,8 +14,25 @@ pub type CommandHandler = fn(&[String]);
 
 pub struct CommandDefinition {
     pub name: &'static str,
+    pub aliases: &'static [&'static str],
     pub description: &'static str,
     pub handler: CommandHandler,
+    // (引数, 説明) の組。`examples <command>` や `<command> --examples` で表示する
+    pub examples: &'static [(&'static str, &'static str)],
+}
+
+// --log-level debug のときに、実行した git コマンドと所要時間を出力する
+fn log_git_invocation(args: &[&str], started: Instant, code: Option<i32>) {
+    log::debug!("git {} (終了コード: {}, {} ms)", args.join(" "), code.map_or("-".to_string(), |c| c.to_string()), started.elapsed().as_millis());
+}
+
+// --verbose のときに、実行する git コマンドを実行前に標準エラー出力へ表示する (取得する標準出力には混ざらない)
+fn echo_git_invocation(args: &[&str]) {
+    if !utils::verbose() { return; }
+    let quoted: Vec<String> = args.iter()
+        .map(|a| if a.is_empty() || a.contains(|c: char| c.is_whitespace() || "'\"$*?".contains(c)) { format!("'{}'", a.replace('\'', "'\\''")) } else { a.to_string() })
+        .collect();
+    eprintln!("{}", format!("$ git {}", quoted.join(" ")).dimmed());
 }
 
 // --- 低レベルなGitコマンド実行ヘルパー ---
@@ -20,11 +40,14 @@ fn execute_git_command_internal(args: &[&str], capture_stdout: bool, description
     let mut command = Command::new("git");
     command.args(args);
 
+    echo_git_invocation(args);
+    let started = Instant::now();
     let output_res = if capture_stdout {
         command.stderr(Stdio::piped()).output()
     } else {
         command.stdout(Stdio::inherit()).stderr(Stdio::inherit()).output()
     };
+    log_git_invocation(args, started, output_res.as_ref().ok().and_then(|o| o.status.code()));
 
     match output_res {
         Ok(output) => {
@@ -59,24 +82,103 @@ impl GitCommand {
     fn run_stdout(args: &[&str], cmd_description: &str) -> CommandResult<String> {
         execute_git_command_internal(args, true, cmd_description)
     }
+    // 出力はそのまま端末に流し、失敗 (コンフリクト等) は Err ではなく false で返す
+    fn run_interactive_check(args: &[&str], cmd_description: &str) -> CommandResult<bool> {
+        echo_git_invocation(args);
+        let started = Instant::now();
+        let status_res = Command::new("git").args(args).stdout(Stdio::inherit()).stderr(Stdio::inherit()).status();
+        log_git_invocation(args, started, status_res.as_ref().ok().and_then(|s| s.code()));
+        match status_res {
+            Ok(status) => Ok(status.success()),
+            Err(e) => Err(format!("エラー: コマンド \"{}\" の実行に失敗しました。詳細: {}", cmd_description, e)),
+        }
+    }
+    // 標準入力に input を渡して実行する (git credential など)
+    fn run_with_stdin(args: &[&str], input: &str, cmd_description: &str) -> CommandResult<()> {
+        echo_git_invocation(args);
+        let started = Instant::now();
+        let spawn_res = Command::new("git").args(args).stdin(Stdio::piped()).stdout(Stdio::null()).stderr(Stdio::piped()).spawn();
+        let output_res = spawn_res.and_then(|mut child| {
+            if let Some(mut stdin) = child.stdin.take() { stdin.write_all(input.as_bytes())?; }
+            child.wait_with_output()
+        });
+        log_git_invocation(args, started, output_res.as_ref().ok().and_then(|o| o.status.code()));
+        match output_res {
+            Ok(output) if output.status.success() => Ok(()),
+            Ok(output) => Err(format!("エラー: コマンド \"{}\" 失敗 (コード: {})\nstderr:\n{}", cmd_description, output.status.code().unwrap_or(-1), String::from_utf8_lossy(&output.stderr).trim())),
+            Err(e) => Err(format!("エラー: コマンド \"{}\" の実行に失敗しました。詳細: {}", cmd_description, e)),
+        }
+    }
+    // --path 指定時は git の引数の末尾にパス指定 (-- <dir>) を付ける
+    fn with_path_scope<'a>(args: &[&'a str]) -> Vec<&'a str> {
+        let mut scoped = args.to_vec();
+        if let Some(path) = utils::path_scope() {
+            if !scoped.contains(&"--") { scoped.push("--"); }
+            scoped.push(path);
+        }
+        scoped
+    }
     fn run_check_exit_code_zero(args: &[&str], cmd_description: &str) -> CommandResult<bool> {
-        match Command::new("git").args(args).stdout(Stdio::null()).stderr(Stdio::null()).status() {
+        echo_git_invocation(args);
+        let started = Instant::now();
+        let status_res = Command::new("git").args(args).stdout(Stdio::null()).stderr(Stdio::null()).status();
+        log_git_invocation(args, started, status_res.as_ref().ok().and_then(|s| s.code()));
+        match status_res {
             Ok(status) => Ok(status.success()),
             Err(e) => Err(format!("コマンド \"{}\" の状態確認に失敗: {}", cmd_description, e)),
         }
     }
 
     pub fn init() -> CommandResult<()> { Self::run_interactive(&["init"], "git init") }
+    pub fn init_with_branch(branch: &str) -> CommandResult<()> { Self::run_interactive(&["init", "--initial-branch", branch], "git init --initial-branch") }
     pub fn remote_add(remote: &str, url: &str) -> CommandResult<()> { Self::run_interactive(&["remote", "add", remote, url], "git remote add") }
     pub fn remote_set_url(remote: &str, url: &str) -> CommandResult<()> { Self::run_interactive(&["remote", "set-url", remote, url], "git remote set-url") }
     pub fn remote_remove(remote: &str) -> CommandResult<()> { Self::run_interactive(&["remote", "remove", remote], "git remote remove")}
     pub fn remote_get_url(remote: &str) -> CommandResult<String> { Self::run_stdout(&["remote", "get-url", remote], "git remote get-url") }
+    pub fn ls_remote_heads(remote: &str) -> CommandResult<String> { Self::run_stdout(&["ls-remote", "--heads", remote], "git ls-remote --heads") }
     pub fn remote_list_str() -> CommandResult<String> { Self::run_stdout(&["remote"], "git remote") }
     
     pub fn add(files: &str) -> CommandResult<()> { Self::run_interactive(&["add", files], "git add") }
+    // 削除されたファイルも含めて、指定したパスだけをステージする
+    pub fn add_paths(paths: &[String]) -> CommandResult<()> {
+        let mut args = vec!["add", "-A", "--"];
+        args.extend(paths.iter().map(|p| p.as_str()));
+        Self::run_interactive(&args, "git add -A -- <paths>")
+    }
     pub fn commit(message: &str) -> CommandResult<()> { Self::run_interactive(&["commit", "-m", message], "git commit") }
+    pub fn commit_with_body(subject: &str, body: &str) -> CommandResult<()> {
+        Self::run_interactive(&["commit", "-m", subject, "-m", body], "git commit -m <subject> -m <body>")
+    }
+    // kind は "fixup" または "squash"。件名が "fixup! <対象の件名>" のコミットを作り、rebase --autosquash でまとめられるようにする
+    pub fn commit_fixup(kind: &str, commit: &str, message: Option<&str>) -> CommandResult<()> {
+        let target = format!("--{}={}", kind, commit);
+        match message {
+            Some(msg) => Self::run_interactive(&["commit", &target, "-m", msg], "git commit --squash=<commit> -m"),
+            None => Self::run_interactive(&["commit", &target], "git commit --fixup=<commit>"),
+        }
+    }
+    // 直前のコミットのメッセージはそのままに、ステージした変更を追加する
+    // メッセージ未指定なら直前のコミットのメッセージをそのまま使う
+    pub fn commit_amend(message: Option<&str>) -> CommandResult<()> {
+        match message {
+            Some(msg) => Self::run_interactive(&["commit", "--amend", "-m", msg], "git commit --amend -m"),
+            None => Self::run_interactive(&["commit", "--amend", "--no-edit"], "git commit --amend --no-edit"),
+        }
+    }
+    pub fn commit_with_date(message: &str, date: &str) -> CommandResult<()> {
+        Self::run_interactive(&["commit", "-m", message, "--date", date], "git commit --date")
+    }
+    // エディタ (core.editor) で差分付きテンプレートを編集してコミットする。メッセージが空なら git が中止し false を返す
+    pub fn commit_edit(date: Option<&str>) -> CommandResult<bool> {
+        match date {
+            Some(d) => Self::run_interactive_check(&["commit", "--verbose", "--date", d], "git commit --verbose --date"),
+            None => Self::run_interactive_check(&["commit", "--verbose"], "git commit --verbose"),
+        }
+    }
     pub fn push(remote: &str, branch: &str) -> CommandResult<()> { Self::run_interactive(&["push", remote, branch], "git push") }
     pub fn push_u(remote: &str, branch: &str) -> CommandResult<()> { Self::run_interactive(&["push", "-u", remote, branch], "git push -u") }
+    // リモートが最後に取得した時点から動いていなければ上書きする (他の人のコミットを消さないよう --force は使わない)
+    pub fn push_force_with_lease(remote: &str, branch: &str) -> CommandResult<()> { Self::run_interactive(&["push", "--force-with-lease", "-u", remote, branch], "git push --force-with-lease") }
     pub fn push_delete(remote: &str, branch: &str) -> CommandResult<()> { Self::run_interactive(&["push", remote, "--delete", branch], "git push --delete") }
     pub fn push_ref_to_ref(remote: &str, source_and_dest_ref: &str) -> CommandResult<()> {
         Self::run_interactive(&["push", remote, source_and_dest_ref], "git push <ref>:<ref>")
@@ -84,28 +186,255 @@ impl GitCommand {
         
     pub fn branch_list_all_str() -> CommandResult<String> { Self::run_stdout(&["branch", "--all", "--no-color"], "git branch --all")}
     pub fn branch_list_local_str() -> CommandResult<String> { Self::run_stdout(&["branch", "--no-color"], "git branch")}
+    pub fn branch_contains(commit: &str) -> CommandResult<String> {
+        Self::run_stdout(&["branch", "--all", "--no-color", "--contains", commit], "git branch --all --contains")
+    }
+    // 指定した参照に完全にマージ済みのローカルブランチ名を1行ずつ返す
+    pub fn branch_list_merged(base: &str) -> CommandResult<String> {
+        Self::run_stdout(&["branch", "--merged", base, "--format=%(refname:short)"], "git branch --merged")
+    }
+    pub fn branch_remote_contains(commit: &str) -> CommandResult<String> {
+        Self::run_stdout(&["branch", "-r", "--no-color", "--contains", commit], "git branch -r --contains")
+    }
     pub fn branch_create_local(name: &str) -> CommandResult<()> { Self::run_interactive(&["branch", name], "git branch <name>") }
-    pub fn branch_create_local_from(name: &str, source: &str) -> CommandResult<()> { Self::run_interactive(&["branch", name, source], "git branch <name> <source>") }
-    pub fn branch_delete_local_d(branch: &str) -> CommandResult<()> { Self::run_interactive(&["branch", "-d", branch], "git branch -d") }
+    // リモートブランチから作成しても、そのリモートブランチを上流に設定しない (プッシュ時に同名の上流を設定する)
+    pub fn branch_create_local_from(name: &str, source: &str) -> CommandResult<()> { Self::run_interactive(&["branch", "--no-track", name, source], "git branch --no-track <name> <source>") }
+    pub fn check_ref_format_branch(name: &str) -> CommandResult<bool> {
+        Self::run_check_exit_code_zero(&["check-ref-format", "--branch", name], "git check-ref-format --branch")
+    }
+    // 上流をリモートブランチに設定してローカルブランチを作成する (切り替えはしない)
+    pub fn branch_track(name: &str, remote_branch: &str) -> CommandResult<()> {
+        Self::run_interactive(&["branch", "--track", name, remote_branch], "git branch --track <name> <remote>/<name>")
+    }
+    pub fn branch_rename(old: &str, new: &str) -> CommandResult<()> { Self::run_interactive(&["branch", "-m", old, new], "git branch -m <old> <new>") }
+    // 失敗理由 (未マージ等) を呼び出し側で判定できるよう、stderr はエラーに含めて返す
+    pub fn branch_delete_local_d(branch: &str) -> CommandResult<()> { Self::run_stdout(&["branch", "-d", branch], "git branch -d").map(|_| ()) }
+    pub fn branch_delete_local_force(branch: &str) -> CommandResult<()> { Self::run_interactive(&["branch", "-D", branch], "git branch -D") }
 
     pub fn checkout(branch: &str) -> CommandResult<()> { Self::run_interactive(&["checkout", branch], "git checkout") }
+    pub fn checkout_file_from(ref_name: &str, path: &str) -> CommandResult<()> {
+        Self::run_interactive(&["checkout", ref_name, "--", path], "git checkout <ref> -- <path>")
+    }
+    // ハンク単位の選択は git の対話 UI に任せる
+    pub fn checkout_patch(path: &str) -> CommandResult<()> { Self::run_interactive(&["checkout", "-p", "--", path], "git checkout -p -- <path>") }
     pub fn checkout_b(branch: &str) -> CommandResult<()> { Self::run_interactive(&["checkout", "-b", branch], "git checkout -b") }
+    pub fn checkout_b_from(branch: &str, start_point: &str) -> CommandResult<()> {
+        Self::run_interactive(&["checkout", "-b", branch, start_point], "git checkout -b <branch> <start>")
+    }
     
     pub fn merge(branch: &str) -> CommandResult<bool> { Self::run_check_exit_code_zero(&["merge", branch], "git merge") }
+    // 作業ツリーを変更せずにマージ結果を計算する (git 2.38 以降)。(コンフリクトなしか, コンフリクトしたファイル) を返す
+    pub fn merge_tree(base: &str, head: &str) -> CommandResult<(bool, Vec<String>)> {
+        let args = ["merge-tree", "--write-tree", "--name-only", "--no-messages", base, head];
+        echo_git_invocation(&args);
+        let started = Instant::now();
+        let output = Command::new("git")
+            .args(args)
+            .output()
+            .map_err(|e| format!("エラー: コマンド \"git merge-tree\" の実行に失敗しました。詳細: {}", e))?;
+        log_git_invocation(&args, started, output.status.code());
+        match output.status.code() {
+            // 1行目は結果のツリーID、以降がコンフリクトしたファイル
+            Some(0) | Some(1) => {
+                let files = String::from_utf8_lossy(&output.stdout).lines().skip(1).map(|l| l.to_string()).collect();
+                Ok((output.status.success(), files))
+            }
+            code => Err(format!("エラー: コマンド \"git merge-tree\" 失敗 (コード: {})\nstderr:\n{}", code.unwrap_or(-1), String::from_utf8_lossy(&output.stderr).trim())),
+        }
+    }
+    pub fn merge_ff_only(ref_name: &str) -> CommandResult<bool> { Self::run_interactive_check(&["merge", "--ff-only", ref_name], "git merge --ff-only") }
+    // マージコミットのメッセージは既定のまま (エディタを開かない)
+    pub fn merge_no_ff(branch: &str) -> CommandResult<bool> { Self::run_interactive_check(&["merge", "--no-ff", "--no-edit", branch], "git merge --no-ff --no-edit") }
+    // 変更をステージするだけで、コミットは呼び出し側で行う
+    pub fn merge_squash(branch: &str) -> CommandResult<bool> { Self::run_interactive_check(&["merge", "--squash", branch], "git merge --squash") }
+    // コンフリクト中のファイルのうち、rerere が記録済みの解決を適用できなかったもの
+    pub fn rerere_remaining() -> CommandResult<String> { Self::run_stdout(&["rerere", "remaining"], "git rerere remaining") }
+    // 現在のコンフリクトを記録する (rerere を途中で有効にした場合に使う)
+    pub fn rerere() -> CommandResult<()> { Self::run_interactive(&["rerere"], "git rerere") }
+    pub fn merge_autostash(branch: &str) -> CommandResult<bool> { Self::run_check_exit_code_zero(&["merge", "--autostash", branch], "git merge --autostash") }
     pub fn pull(remote: &str, branch: &str) -> CommandResult<bool> { 
         Self::run_check_exit_code_zero(&["pull", remote, branch], "git pull (check)")
     }
+    pub fn pull_rebase(remote: &str, branch: &str) -> CommandResult<bool> {
+        Self::run_check_exit_code_zero(&["pull", "--rebase", remote, branch], "git pull --rebase (check)")
+    }
+    pub fn pull_ff_only(remote: &str, branch: &str) -> CommandResult<bool> {
+        Self::run_check_exit_code_zero(&["pull", "--ff-only", remote, branch], "git pull --ff-only (check)")
+    }
     
+    pub fn rebase(upstream: &str) -> CommandResult<bool> { Self::run_interactive_check(&["rebase", upstream], "git rebase") }
+    pub fn rebase_autostash(upstream: &str) -> CommandResult<bool> { Self::run_interactive_check(&["rebase", "--autostash", upstream], "git rebase --autostash") }
+    // git rebase -i の todo を、エディタを開かずに todo_path の内容で置き換えて実行する (squash のメッセージ編集ではエディタが開く)
+    pub fn rebase_interactive_with_todo(upstream: &str, todo_path: &str) -> CommandResult<bool> {
+        let args = ["rebase", "-i", upstream];
+        echo_git_invocation(&args);
+        let started = Instant::now();
+        let status_res = Command::new("git")
+            .args(args)
+            .env("GIT_SEQUENCE_EDITOR", format!("cp '{}'", todo_path.replace('\'', "'\\''")))
+            .stdout(Stdio::inherit()).stderr(Stdio::inherit())
+            .status();
+        log_git_invocation(&args, started, status_res.as_ref().ok().and_then(|s| s.code()));
+        match status_res {
+            Ok(status) => Ok(status.success()),
+            Err(e) => Err(format!("エラー: コマンド \"git rebase -i\" の実行に失敗しました。詳細: {}", e)),
+        }
+    }
+
+    pub fn cherry_pick(commit: &str) -> CommandResult<bool> { Self::run_interactive_check(&["cherry-pick", commit], "git cherry-pick") }
+    pub fn merge_abort() -> CommandResult<()> { Self::run_interactive(&["merge", "--abort"], "git merge --abort") }
+    pub fn rebase_abort() -> CommandResult<()> { Self::run_interactive(&["rebase", "--abort"], "git rebase --abort") }
+    pub fn cherry_pick_abort() -> CommandResult<()> { Self::run_interactive(&["cherry-pick", "--abort"], "git cherry-pick --abort") }
+    pub fn cherry_pick_no_commit(commit: &str) -> CommandResult<bool> {
+        Self::run_interactive_check(&["cherry-pick", "--no-commit", commit], "git cherry-pick --no-commit")
+    }
+
     pub fn fetch_prune(remote: &str) -> CommandResult<()> { Self::run_interactive(&["fetch", remote, "--prune"], "git fetch --prune") }
+    // fetch --prune で削除された追跡ブランチ (例: "origin/old-feature") を返す。
+    // --porcelain は新しい git にしか無く、stderr の "[deleted]" は翻訳されるため、前後の参照を比べる
+    pub fn fetch_prune_report(remote: &str) -> CommandResult<Vec<String>> {
+        let list_refs = || Self::run_stdout(&["for-each-ref", "--format=%(refname:short)", &format!("refs/remotes/{}", remote)], "git for-each-ref refs/remotes/<remote>");
+        let before = list_refs()?;
+        Self::fetch_prune(remote)?;
+        let after = list_refs()?;
+        let remaining: std::collections::HashSet<&str> = after.lines().collect();
+        Ok(before.lines().filter(|r| !remaining.contains(r)).map(|r| r.to_string()).collect())
+    }
+    // 進行状況を表示しない (機械向けの出力を乱さないため)
+    pub fn fetch_prune_quiet(remote: &str) -> CommandResult<()> { Self::run_stdout(&["fetch", "--quiet", remote, "--prune"], "git fetch --quiet --prune").map(|_| ()) }
+    pub fn fetch_branch(remote: &str, branch: &str) -> CommandResult<()> { Self::run_interactive(&["fetch", remote, branch], "git fetch <remote> <branch>") }
+    // チェックアウトしていないローカルブランチを、リモートの同名ブランチまで fast-forward する (できない場合は失敗)
+    pub fn fetch_fast_forward(remote: &str, branch: &str) -> CommandResult<()> {
+        Self::run_interactive(&["fetch", remote, &format!("{}:{}", branch, branch)], "git fetch <remote> <branch>:<branch>")
+    }
+    pub fn fetch_tags(remote: &str) -> CommandResult<()> { Self::run_interactive(&["fetch", remote, "--tags"], "git fetch --tags") }
+    pub fn ls_remote_tags(remote: &str) -> CommandResult<String> { Self::run_stdout(&["ls-remote", "--tags", remote], "git ls-remote --tags") }
+    pub fn for_each_ref_tags() -> CommandResult<String> {
+        Self::run_stdout(&["for-each-ref", "--format=%(refname:short)", "refs/tags"], "git for-each-ref refs/tags")
+    }
+    pub fn tag_delete(tag: &str) -> CommandResult<()> { Self::run_interactive(&["tag", "-d", tag], "git tag -d") }
     
+    pub fn stash_push(message: Option<&str>) -> CommandResult<()> {
+        match message {
+            Some(msg) => Self::run_interactive(&["stash", "push", "-m", msg], "git stash push -m"),
+            None => Self::run_interactive(&["stash", "push"], "git stash push"),
+        }
+    }
+    pub fn stash_pop() -> CommandResult<bool> { Self::run_interactive_check(&["stash", "pop"], "git stash pop") }
+    pub fn stash_pop_ref(stash: &str) -> CommandResult<bool> { Self::run_interactive_check(&["stash", "pop", stash], "git stash pop <stash>") }
+    // スタッシュ作成時のコミットから新しいブランチを作って切り替え、そこでスタッシュを適用する (成功すればスタッシュは削除される)
+    pub fn stash_branch(branch: &str, stash: &str) -> CommandResult<bool> {
+        Self::run_interactive_check(&["stash", "branch", branch, stash], "git stash branch")
+    }
+    pub fn stash_drop(stash: &str) -> CommandResult<()> { Self::run_interactive(&["stash", "drop", stash], "git stash drop") }
+    pub fn stash_apply(stash: &str) -> CommandResult<bool> { Self::run_interactive_check(&["stash", "apply", stash], "git stash apply") }
+    // 各行 "stash@{n}\t<メッセージ>" の形式
+    pub fn stash_list() -> CommandResult<String> { Self::run_stdout(&["stash", "list", "--format=%gd%x09%s"], "git stash list") }
+    // 端末に直接出力するので git のページャーがそのまま使われる
+    pub fn stash_show(stash: &str) -> CommandResult<()> { Self::run_interactive(&["stash", "show", "-p", stash], "git stash show -p") }
+    // スタッシュの内容と作業ツリー (base 指定時はそのコミット) の差分。ページャーで表示する
+    pub fn diff_stash(stash: &str, base: Option<&str>) -> CommandResult<()> {
+        match base {
+            Some(base) => Self::run_interactive(&["diff", base, stash], "git diff <base> <stash>"),
+            None => Self::run_interactive(&["diff", stash], "git diff <stash>"),
+        }
+    }
+
+    // 各行 "<完全なsha>\t<短縮sha>\t<件名>" の形式で最近のコミットを返す
+    pub fn log_oneline(branch: &str, max_count: usize) -> CommandResult<String> {
+        Self::run_stdout(&["log", "--format=%H%x09%h%x09%s", "-n", &max_count.to_string(), branch, "--"], "git log")
+    }
+
+    pub fn bundle_create(file: &str) -> CommandResult<()> { Self::run_interactive(&["bundle", "create", file, "--all"], "git bundle create --all") }
+    pub fn bundle_verify(file: &str) -> CommandResult<()> { Self::run_interactive(&["bundle", "verify", "--quiet", file], "git bundle verify") }
+    pub fn clone(source: &str, dir: Option<&str>) -> CommandResult<()> {
+        match dir {
+            Some(dir) => Self::run_interactive(&["clone", source, dir], "git clone <source> <dir>"),
+            None => Self::run_interactive(&["clone", source], "git clone <source>"),
+        }
+    }
+    // バンドル内のブランチを refs/remotes/<name>/* に取り込む
+    pub fn fetch_bundle(file: &str, name: &str) -> CommandResult<()> {
+        Self::run_interactive(&["fetch", file, &format!("refs/heads/*:refs/remotes/{}/*", name)], "git fetch <bundle>")
+    }
+    pub fn show_toplevel() -> CommandResult<String> { Self::run_stdout(&["rev-parse", "--show-toplevel"], "git rev-parse --show-toplevel") }
+    pub fn count_objects() -> CommandResult<String> { Self::run_stdout(&["count-objects", "-vH"], "git count-objects -vH") }
+    pub fn gc() -> CommandResult<()> { Self::run_interactive(&["gc"], "git gc") }
+
+    // log_oneline と同じ形式で範囲内のコミットを返す
+    pub fn log_range_oneline(range: &str) -> CommandResult<String> {
+        Self::run_stdout(&Self::with_path_scope(&["log", "--format=%H%x09%h%x09%s", range, "--"]), "git log <range>")
+    }
+    // HEAD の reflog を新しい順に "<移動後のsha>\t<操作の説明>" の形式で返す
+    pub fn reflog_head() -> CommandResult<String> {
+        Self::run_stdout(&["reflog", "show", "--format=%H%x09%gs", "HEAD", "--"], "git reflog show HEAD")
+    }
+    // どのブランチ・タグ・reflog からも辿れないコミットの sha を1行ずつ返す
+    pub fn fsck_dangling_commits() -> CommandResult<String> {
+        let output = Self::run_stdout(&["fsck", "--no-reflogs", "--no-progress", "--dangling"], "git fsck --dangling")?;
+        Ok(output.lines().filter_map(|l| l.strip_prefix("dangling commit ")).collect::<Vec<_>>().join("\n"))
+    }
+    // "<短縮sha>\t<件名>\t<相対日時>" の形式
+    pub fn commit_summary(commit: &str) -> CommandResult<String> {
+        Self::run_stdout(&["log", "-1", "--format=%h%x09%s%x09%cr", commit, "--"], "git log -1")
+    }
+    // 各行 "<コミット数>\t<作成者>" の形式で、コミット数の多い順に返す
+    pub fn shortlog(range: &str) -> CommandResult<String> { Self::run_stdout(&["shortlog", "-sn", range, "--"], "git shortlog -sn") }
+    pub fn last_tag() -> CommandResult<String> { Self::run_stdout(&["describe", "--tags", "--abbrev=0"], "git describe --tags --abbrev=0") }
+
     pub fn symbolic_ref_head() -> CommandResult<String> {
         let result = Self::run_stdout(&["symbolic-ref", "--short", "-q", "HEAD"], "git symbolic-ref --short HEAD")?;
         if result == "HEAD" { return Ok(String::new()); }
         Ok(result)
     }
+    // リポジトリ外では Err を返す。ワークツリーやサブモジュールでは .git はファイルなので、パスはこれで解決する
+    // core.hooksPath が設定されていればそちらを返す
+    pub fn hooks_dir() -> CommandResult<String> {
+        Self::run_stdout(&["rev-parse", "--path-format=absolute", "--git-path", "hooks"], "git rev-parse --git-path hooks")
+    }
+    pub fn git_dir() -> CommandResult<String> { Self::run_stdout(&["rev-parse", "--absolute-git-dir"], "git rev-parse --absolute-git-dir") }
+    // リンクされたワークツリーでは本体リポジトリの .git を指す
+    pub fn git_common_dir() -> CommandResult<String> {
+        Self::run_stdout(&["rev-parse", "--path-format=absolute", "--git-common-dir"], "git rev-parse --git-common-dir")
+    }
     pub fn config_get(key: &str) -> CommandResult<String> {
         Self::run_stdout(&["config", key], &format!("git config {}", key))
     }
+    // 複数の値を持つキー (credential.helper など)。未設定なら空
+    pub fn config_get_all(key: &str) -> CommandResult<Vec<String>> {
+        match Self::run_stdout(&["config", "--get-all", key], &format!("git config --get-all {}", key)) {
+            Ok(values) => Ok(values.lines().map(|l| l.to_string()).collect()),
+            Err(_) => Ok(Vec::new()), // キーが無い場合も終了コード 1
+        }
+    }
+    // URL に対して保存されている資格情報を、設定されているすべてのヘルパーから削除する
+    pub fn credential_reject(url: &str) -> CommandResult<()> {
+        Self::run_with_stdin(&["credential", "reject"], &format!("url={}\n\n", url), "git credential reject")
+    }
+    // credential-cache のデーモンを終了し、メモリ上の資格情報をすべて破棄する (起動していなくても成功する)
+    pub fn credential_cache_exit() -> CommandResult<()> { Self::run_interactive(&["credential-cache", "exit"], "git credential-cache exit") }
+    pub fn config_get_global(key: &str) -> CommandResult<String> {
+        Self::run_stdout(&["config", "--global", key], &format!("git config --global {}", key))
+    }
+    pub fn config_set(key: &str, value: &str, global: bool) -> CommandResult<()> {
+        if global {
+            Self::run_interactive(&["config", "--global", key, value], "git config --global <key> <value>")
+        } else {
+            Self::run_interactive(&["config", key, value], "git config <key> <value>")
+        }
+    }
+    // リポジトリの設定からキーを削除する。キーが無かった場合は false
+    pub fn config_unset_local(key: &str) -> CommandResult<bool> {
+        Self::run_check_exit_code_zero(&["config", "--local", "--unset", key], "git config --local --unset <key>")
+    }
+    // 各行 "<キー>=<値>" の形式。後の行ほど優先度が高い (system → global → local)
+    pub fn config_list(global: bool) -> CommandResult<String> {
+        if global {
+            Self::run_stdout(&["config", "--global", "--list"], "git config --global --list")
+        } else {
+            Self::run_stdout(&["config", "--list"], "git config --list")
+        }
+    }
     pub fn rev_parse_verify(ref_name: &str) -> CommandResult<bool> {
         Self::run_check_exit_code_zero(&["rev-parse", "--verify", "--quiet", ref_name], "git rev-parse --verify")
     }
@@ -113,31 +442,345 @@ impl GitCommand {
         Self::run_stdout(&["rev-parse", ref_name], "git rev-parse")
     }
     pub fn status_porcelain_v1() -> CommandResult<String> {
-        Self::run_stdout(&["status", "--porcelain"], "git status --porcelain")
+        Self::run_stdout(&Self::with_path_scope(&["status", "--porcelain"]), "git status --porcelain")
+    }
+    pub fn ls_tree_files(ref_name: &str) -> CommandResult<String> {
+        Self::run_stdout(&["ls-tree", "-r", "--name-only", ref_name], "git ls-tree -r --name-only")
+    }
+    // すべての参照から到達できるオブジェクトを "<sha> <パス>" の形式で返す (コミットやルートツリーはパスなし)
+    pub fn rev_list_objects_all() -> CommandResult<String> { Self::run_stdout(&["rev-list", "--objects", "--all"], "git rev-list --objects --all") }
+    // リポジトリ内のすべての blob を "<sha> <サイズ>" の形式で返す
+    pub fn cat_file_all_blob_sizes() -> CommandResult<String> {
+        let output = Self::run_stdout(&["cat-file", "--batch-all-objects", "--batch-check=%(objecttype) %(objectname) %(objectsize)"], "git cat-file --batch-check")?;
+        Ok(output.lines().filter_map(|l| l.strip_prefix("blob ")).collect::<Vec<_>>().join("\n"))
+    }
+    // オブジェクトを追加/削除したコミットを "<短縮sha>\t<件名>" の形式で新しい順に返す
+    pub fn log_find_object(object: &str) -> CommandResult<String> {
+        Self::run_stdout(&["log", "--all", "--format=%h%x09%s", &format!("--find-object={}", object)], "git log --find-object")
+    }
+    // パス指定 (glob 可) に一致する追跡中のファイル
+    pub fn ls_files_matching(pathspec: &str) -> CommandResult<String> { Self::run_stdout(&["ls-files", "--", pathspec], "git ls-files <pathspec>") }
+    // 作業ツリーのファイルは残したまま、追跡だけをやめる
+    pub fn rm_cached(paths: &[String]) -> CommandResult<()> {
+        let mut args = vec!["rm", "--cached", "-q", "--"];
+        args.extend(paths.iter().map(|p| p.as_str()));
+        Self::run_interactive(&args, "git rm --cached")
+    }
+    // 範囲 (例: "main...feature") で変更されたファイルのパス
+    pub fn diff_name_only(range: &str) -> CommandResult<String> {
+        Self::run_stdout(&Self::with_path_scope(&["diff", "--name-only", range, "--"]), "git diff --name-only <range>")
+    }
+    pub fn ls_files() -> CommandResult<String> { Self::run_stdout(&["ls-files"], "git ls-files") }
+    // 各行 "<パス>: <属性>: <値>" の形式
+    pub fn check_attr(path: &str) -> CommandResult<String> { Self::run_stdout(&["check-attr", "-a", "--", path], "git check-attr -a") }
+    // 直下のディレクトリ名を1行ずつ返す
+    pub fn ls_tree_dirs(ref_name: &str) -> CommandResult<String> {
+        Self::run_stdout(&["ls-tree", "-d", "--name-only", ref_name], "git ls-tree -d --name-only")
+    }
+    pub fn sparse_checkout_init() -> CommandResult<()> { Self::run_interactive(&["sparse-checkout", "init", "--cone"], "git sparse-checkout init --cone") }
+    pub fn sparse_checkout_list() -> CommandResult<String> { Self::run_stdout(&["sparse-checkout", "list"], "git sparse-checkout list") }
+    pub fn sparse_checkout_add(dirs: &[String]) -> CommandResult<()> {
+        let mut args = vec!["sparse-checkout", "add"];
+        args.extend(dirs.iter().map(|d| d.as_str()));
+        Self::run_interactive(&args, "git sparse-checkout add")
+    }
+    pub fn sparse_checkout_set(dirs: &[String]) -> CommandResult<()> {
+        let mut args = vec!["sparse-checkout", "set"];
+        args.extend(dirs.iter().map(|d| d.as_str()));
+        Self::run_interactive(&args, "git sparse-checkout set")
+    }
+    pub fn sparse_checkout_disable() -> CommandResult<()> { Self::run_interactive(&["sparse-checkout", "disable"], "git sparse-checkout disable") }
+    pub fn status_porcelain_v2() -> CommandResult<String> {
+        Self::run_stdout(&Self::with_path_scope(&["status", "--porcelain=v2"]), "git status --porcelain=v2")
+    }
+    // ヘッダー行 (# branch.*) 付き。シェルのプロンプト表示用に1回の呼び出しで必要な情報をすべて得る
+    pub fn status_porcelain_v2_branch() -> CommandResult<String> {
+        Self::run_stdout(&["status", "--porcelain=v2", "--branch"], "git status --porcelain=v2 --branch")
+    }
+    // ステージした変更の概要 (full なら差分全体も)。出力は端末にそのまま流す
+    pub fn diff_cached(full: bool) -> CommandResult<()> {
+        let base: &[&str] = if full { &["diff", "--cached", "--stat", "--patch"] } else { &["diff", "--cached", "--stat"] };
+        Self::run_interactive(&Self::with_path_scope(base), "git diff --cached")
+    }
+    pub fn unstage(path: &str) -> CommandResult<()> { Self::run_interactive(&["reset", "-q", "--", path], "git reset -- <path>") }
+    pub fn reset_soft(commit: &str) -> CommandResult<()> { Self::run_interactive(&["reset", "--soft", commit], "git reset --soft") }
+    // mode は "soft"/"mixed"/"hard"
+    pub fn reset_head(mode: &str, commit: &str) -> CommandResult<()> {
+        Self::run_interactive(&["reset", &format!("--{}", mode), commit], "git reset <mode> <commit>")
+    }
+    pub fn reset_hard_head() -> CommandResult<()> { Self::run_interactive(&["reset", "--hard", "HEAD"], "git reset --hard HEAD") }
+    // 指定パス配下の追跡中のファイルだけを HEAD の内容に戻す (ステージと作業ツリーの両方)
+    pub fn restore_head_in(path: &str) -> CommandResult<()> {
+        Self::run_interactive(&["restore", "--source=HEAD", "--staged", "--worktree", "--", path], "git restore --source=HEAD --staged --worktree")
+    }
+    // log_oneline と同じ形式で、範囲内のコミットを古い順に返す
+    pub fn log_range_oneline_reverse(range: &str) -> CommandResult<String> {
+        Self::run_stdout(&["log", "--reverse", "--format=%H%x09%h%x09%s", range, "--"], "git log --reverse <range>")
+    }
+    // 文字列 (regex 時は正規表現) の出現数が変わったコミットを "<短縮sha>\t<作成者>\t<相対日時>\t<件名>" の形式で返す
+    pub fn log_pickaxe(term: &str, regex: bool, path: Option<&str>) -> CommandResult<String> {
+        let pickaxe = format!("{}{}", if regex { "-G" } else { "-S" }, term);
+        let base = ["log", "--format=%h%x09%an%x09%ar%x09%s", &pickaxe, "--"];
+        let args = match path {
+            Some(path) => [&base[..], &[path]].concat(),
+            None => Self::with_path_scope(&base),
+        };
+        Self::run_stdout(&args, "git log -S/-G")
+    }
+    // グラフ付きの1行ログ。出力は端末にそのまま流す (git 自身の色付けを残すため)
+    pub fn log_graph(max_count: usize, all: bool) -> CommandResult<()> {
+        let count = max_count.to_string();
+        let mut base = vec!["log", "--graph", "--oneline", "--decorate", "-n", &count];
+        if all { base.push("--all"); }
+        base.push("--");
+        Self::run_interactive(&Self::with_path_scope(&base), "git log --graph")
+    }
+    // 古い順に件名を1行ずつ返す
+    pub fn log_subjects(range: &str) -> CommandResult<String> {
+        Self::run_stdout(&["log", "--reverse", "--format=%s", range, "--"], "git log --format=%s")
+    }
+    pub fn rev_list_count(range: &str) -> CommandResult<usize> {
+        let count = Self::run_stdout(&["rev-list", "--count", range], "git rev-list --count")?;
+        count.parse().map_err(|_| format!("エラー: git rev-list --count の出力を解析できません: {}", count))
+    }
+    // "a...b" の範囲で (a のみのコミット数, b のみのコミット数) を1回の呼び出しで返す
+    pub fn rev_list_left_right_count(range: &str) -> CommandResult<(usize, usize)> {
+        let counts = Self::run_stdout(&["rev-list", "--left-right", "--count", range], "git rev-list --left-right --count")?;
+        counts.split_once('\t')
+            .and_then(|(left, right)| Some((left.parse().ok()?, right.parse().ok()?)))
+            .ok_or_else(|| format!("エラー: git rev-list --left-right --count の出力を解析できません: {}", counts))
+    }
+    // ブランチの上流 (例: "origin/main")。上流が無ければ Err
+    pub fn upstream_of(branch: &str) -> CommandResult<String> {
+        Self::run_stdout(&["rev-parse", "--abbrev-ref", &format!("{}@{{upstream}}", branch)], "git rev-parse --abbrev-ref <branch>@{upstream}")
+    }
+    // 各行 "<ブランチ名>\t<コミットID>\t<上流 (無ければ空)>\t<差分 (例: "[ahead 3, behind 1]"、無ければ空)>" の形式
+    pub fn for_each_ref_with_tracking() -> CommandResult<String> {
+        Self::run_stdout(&["for-each-ref", "--format=%(refname:short)%09%(objectname)%09%(upstream:short)%09%(upstream:track)", "refs/heads"], "git for-each-ref refs/heads")
+    }
+    // 各行 "<リモート>/<ブランチ名>"。<リモート>/HEAD のようなシンボリック参照は除く
+    pub fn for_each_ref_remote_branches() -> CommandResult<String> {
+        let output = Self::run_stdout(&["for-each-ref", "--format=%(if)%(symref)%(then)%(else)%(refname:lstrip=2)%(end)", "refs/remotes"], "git for-each-ref refs/remotes")?;
+        Ok(output.lines().filter(|l| !l.is_empty()).collect::<Vec<_>>().join("\n"))
+    }
+    // 各行 "<ブランチ名>\t<上流ブランチ (無ければ空)>" の形式
+    pub fn for_each_ref_local_upstreams() -> CommandResult<String> {
+        Self::run_stdout(&["for-each-ref", "--format=%(refname:short)%09%(upstream:short)", "refs/heads"], "git for-each-ref refs/heads")
+    }
+    // リモートに問い合わせて <remote>/HEAD をリモートの既定ブランチに向け直す
+    pub fn remote_set_head_auto(remote: &str) -> CommandResult<()> { Self::run_interactive(&["remote", "set-head", remote, "--auto"], "git remote set-head --auto") }
+    pub fn symbolic_ref_remote_head(remote: &str) -> CommandResult<String> {
+        Self::run_stdout(&["symbolic-ref", "--short", &format!("refs/remotes/{}/HEAD", remote)], "git symbolic-ref refs/remotes/<remote>/HEAD")
+    }
+    // ancestor が descendant の祖先 (または同じコミット) なら true
+    pub fn is_ancestor(ancestor: &str, descendant: &str) -> CommandResult<bool> {
+        Self::run_check_exit_code_zero(&["merge-base", "--is-ancestor", ancestor, descendant], "git merge-base --is-ancestor")
     }
     pub fn merge_base(commit1: &str, commit2: &str) -> CommandResult<String> {
         Self::run_stdout(&["merge-base", commit1, commit2], "git merge-base")
     }
 }
 
+// ブランチの同期状態・未コミットの変更の判定と、削除などの取り消せない操作。
+// テストでは utils::MockGitCommand、--dry-run では DryRunGitCommand に差し替える
+pub trait GitCommandTrait {
+    fn rev_parse_verify(&self, ref_name: &str) -> CommandResult<bool>;
+    fn rev_parse_commit_id(&self, ref_name: &str) -> CommandResult<String>;
+    fn rev_list_count(&self, range: &str) -> CommandResult<usize>;
+    fn status_porcelain_v1(&self) -> CommandResult<String>;
+    fn status_porcelain_v2(&self) -> CommandResult<String>;
+    fn upstream_of(&self, branch: &str) -> CommandResult<String>;
+    fn is_ancestor(&self, ancestor: &str, descendant: &str) -> CommandResult<bool>;
+    // 以下は変更を伴う操作
+    fn push_delete(&self, remote: &str, branch: &str) -> CommandResult<()>;
+    fn branch_delete_local_d(&self, branch: &str) -> CommandResult<()>;
+    fn branch_delete_local_force(&self, branch: &str) -> CommandResult<()>;
+    fn reset_head(&self, mode: &str, commit: &str) -> CommandResult<()>;
+    // 各行 "stash@{n}\t<メッセージ>" の形式
+    fn stash_list(&self) -> CommandResult<String>;
+    fn stash_push(&self, message: Option<&str>) -> CommandResult<()>;
+    // 適用できた (コンフリクトしなかった) 場合に true
+    fn stash_pop(&self, stash: &str) -> CommandResult<bool>;
+    fn add(&self, files: &str) -> CommandResult<()>;
+    fn commit(&self, message: &str) -> CommandResult<()>;
+    fn reset_hard_head(&self) -> CommandResult<()>;
+    fn restore_head_in(&self, path: &str) -> CommandResult<()>;
+    // true の場合、変更を伴う操作は表示だけで実行されない (確認も y とみなす)
+    fn is_dry_run(&self) -> bool { false }
+}
+
+impl GitCommandTrait for GitCommand {
+    fn rev_parse_verify(&self, ref_name: &str) -> CommandResult<bool> { GitCommand::rev_parse_verify(ref_name) }
+    fn rev_parse_commit_id(&self, ref_name: &str) -> CommandResult<String> { GitCommand::rev_parse_commit_id(ref_name) }
+    fn rev_list_count(&self, range: &str) -> CommandResult<usize> { GitCommand::rev_list_count(range) }
+    fn status_porcelain_v1(&self) -> CommandResult<String> { GitCommand::status_porcelain_v1() }
+    fn status_porcelain_v2(&self) -> CommandResult<String> { GitCommand::status_porcelain_v2() }
+    fn upstream_of(&self, branch: &str) -> CommandResult<String> { GitCommand::upstream_of(branch) }
+    fn is_ancestor(&self, ancestor: &str, descendant: &str) -> CommandResult<bool> { GitCommand::is_ancestor(ancestor, descendant) }
+    fn push_delete(&self, remote: &str, branch: &str) -> CommandResult<()> { GitCommand::push_delete(remote, branch) }
+    fn branch_delete_local_d(&self, branch: &str) -> CommandResult<()> { GitCommand::branch_delete_local_d(branch) }
+    fn branch_delete_local_force(&self, branch: &str) -> CommandResult<()> { GitCommand::branch_delete_local_force(branch) }
+    fn reset_head(&self, mode: &str, commit: &str) -> CommandResult<()> { GitCommand::reset_head(mode, commit) }
+    fn stash_list(&self) -> CommandResult<String> { GitCommand::stash_list() }
+    fn stash_push(&self, message: Option<&str>) -> CommandResult<()> { GitCommand::stash_push(message) }
+    fn stash_pop(&self, stash: &str) -> CommandResult<bool> { GitCommand::stash_pop_ref(stash) }
+    fn add(&self, files: &str) -> CommandResult<()> { GitCommand::add(files) }
+    fn commit(&self, message: &str) -> CommandResult<()> { GitCommand::commit(message) }
+    fn reset_hard_head(&self) -> CommandResult<()> { GitCommand::reset_hard_head() }
+    fn restore_head_in(&self, path: &str) -> CommandResult<()> { GitCommand::restore_head_in(path) }
+}
+
+// --dry-run 用。問い合わせは実際の git に任せ、変更を伴う操作は実行する代わりに内容を表示する
+pub struct DryRunGitCommand;
+
+impl DryRunGitCommand {
+    fn print(action: &str) {
+        println!("{} {}", "[dry-run]".yellow().bold(), action);
+    }
+}
+
+impl GitCommandTrait for DryRunGitCommand {
+    fn rev_parse_verify(&self, ref_name: &str) -> CommandResult<bool> { GitCommand::rev_parse_verify(ref_name) }
+    fn rev_parse_commit_id(&self, ref_name: &str) -> CommandResult<String> { GitCommand::rev_parse_commit_id(ref_name) }
+    fn rev_list_count(&self, range: &str) -> CommandResult<usize> { GitCommand::rev_list_count(range) }
+    fn status_porcelain_v1(&self) -> CommandResult<String> { GitCommand::status_porcelain_v1() }
+    fn status_porcelain_v2(&self) -> CommandResult<String> { GitCommand::status_porcelain_v2() }
+    fn upstream_of(&self, branch: &str) -> CommandResult<String> { GitCommand::upstream_of(branch) }
+    fn is_ancestor(&self, ancestor: &str, descendant: &str) -> CommandResult<bool> { GitCommand::is_ancestor(ancestor, descendant) }
+    fn push_delete(&self, remote: &str, branch: &str) -> CommandResult<()> {
+        let exists = GitCommand::rev_parse_verify(&format!("refs/remotes/{}/{}", remote, branch)).unwrap_or(false);
+        let note = if exists { "" } else { " (前回の取得時点ではリモートに無いため、失敗する可能性があります)" };
+        Self::print(&format!("リモートブランチ '{}/{}' を削除します: git push {} --delete {}{}", remote, branch, remote, branch, note));
+        Ok(())
+    }
+    fn branch_delete_local_d(&self, branch: &str) -> CommandResult<()> {
+        Self::print(&format!("ローカルブランチ '{}' を削除します: git branch -d {}", branch, branch));
+        Ok(())
+    }
+    fn branch_delete_local_force(&self, branch: &str) -> CommandResult<()> {
+        Self::print(&format!("ローカルブランチ '{}' を強制削除します: git branch -D {}", branch, branch));
+        Ok(())
+    }
+    fn reset_head(&self, mode: &str, commit: &str) -> CommandResult<()> {
+        Self::print(&format!("git reset --{} {} を実行します。", mode, commit));
+        Ok(())
+    }
+    fn stash_list(&self) -> CommandResult<String> { GitCommand::stash_list() }
+    fn stash_push(&self, message: Option<&str>) -> CommandResult<()> {
+        Self::print(&format!("未コミットの変更を退避します: git stash push{}", message.map(|m| format!(" -m \"{}\"", m)).unwrap_or_default()));
+        Ok(())
+    }
+    fn stash_pop(&self, stash: &str) -> CommandResult<bool> {
+        Self::print(&format!("スタッシュを適用して削除します: git stash pop {}", stash));
+        Ok(true)
+    }
+    fn add(&self, files: &str) -> CommandResult<()> {
+        Self::print(&format!("変更をステージします: git add {}", files));
+        Ok(())
+    }
+    fn commit(&self, message: &str) -> CommandResult<()> {
+        Self::print(&format!("コミットします: git commit -m \"{}\"", message));
+        Ok(())
+    }
+    fn reset_hard_head(&self) -> CommandResult<()> {
+        Self::print("追跡中のファイルの変更をすべて破棄します: git reset --hard HEAD");
+        Ok(())
+    }
+    fn restore_head_in(&self, path: &str) -> CommandResult<()> {
+        Self::print(&format!("'{}' 配下の変更を破棄します: git restore --source=HEAD --staged --worktree -- {}", path, path));
+        Ok(())
+    }
+    fn is_dry_run(&self) -> bool { true }
+}
+
 // COMMAND_DEFINITIONS は pub const にして、cmds.rs から crate::COMMAND_DEFINITIONS で参照
 pub const COMMAND_DEFINITIONS: &[CommandDefinition] = &[
-    CommandDefinition { name: "save", description: "現在の変更を記録し、オプションでリモートに保存します。", handler: cmds::git_save },
-    CommandDefinition { name: "setup", description: "リポジトリの初期化とリモート('origin')の接続設定を行います。", handler: cmds::git_setup },
-    CommandDefinition { name: "branch", description: "ブランチの一覧を状態に応じて色分け表示します。", handler: cmds::git_branch },
-    CommandDefinition { name: "switch", description: "既存のローカルブランチに切り替えます。", handler: cmds::git_switch },
-    CommandDefinition { name: "merge", description: "指定ブランチを現在のブランチにマージします。", handler: cmds::git_merge },
-    CommandDefinition { name: "copy", description: "ブランチをローカルにコピーし、オプションでリモートにプッシュします。", handler: cmds::git_copy },
-    CommandDefinition { name: "delete", description: "ローカルおよびオプションでリモートブランチを削除します。", handler: cmds::git_delete },
-    CommandDefinition { name: "create", description: "新しいローカルブランチを作成し、オプションでリモートにプッシュします。", handler: cmds::git_create },
-    CommandDefinition { name: "help", description: "このヘルプメッセージを表示します。", handler: cmds::show_help },
+    CommandDefinition { name: "save", aliases: &["sa"], description: "現在の変更を記録し、オプションでリモートに保存します。リモートと分岐している場合は、プッシュ前にプルか --force-with-lease での強制プッシュを選べます。(-m <メッセージ>, --amend, --date <日時>, --check, --edit, --review, --review-full, --fixup/--squash [<commit>], --check-base, --merge|--rebase|--ff-only, --push|--no-push, --no-commit-prefix)", handler: cmds::git_save, examples: &[("", "変更をすべてステージしてコミットし、プッシュするか確認します。"), ("-m \"ログイン画面のバグを修正\"", "メッセージを尋ねずにコミットします (プッシュ確認は --yes で省略できます)。"), ("--amend", "変更を直前のコミットに追加します (-m でメッセージも変更)。"), ("--date \"2024-01-01 10:00\"", "コミット日時を指定して記録します。"), ("--check", "設定の pre_save_command (例: cargo test) が成功した場合のみコミットします。"), ("--edit", "エディタで差分を見ながらコミットメッセージを書きます。"), ("--review", "ステージしたファイルと変更の概要を確認し、意図せず含めたファイルを外してからコミットします。"), ("--review-full", "--review に加えて差分全体を表示します。"), ("--fixup", "以前のコミットを選び、それを修正する fixup! コミットを作ります (rebase --autosquash でまとめられます)。"), ("--squash a1b2c3d", "a1b2c3d にまとめる squash! コミットを作ります (メッセージは追記されます)。"), ("--check-base", "プッシュ前に既定ブランチの最新に追従しているか確認し、古ければ更新を提案します。"), ("--rebase", "設定の update_strategy に関わらず、プル時にリベースで取り込みます。"), ("-m \"修正\" --no-push", "設定の auto_push に関わらず、プッシュせずにローカルにだけコミットします。")] },
+    CommandDefinition { name: "setup", aliases: &["se"], description: "リポジトリの初期化とリモート (既定: origin) の接続設定を行います。(--initial-branch, -b で最初のブランチ名を指定)", handler: cmds::git_setup, examples: &[("", "リポジトリを初期化し、リモート (既定: origin) を設定します。"), ("-b main", "git の init.defaultBranch に関わらず、最初のブランチを main にして初期化します。")] },
+    CommandDefinition { name: "branch", aliases: &["br"], description: "ブランチの一覧を状態に応じて色分け表示します。既定ではリモートを取得し、リモートのみのブランチも含めます。(--no-fetch|--fetch, --local, --no-count, --json)", handler: cmds::git_branch, examples: &[("", "リモートを取得してブランチ一覧と同期状態を表示します。現在のブランチには変更の数 (+ステージ済み ~未ステージ ?未追跡) が付きます。"), ("--local --no-fetch", "リモートを取得せず、ローカルブランチだけをすぐに表示します (同期状態は前回取得時のリモートと比較)。"), ("--no-count", "変更の数の代わりに '*' だけを表示します。"), ("--json", "ブランチ名・状態・差分コミット数を JSON の配列で出力します (ダッシュボード等から読み込む用)。")] },
+    CommandDefinition { name: "switch", aliases: &["sw"], description: "既存のローカルブランチに切り替えます。(--no-post)", handler: cmds::git_switch, examples: &[("", "ブランチ一覧から名前を入力して切り替えます。"), ("", "タグやコミットを入力すると、そこから新しいブランチを作成できます。"), ("--no-post", "設定の post_checkout_command (例: npm install) を実行せずに切り替えます。")] },
+    CommandDefinition { name: "merge", aliases: &["mg"], description: "指定ブランチを現在のブランチにマージします。(--autostash)", handler: cmds::git_merge, examples: &[("", "入力したブランチを現在のブランチにマージします。"), ("--autostash", "未コミットの変更を一時退避してマージし、終了後に戻します。")] },
+    CommandDefinition { name: "copy", aliases: &["cp"], description: "ブランチをローカルにコピーし、オプションでリモートにプッシュします。(--prefix <接頭辞>, --no-prefix)", handler: cmds::git_copy, examples: &[("", "既存ブランチから新しいブランチを作成します。"), ("--prefix feature/", "入力したブランチ名の前に feature/ を付けます (設定の branch_prefix より優先)。")] },
+    CommandDefinition { name: "delete", aliases: &["del"], description: "ローカルおよびオプションでリモートブランチを削除します。(--force, -f で未マージでも削除, --dry-run)", handler: cmds::git_delete, examples: &[("", "ローカルブランチ (または <リモート>/<名前> でリモート。リモートの既定は origin) を削除します。"), ("--force", "未マージの実験用ブランチなども確認の上で削除します。"), ("--dry-run", "どのブランチがどう削除されるかを表示するだけで、何も削除しません。")] },
+    CommandDefinition { name: "create", aliases: &["cr"], description: "新しいローカルブランチを作成し、オプションでリモートにプッシュします。(--from <ref>, --prefix <接頭辞>, --no-prefix)", handler: cmds::git_create, examples: &[("", "新しいブランチを作成します。フィーチャーブランチ上では最新の既定ブランチからの作成を提案します。"), ("--from v1.2", "タグ v1.2 から新しいブランチを作成します。"), ("--prefix feature/JIRA-123-", "入力した名前の前に接頭辞を付けます (例: login → feature/JIRA-123-login)。")] },
+    CommandDefinition { name: "move", aliases: &["mv"], description: "未コミットの変更を別の(または新しい)ブランチへ移動します。", handler: cmds::git_move_changes, examples: &[("", "未コミットの変更をスタッシュ経由で別のブランチへ移します。")] },
+    CommandDefinition { name: "examples", aliases: &["ex"], description: "指定したコマンドの使用例を表示します。", handler: cmds::show_examples, examples: &[("save", "save コマンドの使用例を表示します。"), ("", "すべてのコマンドの使用例を表示します。")] },
+    CommandDefinition { name: "tags", aliases: &[], description: "ローカルとリモート (既定: origin) のタグの差分を表示し、同期します。", handler: cmds::git_tags, examples: &[("", "タグを取得し、片方にしか存在しないタグを一覧表示します。")] },
+    CommandDefinition { name: "contains", aliases: &[], description: "指定したコミットを含むブランチ(ローカル/リモート)を表示します。", handler: cmds::git_contains, examples: &[("", "最近のコミットから選択して、それを含むブランチを表示します。"), ("a1b2c3d", "コミット a1b2c3d がどのブランチに取り込まれたか確認します。")] },
+    CommandDefinition { name: "status", aliases: &[], description: "変更ファイルをステージ済み/未ステージ/未追跡ごとに表示します。", handler: cmds::git_status, examples: &[("", "作業ツリーの状態を表示します。サブモジュールの変更は区別して表示されます。")] },
+    CommandDefinition { name: "restore", aliases: &["rs"], description: "ファイルを最後のコミットまたは指定したブランチ/コミットの内容に戻します。(--from <ref>, --patch)", handler: cmds::git_restore, examples: &[("src/main.rs --patch", "src/main.rs の変更をハンク単位で選んで破棄します。"), ("", "戻す元(HEAD/ブランチ/コミット)とファイルを選択して復元します。"), ("src/main.rs --from v1.0", "src/main.rs をタグ v1.0 時点の内容に戻します。")] },
+    CommandDefinition { name: "stash", aliases: &["st"], description: "作業中の変更を退避し、退避したスタッシュを選択して適用します。(push|list|pop|apply|drop|branch|diff, --show で内容を確認)", handler: cmds::git_stash, examples: &[("push", "未コミットの変更をメッセージ付きで退避します (switch 前などに)。"), ("push \"ログイン画面の途中\"", "メッセージを指定して退避します。"), ("", "スタッシュの一覧を表示します (list と同じ)。"), ("pop", "スタッシュを選択して適用し、一覧から削除します。"), ("pop --show", "適用前にスタッシュの差分をページャーで確認します。"), ("apply", "スタッシュを一覧に残したまま適用します。"), ("drop", "スタッシュを選択して削除します。"), ("branch fix/old-work", "スタッシュ作成時のコミットから fix/old-work を作成し、そこでスタッシュを適用します (現在のブランチに適用できない場合に)。"), ("diff", "スタッシュと現在の作業ツリーの差分を表示します (--head で HEAD との差分)。")] },
+    CommandDefinition { name: "aliases", aliases: &[], description: "組み込みと設定ファイルのエイリアスの一覧を表示します。", handler: cmds::show_aliases, examples: &[("", "エイリアスと展開後のコマンドを表示します。")] },
+    CommandDefinition { name: "size", aliases: &[], description: "リポジトリのオブジェクト数とディスク使用量を表示します。", handler: cmds::git_size, examples: &[("", "オブジェクト数とサイズを表示し、必要なら git gc を提案します。")] },
+    CommandDefinition { name: "unpushed", aliases: &[], description: "未プッシュのコミットがあるローカルブランチを一覧表示します。", handler: cmds::git_unpushed, examples: &[("", "ローカルにしか無いコミットを持つブランチとその数を表示します。")] },
+    CommandDefinition { name: "repo", aliases: &[], description: "リポジトリとリモートの管理を行います。(remote test, remote fix-head, remote logout, config pull-mode, sparse)", handler: cmds::git_repo, examples: &[("remote test", "リモート (既定: origin) への SSH/HTTPS 接続と認証を確認します。"), ("remote fix-head", "<リモート>/HEAD (既定: origin/HEAD) をリモートの既定ブランチに向け直します (既定ブランチを特定できない場合に)。"), ("remote logout", "リモート (既定: origin) のホストについて保存・キャッシュされた資格情報を削除します (別アカウントに切り替える場合に)。"), ("config pull-mode rebase", "git pull の既定の動作 (pull.rebase/pull.ff) をリベースに設定します (--global で全体に)。"), ("sparse", "スパースチェックアウトを有効にし、展開中のディレクトリを表示します。"), ("sparse add", "展開するディレクトリを一覧から選んで追加します。"), ("sparse remove docs", "docs ディレクトリを作業ツリーから外します。"), ("sparse disable", "スパースチェックアウトをやめて全ファイルを展開します。")] },
+    CommandDefinition { name: "collapse", aliases: &[], description: "現在のブランチのコミットを1つにまとめます (既定ブランチとの分岐点から)。", handler: cmds::git_collapse, examples: &[("", "フィーチャーブランチの全コミットを1つのコミットにまとめます。")] },
+    CommandDefinition { name: "cherry-pick", aliases: &["cx"], description: "指定したコミットの変更を現在のブランチに取り込みます。未指定時は他のブランチからコミットを選択します。(--no-commit)", handler: cmds::git_cherry_pick, examples: &[("", "取り込み元のブランチとコミットを選択して取り込みます。"), ("a1b2c3d", "コミット a1b2c3d を現在のブランチに取り込みます。"), ("a1b2c3d --no-commit", "コミットせずに変更だけを作業ツリーに適用し、編集後に save でコミットします。")] },
+    CommandDefinition { name: "conflict-check", aliases: &[], description: "マージせずに、指定ブランチとのコンフリクトを事前に確認します。", handler: cmds::git_conflict_check, examples: &[("", "ブランチを選択し、マージした場合にコンフリクトするファイルを表示します。"), ("feature/login", "feature/login をマージした場合のコンフリクトを確認します。")] },
+    CommandDefinition { name: "config", aliases: &[], description: "git の設定を表示・変更します。(list|get|set, --global)", handler: cmds::git_config, examples: &[("", "有効な git の設定をキーの順に表示します。"), ("get user.email", "user.email の現在の値を表示します。"), ("set user.name \"Taro Yamada\" --global", "グローバル設定の user.name を変更します。"), ("set", "よく使う設定キーを一覧から選んで変更します。")] },
+    CommandDefinition { name: "changelog", aliases: &[], description: "2つの参照間のコミットから Markdown の変更履歴を作成します。", handler: cmds::git_changelog, examples: &[("", "最新のタグから HEAD までの変更履歴を出力します。"), ("v1.0 v1.1 > CHANGELOG.md", "v1.0 から v1.1 までの変更履歴をファイルに書き出します。")] },
+    CommandDefinition { name: "cleanup", aliases: &[], description: "既定ブランチにマージ済みで未プッシュのコミットが無いローカルブランチをまとめて削除します。", handler: cmds::git_cleanup, examples: &[("", "安全に削除できるブランチを一覧から選んで削除します。")] },
+    CommandDefinition { name: "prompt", aliases: &[], description: "シェルのプロンプト向けに現在のブランチと状態を1行で表示します。(--color)", handler: cmds::git_prompt, examples: &[("", "例: 'main*↑1↓2' (ブランチ名、未コミットの変更、プッシュ/プルが必要なコミット数)。"), ("--color", "色付きで表示します。"), ("", "bash の例: PS1='\\w $(mygit prompt 2>/dev/null)\\$ '")] },
+    CommandDefinition { name: "recover", aliases: &[], description: "削除したブランチを reflog から探して復元します。(--lost)", handler: cmds::git_recover, examples: &[("", "最近削除したブランチを一覧から選んで、最後のコミットの位置に作り直します。"), ("--lost", "どこからも辿れなくなったコミットも候補に含めます (git fsck)。")] },
+    CommandDefinition { name: "rebase", aliases: &["rb"], description: "現在のブランチを指定したブランチの上にリベースします。(--select, --autostash, --interactive, --autosquash)", handler: cmds::git_rebase, examples: &[("main", "現在のブランチを main の先端の上に付け替えます。"), ("--select", "リベース先のブランチを一覧から選びます (現在のブランチは除きます)。"), ("--autostash", "未コミットの変更を一時的に退避してリベースし、終わったら戻します。"), ("--interactive", "既定ブランチからのコミットを一覧で並べ替え・まとめ・削除してからリベースします。"), ("--autosquash", "fixup!/squash! コミットを対象のコミットの後ろに並べた計画から始めます (--interactive と同じ画面)。")] },
+    CommandDefinition { name: "ancestry", aliases: &[], description: "2つのブランチ/コミットの関係 (祖先・子孫・分岐) を表示します。", handler: cmds::git_ancestry, examples: &[("", "2つのブランチを一覧から選んで関係を調べます。"), ("feature/login main", "feature/login が main から派生しているか、どれだけ進んでいるかを表示します。")] },
+    CommandDefinition { name: "strategy", aliases: &[], description: "最新の変更を取り込む方法 (merge|rebase|ff-only) を表示・設定します。", handler: cmds::git_strategy, examples: &[("", "現在の取り込み方法 (設定ファイルの update_strategy) を表示します。"), ("rebase", "プル時などに常にリベースで取り込むよう設定します。"), ("ff-only", "fast-forward できない取り込みは行わないよう設定します。")] },
+    CommandDefinition { name: "split", aliases: &[], description: "変更ファイルを2つのグループに分けて、別々のコミットにします。", handler: cmds::git_split, examples: &[("", "1つ目と2つ目のコミットに含めるファイルをそれぞれ選び、メッセージを付けてコミットします。")] },
+    CommandDefinition { name: "attributes", aliases: &[], description: "ファイルに適用される .gitattributes の属性 (改行・バイナリ・マージ方法など) を表示します。", handler: cmds::git_attributes, examples: &[("", "追跡中のファイルを選んで、有効な属性を表示します。"), ("assets/logo.png", "logo.png がバイナリとして扱われているかなどを確認します。")] },
+    CommandDefinition { name: "bundle", aliases: &[], description: "全ブランチ・タグをバンドルファイルにバックアップし、そこから復元します。(create|clone|fetch)", handler: cmds::git_bundle, examples: &[("create", "全ての参照を <リポジトリ名>.bundle に書き出します。"), ("create /media/usb/backup.bundle", "USB メモリなどにバックアップします。"), ("clone backup.bundle myrepo", "バンドルから新しいリポジトリを作成します。"), ("fetch backup.bundle", "バンドル内のブランチを bundle/<名前> として取り込みます。")] },
+    CommandDefinition { name: "addto", aliases: &[], description: "追加し忘れたファイルを直前のコミットに加えます (メッセージは変更しません)。", handler: cmds::git_addto, examples: &[("", "変更・未追跡のファイルを選んで直前のコミットに追加します。"), ("src/lib.rs README.md", "指定したファイルを直前のコミットに追加します。")] },
+    CommandDefinition { name: "default-check", aliases: &[], description: "ローカルの既定ブランチ (main/master) がリモートより古くないか確認し、更新します。", handler: cmds::git_default_check, examples: &[("", "リモート (既定: origin) と比べて遅れ/進み/分岐を表示し、遅れている場合は fast-forward を提案します。")] },
+    CommandDefinition { name: "shortlog", aliases: &[], description: "範囲内のコミット数を作成者ごとに集計して表示します。", handler: cmds::git_shortlog, examples: &[("", "現在のブランチの既定ブランチからの分岐以降を作成者ごとに集計します。"), ("v1.0..v2.0", "v1.0 から v2.0 までの作成者ごとのコミット数を表示します。")] },
+    CommandDefinition { name: "restack", aliases: &[], description: "既定ブランチを取得し、現在の作業ブランチをその最新の上にリベースし直します。", handler: cmds::git_restack, examples: &[("", "リモート (既定: origin) の <既定ブランチ> を取得してリベースし、再適用したコミットと基点の変化を表示します。")] },
+    CommandDefinition { name: "hook", aliases: &[], description: "Git フックの一覧表示と、一時的な無効化/再有効化を行います。", handler: cmds::git_hook, examples: &[("list", "インストール済みのフックと有効/無効の状態を表示します。"), ("disable pre-commit", "pre-commit フックを <名前>.disabled にリネームして無効化します。"), ("enable", "無効化したフックを選んで再有効化します。")] },
+    CommandDefinition { name: "pickaxe", aliases: &[], description: "指定した文字列を追加/削除したコミットを探します (git log -S/-G)。", handler: cmds::git_pickaxe, examples: &[("parse_config", "'parse_config' を追加または削除したコミットを表示します。"), ("'fn \\w+_legacy' src --regex", "src 以下で正規表現に一致する行を変更したコミットを表示します。")] },
+    CommandDefinition { name: "identity", aliases: &[], description: "設定ファイルの [identities.<名前>] から選んだユーザー名/メールアドレス/署名鍵をこのリポジトリに設定します。", handler: cmds::git_identity, examples: &[("", "現在のユーザー情報を表示し、設定するプロファイルを選択します。"), ("work", "プロファイル 'work' をこのリポジトリに設定します。"), ("--auto", "リモート (既定: origin) のホストに一致する hosts を持つプロファイルを設定します。")] },
+    CommandDefinition { name: "diverge", aliases: &[], description: "すべてのローカルブランチの上流と進み/遅れの数を表にして表示します。", handler: cmds::git_diverge, examples: &[("", "上流とのずれが大きい順にブランチを表示します。"), ("--fetch", "先にリモートを取得してから表示します。"), ("detail", "現在のブランチのローカルのみ/リモートのみのコミットを表示します (ブランチ名も指定可)。")] },
+    CommandDefinition { name: "start", aliases: &[], description: "チケット番号から命名規則に沿ったブランチを最新の既定ブランチから作成し、切り替えます。(--push)", handler: cmds::git_start, examples: &[("PROJ-123 login form validation", "feature/PROJ-123-login-form-validation を作成して切り替えます (形式は設定の branch_name_template)。"), ("PROJ-123 --push", "作成したブランチをそのままリモートにプッシュし追跡設定します。")] },
+    CommandDefinition { name: "finish", aliases: &[], description: "現在の作業ブランチを既定ブランチにマージ (またはスカッシュ) してプッシュし、作業ブランチを削除します。", handler: cmds::git_finish, examples: &[("", "既定ブランチを最新にしてからマージし、各段階で確認しながら後片付けします。")] },
+    CommandDefinition { name: "merge-info", aliases: &[], description: "git のマージに関する設定 (fast-forward、rerere など) と、指定ブランチのマージが fast-forward になるかを表示します。", handler: cmds::git_merge_info, examples: &[("", "マージ関連の設定を表示し、マージするブランチを選んで結果の種類を確認します。"), ("feature/login", "feature/login を現在のブランチにマージした場合の種類を表示します。")] },
+    CommandDefinition { name: "track", aliases: &[], description: "ローカルに無いリモートブランチを選び、追跡するローカルブランチをまとめて作成します (切り替えはしません)。", handler: cmds::git_track, examples: &[("", "リモートを取得し、ローカルに無いブランチの一覧から作成するものを選びます。"), ("--all", "ローカルに無いリモートブランチをすべて追跡します。")] },
+    CommandDefinition { name: "push-upto", aliases: &[], description: "未プッシュのコミットから選んだコミットまでを、現在のブランチとしてリモートにプッシュします (それより新しいコミットはローカルに残します)。", handler: cmds::git_push_upto, examples: &[("", "未プッシュのコミット一覧から、どこまでプッシュするかを選びます。"), ("a1b2c3d", "a1b2c3d までをプッシュします。")] },
+    CommandDefinition { name: "bigfiles", aliases: &[], description: "履歴全体から大きなファイル (blob) を探し、サイズの大きい順にパスと追加したコミットを表示します。(--top <件数>)", handler: cmds::git_bigfiles, examples: &[("", "履歴中の大きなファイルを上位 10 件表示します。"), ("--top 30", "上位 30 件を表示します。")] },
+    CommandDefinition { name: "untrack", aliases: &[], description: "パターンに一致する追跡中のファイルを追跡対象から外し (ファイルは残します)、.gitignore に追加します。", handler: cmds::git_untrack, examples: &[("target", "誤ってコミットした target ディレクトリの追跡をやめ、.gitignore に追加します。"), ("'*.log'", "すべての .log ファイルの追跡をやめ、*.log を .gitignore に追加します。")] },
+    CommandDefinition { name: "changed-dirs", aliases: &[], description: "指定ブランチで (分岐点以降に) 変更されたファイルを、ディレクトリごとに集計して表示します。(--depth <階層>)", handler: cmds::git_changed_dirs, examples: &[("", "比較するブランチを一覧から選び、最上位のディレクトリごとの変更ファイル数を表示します。"), ("feature/login --depth 2", "feature/login の変更を2階層目のディレクトリごとに集計します。")] },
+    CommandDefinition { name: "rename-batch", aliases: &[], description: "正規表現に一致するローカルブランチをまとめて改名します。(--push でリモートも改名)", handler: cmds::git_rename_batch, examples: &[("'^(fix|feat)/' 'team-a/$1/'", "fix/... と feat/... のブランチに team-a/ を付けます (改名前に一覧で確認します)。"), ("'^wip-' 'draft/' --push", "wip-... を draft/... に改名し、リモート (既定: origin) 上のブランチも改名します。")] },
+    CommandDefinition { name: "log", aliases: &["lg"], description: "コミット履歴をグラフ付きで簡潔に表示します。(-n で件数、--all で全ブランチ)", handler: cmds::git_log, examples: &[("", "現在のブランチの直近20件をグラフ表示します。"), ("-n 50 --all", "全ブランチの直近50件を表示します。")] },
+    CommandDefinition { name: "rename", aliases: &["rn"], description: "ローカルブランチを改名します。リモートに旧名のブランチがあれば、リモートも改名するか確認します。", handler: cmds::git_rename, examples: &[("", "改名するブランチを一覧から選び、新しい名前を入力します。"), ("feature/login feature/PROJ-12-login", "ブランチ名を直接指定して改名します。")] },
+    CommandDefinition { name: "reset", aliases: &[], description: "直前のコミットを取り消します。(--soft|--mixed|--hard, --count <n>, --dry-run)", handler: cmds::git_reset, examples: &[("", "取り消し方 (変更をステージに残す/作業ツリーに残す/破棄する) を選んで直前のコミットを取り消します。"), ("--soft --count 3", "直前の3コミットを取り消し、変更はステージしたまま残します (まとめ直す場合など)。")] },
+    CommandDefinition { name: "pull", aliases: &["pl"], description: "現在のブランチにリモートの最新の変更を取り込みます。(--merge|--rebase|--ff-only)", handler: cmds::git_pull, examples: &[("", "設定の update_strategy (未設定時はマージ) でリモートの同名ブランチをプルします。"), ("--rebase", "自分のコミットをリモートの最新の上に付け替えて取り込みます。")] },
+    CommandDefinition { name: "sync", aliases: &[], description: "リモートを取得し、遅れているローカルブランチをまとめて fast-forward で最新にします。", handler: cmds::git_sync, examples: &[("", "fetch --prune の後、リモートより遅れているブランチを更新し、更新/スキップ/最新の一覧を表示します (分岐したブランチは触りません)。")] },
+    CommandDefinition { name: "undo", aliases: &[], description: "途中で止まっている merge/rebase/cherry-pick を中止し、開始前の状態に戻します。", handler: cmds::git_undo, examples: &[("", "コンフリクトで止まっている操作を確認して中止します (git merge/rebase/cherry-pick --abort)。")] },
+    CommandDefinition { name: "completions", aliases: &[], description: "シェルの補完スクリプトを出力します。(bash|zsh|fish|powershell)", handler: cmds::git_completions, examples: &[("bash >> ~/.bashrc", "bash でコマンド名とグローバルオプションを補完できるようにします。"), ("zsh > ~/.zfunc/_mygit", "zsh 用の補完関数を fpath 上のディレクトリに置きます。"), ("fish > ~/.config/fish/completions/mygit.fish", "fish 用の補完を設定します。")] },
+    CommandDefinition { name: "help", aliases: &["h"], description: "このヘルプメッセージを表示します。", handler: cmds::show_help, examples: &[("", "コマンド一覧を表示します。")] },
 ];
 
 mod cmds;
+mod utils;
+
+// コマンド名または組み込みエイリアスから定義を探す
+pub fn find_command(name: &str) -> Option<&'static CommandDefinition> {
+    COMMAND_DEFINITIONS.iter().find(|c| c.name == name || c.aliases.contains(&name))
+}
+// 設定ファイルのエイリアス (例: co = "switch", sc = "save --check") を展開する。組み込みコマンドより優先しない
+fn expand_user_alias(args: Vec<String>) -> Vec<String> {
+    let Some(name) = args.get(1) else { return args };
+    if find_command(name).is_some() { return args; }
+    match utils::config().aliases.get(name) {
+        Some(expansion) => {
+            let mut expanded = vec![args[0].clone()];
+            expanded.extend(expansion.split_whitespace().map(|s| s.to_string()));
+            expanded.extend(args.into_iter().skip(2));
+            expanded
+        }
+        None => args,
+    }
+}
+
 // use cmds::CommandHandler; // CommandHandler は main.rs で pub type となったので不要
 
 fn main() {
-    let args: Vec<String> = std::env::args().collect();
+    let args = expand_user_alias(utils::extract_global_options(std::env::args().collect()));
     let program_name = args.first().map_or("mygit", |s| s.as_str());
 
     if args.len() < 2 {
@@ -147,11 +790,13 @@ fn main() {
 
     let command_name = args[1].as_str();
 
-    for cmd_def in COMMAND_DEFINITIONS {
-        if cmd_def.name == command_name {
-            (cmd_def.handler)(&args);
+    if let Some(cmd_def) = find_command(command_name) {
+        if args.iter().skip(2).any(|a| a == "--examples") {
+            cmds::print_examples(cmd_def);
             return;
         }
+        (cmd_def.handler)(&args);
+        return;
     }
 
     eprintln!("エラー: 不明なコマンド '{}'", command_name);
@@ -0,0 +1,844 @@
+// utils.rs
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use serde::Deserialize;
+use crate::CommandResult;
+
+// --- 設定ファイル (~/.config/mygit/config.toml) ---
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct Config {
+    // save --check で、コミット前に実行するシェルコマンド (例: "cargo test")
+    pub pre_save_command: Option<String>,
+    // true の場合、merge などで常に --autostash を使う
+    pub autostash: bool,
+    // [aliases] セクション: エイリアス名 → 展開後のコマンド (引数を含めてよい)
+    pub aliases: BTreeMap<String, String>,
+    // コミットメッセージ件名のチェック (save 時)。いずれも設定した場合のみ有効
+    pub commit_subject_max_length: Option<usize>, // 例: 72
+    pub commit_subject_no_period: bool,           // 末尾の "." を禁止
+    pub commit_subject_no_lowercase: bool,        // すべて小文字の件名を禁止
+    pub commit_subject_no_uppercase: bool,        // すべて大文字の件名を禁止
+    // true の場合、チェックに違反したらコミットを中止する (既定は警告のみ)
+    pub commit_subject_strict: bool,
+    // true の場合、save のプッシュ前に常に --check-base を行う
+    pub require_updated_base: bool,
+    // 最新の変更を取り込む方法 (merge / rebase / ff-only)。未設定時は merge
+    pub update_strategy: Option<UpdateStrategy>,
+    // switch/create/copy でブランチを切り替えた後に実行するシェルコマンド (例: "npm install")。--no-post で省略
+    pub post_checkout_command: Option<String>,
+    // start コマンドで作るブランチ名の形式。{ticket} と {slug} (説明から作る) を置き換える。未設定時は "feature/{ticket}-{slug}"
+    pub branch_name_template: Option<String>,
+    // create/copy で入力したブランチ名の前に付ける接頭辞 (例: "feature/")。--prefix で上書き、--no-prefix で省略
+    pub branch_prefix: Option<String>,
+    // 既定のリモート名。--remote で上書き。未設定時は "origin"
+    pub remote: Option<String>,
+    // save でプッシュするか。true/false なら確認せずに従う (--push/--no-push で上書き)。未設定時は確認する
+    pub auto_push: Option<bool>,
+    // false の場合、branch で一覧の前にリモートを取得しない (--fetch で上書き)。未設定時は取得する
+    pub auto_fetch: Option<bool>,
+    // save のコミットメッセージの前に付ける接頭辞 (例: "[PROJ-12] ")。--no-commit-prefix で省略
+    pub commit_prefix: Option<String>,
+    // [identities.<名前>] セクション: identity コマンドでリポジトリに設定するユーザー情報
+    pub identities: BTreeMap<String, Identity>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Identity {
+    pub name: String,
+    pub email: String,
+    pub signing_key: Option<String>,
+    // identity --auto で、origin のホストがこれに一致すれば選ばれる (例: ["github.example.com"])
+    #[serde(default)]
+    pub hosts: Vec<String>,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum UpdateStrategy { Merge, Rebase, FfOnly }
+
+impl UpdateStrategy {
+    pub const ALL: [UpdateStrategy; 3] = [UpdateStrategy::Merge, UpdateStrategy::Rebase, UpdateStrategy::FfOnly];
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UpdateStrategy::Merge => "merge",
+            UpdateStrategy::Rebase => "rebase",
+            UpdateStrategy::FfOnly => "ff-only",
+        }
+    }
+    pub fn parse(value: &str) -> Option<UpdateStrategy> {
+        Self::ALL.into_iter().find(|s| s.as_str() == value)
+    }
+}
+
+fn config_path() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("mygit").join("config.toml"))
+}
+
+fn load_config() -> Config {
+    let Some(path) = config_path() else { return Config::default() };
+    let Ok(content) = std::fs::read_to_string(&path) else { return Config::default() }; // ファイルが無ければ既定値
+    match toml::from_str(&content) {
+        Ok(config) => config,
+        Err(e) => {
+            log::warn!("警告: 設定ファイル {} を読み込めません。既定値を使用します。({})", path.display(), e);
+            Config::default()
+        }
+    }
+}
+
+// 設定ファイルの最上位のキーを1つだけ書き換える (無ければ先頭に追加)。コメントや他の設定はそのまま残す
+pub fn set_config_value(key: &str, toml_value: &str) -> CommandResult<PathBuf> {
+    let path = config_path().ok_or("エラー: 設定ファイルの場所 (HOME) を特定できません。")?;
+    let content = std::fs::read_to_string(&path).unwrap_or_default();
+    let new_line = format!("{} = {}", key, toml_value);
+    let mut lines: Vec<String> = content.lines().map(|l| l.to_string()).collect();
+    // 最初の [section] より前だけが最上位のキー
+    let top_level_end = lines.iter().position(|l| l.trim_start().starts_with('[')).unwrap_or(lines.len());
+    let existing = lines[..top_level_end].iter().position(|l| {
+        l.split_once('=').is_some_and(|(k, _)| k.trim() == key)
+    });
+    match existing {
+        Some(i) => lines[i] = new_line,
+        None => lines.insert(0, new_line),
+    }
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("エラー: {} を作成できません。詳細: {}", dir.display(), e))?;
+    }
+    std::fs::write(&path, lines.join("\n") + "\n").map_err(|e| format!("エラー: {} に書き込めません。詳細: {}", path.display(), e))?;
+    Ok(path)
+}
+
+// --- 状態ファイル (~/.cache/mygit/recent_branches.toml) ---
+// リポジトリの最上位のパス → 最近 switch で選んだブランチ (新しい順)。壊れていても無視して作り直す
+const RECENT_BRANCHES_MAX: usize = 2;
+
+fn recent_branches_path() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CACHE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".cache"),
+    };
+    Some(base.join("mygit").join("recent_branches.toml"))
+}
+
+fn load_recent_branches() -> BTreeMap<String, Vec<String>> {
+    recent_branches_path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|content| toml::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+pub fn recent_branches(repo: &str) -> Vec<String> {
+    load_recent_branches().remove(repo).unwrap_or_default()
+}
+
+// 切り替え先と切り替え元を記録する (書き込めなくてもエラーにしない)
+pub fn remember_switched_branch(repo: &str, branch: &str, previous: &str) {
+    let Some(path) = recent_branches_path() else { return };
+    let mut all = load_recent_branches();
+    let recent = all.entry(repo.to_string()).or_default();
+    for name in [previous, branch] {
+        if name.is_empty() { continue; }
+        recent.retain(|b| b != name);
+        recent.insert(0, name.to_string());
+    }
+    recent.truncate(RECENT_BRANCHES_MAX);
+    let Ok(content) = toml::to_string(&all) else { return };
+    if let Some(dir) = path.parent() { let _ = std::fs::create_dir_all(dir); }
+    let _ = std::fs::write(path, content);
+}
+
+// 設定はプロセス中で一度だけ読み込む
+pub fn config() -> &'static Config {
+    static CONFIG: OnceLock<Config> = OnceLock::new();
+    CONFIG.get_or_init(load_config)
+}
+
+// 説明をブランチ名に使える形にする (英数字以外は "-" に、小文字化)。例: "Fix Login bug!" → "fix-login-bug"
+pub fn slugify(text: &str) -> String {
+    text.to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+// 端末上での表示幅。色付けのエスケープシーケンス (ESC [ ... m など) は数えず、全角文字は 2 として数える
+pub fn visible_width(s: &str) -> usize {
+    let mut width = 0;
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // CSI シーケンスは終端の英字まで読み飛ばす
+            if chars.next() == Some('[') {
+                for c in chars.by_ref() {
+                    if c.is_ascii_alphabetic() { break; }
+                }
+            }
+            continue;
+        }
+        width += char_width(c);
+    }
+    width
+}
+
+fn char_width(c: char) -> usize {
+    match c as u32 {
+        // 結合文字・ゼロ幅文字・異体字セレクタ
+        0x0300..=0x036F | 0x200B..=0x200F | 0x3099..=0x309A | 0xFE00..=0xFE0F => 0,
+        c if c < 0x20 => 0,
+        // 東アジアの全角 (ハングル字母, CJK 記号・かな, CJK 統合漢字, ハングル音節, 全角英数・記号, 絵文字など)
+        0x1100..=0x115F | 0x2E80..=0x303E | 0x3041..=0x33FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF | 0xAC00..=0xD7A3 | 0xF900..=0xFAFF | 0xFE30..=0xFE4F | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6 | 0x1F300..=0x1F64F | 0x1F680..=0x1F6FF | 0x1F900..=0x1F9FF | 0x1FA70..=0x1FAFF
+        | 0x20000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
+
+// --- グローバルオプション (コマンド名の前後どちらにも指定できる) ---
+static PATH_SCOPE: OnceLock<String> = OnceLock::new();
+
+// --path で指定されたディレクトリ。status/add/log などの対象をその配下に限定する
+pub fn path_scope() -> Option<&'static str> {
+    PATH_SCOPE.get().map(|s| s.as_str())
+}
+
+static REMOTE: OnceLock<String> = OnceLock::new();
+
+// --remote で指定されたリモート名。未指定なら設定ファイルの remote、それも無ければ origin
+pub fn remote() -> &'static str {
+    REMOTE.get().map(|s| s.as_str())
+        .or(config().remote.as_deref())
+        .unwrap_or("origin")
+}
+
+static VERBOSE: AtomicBool = AtomicBool::new(false);
+
+// --verbose/-v が指定されたか。実行する git コマンドを表示する
+pub fn verbose() -> bool {
+    VERBOSE.load(Ordering::Relaxed)
+}
+
+static ASSUME_YES: AtomicBool = AtomicBool::new(false);
+
+// --yes/-y が指定されたか。確認はすべて y とみなし、選択や入力が必要な場面ではエラーにする
+pub fn assume_yes() -> bool {
+    ASSUME_YES.load(Ordering::Relaxed)
+}
+
+// 進行状況などの補足メッセージは log 経由で標準エラー出力に出す (結果の出力は println! のまま)
+fn init_logger(level: log::LevelFilter) {
+    use std::io::Write;
+    use colored::Colorize;
+    env_logger::Builder::new()
+        .filter_level(level)
+        .format(|buf, record| match record.level() {
+            log::Level::Error => writeln!(buf, "{}", record.args().to_string().red()),
+            log::Level::Warn => writeln!(buf, "{}", record.args().to_string().yellow()),
+            log::Level::Info => writeln!(buf, "{}", record.args()),
+            _ => writeln!(buf, "{}", format!("[{}] {}", record.level().as_str().to_lowercase(), record.args()).dimmed()),
+        })
+        .init();
+}
+
+// グローバルオプションを取り除いた引数を返す
+pub fn extract_global_options(args: Vec<String>) -> Vec<String> {
+    let mut rest = Vec::with_capacity(args.len());
+    let mut log_level = log::LevelFilter::Info;
+    let mut iter = args.into_iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--log-level" || arg.starts_with("--log-level=") {
+            let value = match arg.strip_prefix("--log-level=") {
+                Some(value) => value.to_string(),
+                None => iter.next().unwrap_or_default(),
+            };
+            log_level = match value.as_str() {
+                "error" => log::LevelFilter::Error,
+                "warn" => log::LevelFilter::Warn,
+                "info" => log::LevelFilter::Info,
+                "debug" => log::LevelFilter::Debug,
+                _ => {
+                    eprintln!("エラー: --log-level には error/warn/info/debug のいずれかを指定してください。('{}')", value);
+                    std::process::exit(1);
+                }
+            };
+        } else if arg == "--path" || arg.starts_with("--path=") {
+            let value = match arg.strip_prefix("--path=") {
+                Some(value) => value.to_string(),
+                None => iter.next().unwrap_or_default(),
+            };
+            if !std::path::Path::new(&value).is_dir() {
+                eprintln!("エラー: --path に指定したディレクトリ '{}' が存在しません。", value);
+                std::process::exit(1);
+            }
+            let _ = PATH_SCOPE.set(value);
+        } else if arg == "--remote" || arg.starts_with("--remote=") {
+            let value = match arg.strip_prefix("--remote=") {
+                Some(value) => value.to_string(),
+                None => iter.next().unwrap_or_default(),
+            };
+            if value.is_empty() || value.contains('/') {
+                eprintln!("エラー: --remote にはリモート名を指定してください。('{}')", value);
+                std::process::exit(1);
+            }
+            let _ = REMOTE.set(value);
+        } else if arg == "--verbose" || arg == "-v" {
+            VERBOSE.store(true, Ordering::Relaxed);
+        } else if arg == "--yes" || arg == "-y" {
+            ASSUME_YES.store(true, Ordering::Relaxed);
+        } else {
+            rest.push(arg);
+        }
+    }
+    init_logger(log_level);
+    rest
+}
+
+// バイト数を "12.3 MiB" のような表記にする
+pub fn format_size(bytes: u64) -> String {
+    let units = ["bytes", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 { format!("{} {}", bytes, units[0]) } else { format!("{:.1} {}", size, units[unit]) }
+}
+
+// --- 外部シェルコマンドの実行 (出力はそのまま端末に流す) ---
+pub fn run_shell_command(command_line: &str) -> CommandResult<bool> {
+    let mut command = if cfg!(windows) {
+        let mut c = Command::new("cmd");
+        c.args(["/C", command_line]);
+        c
+    } else {
+        let mut c = Command::new("sh");
+        c.args(["-c", command_line]);
+        c
+    };
+    match command.stdout(Stdio::inherit()).stderr(Stdio::inherit()).status() {
+        Ok(status) => Ok(status.success()),
+        Err(e) => Err(format!("エラー: コマンド \"{}\" の実行に失敗しました。詳細: {}", command_line, e)),
+    }
+}
+
+// --- `git status --porcelain=v2` の解析 ---
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusEntry {
+    pub index: char,    // ステージ側の状態 ('.' は変更なし, '?' は未追跡)
+    pub worktree: char, // 作業ツリー側の状態
+    pub path: String,
+    pub is_submodule: bool,
+    pub is_conflicted: bool,
+}
+
+impl StatusEntry {
+    pub fn is_untracked(&self) -> bool { self.index == '?' }
+    pub fn is_staged(&self) -> bool { !self.is_untracked() && !self.is_conflicted && self.index != '.' }
+    pub fn is_unstaged(&self) -> bool { !self.is_untracked() && !self.is_conflicted && self.worktree != '.' }
+}
+
+pub fn parse_status(porcelain_v2: &str) -> Vec<StatusEntry> {
+    let mut entries = Vec::new();
+    for line in porcelain_v2.lines() {
+        let mut fields = line.splitn(2, ' ');
+        let kind = fields.next().unwrap_or("");
+        let rest = fields.next().unwrap_or("");
+        // 種類ごとのパス直前までのフィールド数 ("1": 7, "2": 8 (+ 元パス), "u": 9)
+        let skip = match kind {
+            "1" => 7,
+            "2" => 8,
+            "u" => 9,
+            "?" => {
+                entries.push(StatusEntry { index: '?', worktree: '?', path: rest.to_string(), is_submodule: false, is_conflicted: false });
+                continue;
+            }
+            _ => continue, // "#" ヘッダーや "!" (無視ファイル)
+        };
+        let parts: Vec<&str> = rest.splitn(skip + 1, ' ').collect();
+        if parts.len() <= skip { continue; }
+        let mut xy = parts[0].chars();
+        let path = parts[skip].split('\t').next().unwrap_or("").to_string(); // "2" の元パスを除去
+        entries.push(StatusEntry {
+            index: xy.next().unwrap_or('.'),
+            worktree: xy.next().unwrap_or('.'),
+            path,
+            is_submodule: parts[1].starts_with('S'),
+            is_conflicted: kind == "u",
+        });
+    }
+    entries
+}
+
+// 作業ツリーの変更の件数 (parse_status の結果を集計したもの)
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct WorkingTreeStatus {
+    pub staged: usize,
+    pub unstaged: usize,
+    pub untracked: usize,
+    pub conflicted: usize,
+}
+
+impl WorkingTreeStatus {
+    pub fn from_entries(entries: &[StatusEntry]) -> Self {
+        let count = |f: fn(&StatusEntry) -> bool| entries.iter().filter(|e| f(e)).count();
+        WorkingTreeStatus {
+            staged: count(StatusEntry::is_staged),
+            unstaged: count(StatusEntry::is_unstaged),
+            untracked: count(StatusEntry::is_untracked),
+            conflicted: count(|e| e.is_conflicted),
+        }
+    }
+
+    pub fn is_clean(&self) -> bool { *self == WorkingTreeStatus::default() }
+
+    // 例: "ステージ済み 3, 変更 2, 未追跡 1" (0 件の項目は省く)
+    pub fn summary(&self) -> String {
+        [(self.conflicted, "コンフリクト"), (self.staged, "ステージ済み"), (self.unstaged, "変更"), (self.untracked, "未追跡")]
+            .iter()
+            .filter(|(n, _)| *n > 0)
+            .map(|(n, label)| format!("{} {}", label, n))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+pub fn parse_working_tree_status(porcelain_v2: &str) -> WorkingTreeStatus {
+    WorkingTreeStatus::from_entries(&parse_status(porcelain_v2))
+}
+
+// `git status --porcelain=v2 --branch` のヘッダー行 (# branch.*) の解析
+#[derive(Debug, Default)]
+pub struct BranchHeader {
+    pub head: String,          // ブランチ名 (detached の場合は "(detached)")
+    pub oid: String,           // HEAD のコミットID (コミットが無い場合は "(initial)")
+    pub upstream: Option<String>,
+    pub ahead: usize,
+    pub behind: usize,
+}
+
+pub fn parse_branch_header(porcelain_v2: &str) -> BranchHeader {
+    let mut header = BranchHeader::default();
+    for line in porcelain_v2.lines() {
+        let Some(rest) = line.strip_prefix("# branch.") else { continue };
+        let (key, value) = rest.split_once(' ').unwrap_or((rest, ""));
+        match key {
+            "head" => header.head = value.to_string(),
+            "oid" => header.oid = value.to_string(),
+            "upstream" => header.upstream = Some(value.to_string()),
+            "ab" => {
+                // "+<ahead> -<behind>" の形式
+                let mut counts = value.split(' ');
+                header.ahead = counts.next().and_then(|a| a.trim_start_matches('+').parse().ok()).unwrap_or(0);
+                header.behind = counts.next().and_then(|b| b.trim_start_matches('-').parse().ok()).unwrap_or(0);
+            }
+            _ => {}
+        }
+    }
+    header
+}
+
+// --- リモート URL の解析 ---
+#[derive(Debug, PartialEq)]
+pub enum RemoteProtocol { Ssh, Https, Other }
+
+#[derive(Debug, PartialEq)]
+pub struct RemoteUrl {
+    pub protocol: RemoteProtocol,
+    pub user: Option<String>,
+    pub host: String,
+    pub port: Option<String>,
+}
+
+// "git@github.com:user/repo.git" / "ssh://git@host:22/path" / "https://host/path" に対応
+pub fn parse_remote_url(url: &str) -> Option<RemoteUrl> {
+    let (protocol, rest) = if let Some(rest) = url.strip_prefix("ssh://") {
+        (RemoteProtocol::Ssh, rest)
+    } else if let Some(rest) = url.strip_prefix("https://").or_else(|| url.strip_prefix("http://")) {
+        (RemoteProtocol::Https, rest)
+    } else if url.contains("://") {
+        (RemoteProtocol::Other, url.split_once("://")?.1)
+    } else if url.contains(':') && !url.starts_with('/') {
+        // scp 形式 (user@host:path)
+        let (user_host, _) = url.split_once(':')?;
+        let (user, host) = match user_host.split_once('@') {
+            Some((user, host)) => (Some(user.to_string()), host.to_string()),
+            None => (None, user_host.to_string()),
+        };
+        return Some(RemoteUrl { protocol: RemoteProtocol::Ssh, user, host, port: None });
+    } else {
+        return None; // ローカルパス
+    };
+    let authority = rest.split('/').next()?;
+    let (user, host_port) = match authority.rsplit_once('@') {
+        Some((user, host_port)) => (Some(user.to_string()), host_port),
+        None => (None, authority),
+    };
+    let (host, port) = match host_port.split_once(':') {
+        Some((host, port)) => (host.to_string(), Some(port.to_string())),
+        None => (host_port.to_string(), None),
+    };
+    Some(RemoteUrl { protocol, user, host, port })
+}
+
+// `ssh -T` で接続を確認する。GitHub 等は認証に成功しても終了コード 1 を返すので出力も見る
+pub fn test_ssh_connection(remote: &RemoteUrl) -> CommandResult<(bool, String)> {
+    let destination = format!("{}@{}", remote.user.as_deref().unwrap_or("git"), remote.host);
+    let mut command = Command::new("ssh");
+    command.args(["-T", "-o", "BatchMode=yes", "-o", "ConnectTimeout=10"]);
+    if let Some(port) = &remote.port { command.args(["-p", port]); }
+    match command.arg(&destination).stdin(Stdio::null()).output() {
+        Ok(output) => {
+            let message = format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr)).trim().to_string();
+            let lower = message.to_lowercase();
+            let authenticated = output.status.success()
+                || lower.contains("successfully authenticated")
+                || lower.contains("welcome to")
+                || lower.contains("logged in as");
+            Ok((authenticated, message))
+        }
+        Err(e) => Err(format!("エラー: ssh の実行に失敗しました。詳細: {}", e)),
+    }
+}
+
+// --- Conventional Commits (例: "feat(api)!: 説明") の解析 ---
+pub struct ConventionalCommit<'a> {
+    pub kind: &'a str,
+    pub scope: Option<&'a str>,
+    pub breaking: bool,
+    pub description: &'a str,
+}
+
+pub fn parse_conventional_commit(subject: &str) -> Option<ConventionalCommit<'_>> {
+    let (header, description) = subject.split_once(": ")?;
+    let (header, breaking) = match header.strip_suffix('!') {
+        Some(h) => (h, true),
+        None => (header, false),
+    };
+    let (kind, scope) = match header.split_once('(') {
+        Some((kind, rest)) => (kind, Some(rest.strip_suffix(')')?)),
+        None => (header, None),
+    };
+    if kind.is_empty() || !kind.chars().all(|c| c.is_ascii_lowercase()) { return None; }
+    Some(ConventionalCommit { kind, scope, breaking, description: description.trim() })
+}
+
+// --- コミットメッセージ件名のチェック ---
+#[derive(Debug, PartialEq)]
+pub struct SubjectProblem {
+    pub message: String,
+    pub is_error: bool, // commit_subject_strict の場合はコミットを中止する
+}
+
+// 設定 (commit_subject_*) に違反した内容を返す。空なら問題なし
+pub fn lint_commit_subject(subject: &str, rules: &Config) -> Vec<SubjectProblem> {
+    let mut messages = Vec::new();
+    if let Some(max) = rules.commit_subject_max_length {
+        let length = subject.chars().count();
+        if length > max { messages.push(format!("件名が {} 文字あります (上限 {} 文字)。", length, max)); }
+    }
+    if rules.commit_subject_no_period && subject.ends_with('.') {
+        messages.push("件名の末尾にピリオドがあります。".to_string());
+    }
+    // 大文字・小文字の区別がある文字 (英字など) を含む場合のみ判定する
+    let has_lower = subject.chars().any(|c| c.is_lowercase());
+    let has_upper = subject.chars().any(|c| c.is_uppercase());
+    if rules.commit_subject_no_lowercase && has_lower && !has_upper {
+        messages.push("件名がすべて小文字です。".to_string());
+    }
+    if rules.commit_subject_no_uppercase && has_upper && !has_lower {
+        messages.push("件名がすべて大文字です。".to_string());
+    }
+    messages.into_iter().map(|message| SubjectProblem { message, is_error: rules.commit_subject_strict }).collect()
+}
+
+// --- テスト用の GitCommandTrait 実装 ---
+// git を実行せず、フィールドに設定した内容を返す。ビルダーで状況を組み立てる:
+// MockGitCommand::new().commit("origin/main", "b2").count("b2..a1", 0).status(" M src/main.rs")
+#[cfg(test)]
+#[derive(Default)]
+pub struct MockGitCommand {
+    pub branches: Vec<String>,
+    pub commit_ids: std::collections::HashMap<String, String>, // ref → コミットID
+    pub counts: std::collections::HashMap<String, usize>,      // "<a>..<b>" → rev-list --count
+    pub status: String,                                        // status --porcelain の出力
+    pub status_v2: String,                                     // status --porcelain=v2 の出力
+    pub upstreams: std::collections::HashMap<String, String>,  // ブランチ → 上流
+    pub ancestors: std::collections::HashSet<(String, String)>, // (祖先, 子孫) の組
+    pub stashes: String,                                        // stash list の出力 ("stash@{n}\t<メッセージ>")
+    pub calls: std::cell::RefCell<Vec<String>>,                 // 実行された変更を伴う操作 (例: "branch -d feature")
+}
+
+#[cfg(test)]
+impl MockGitCommand {
+    pub fn new() -> Self { Self::default() }
+
+    // ローカルブランチ (refs/heads/<name> と <name> の両方で引ける)
+    pub fn branch(mut self, name: &str, commit_id: &str) -> Self {
+        self.branches.push(name.to_string());
+        self.commit_ids.insert(format!("refs/heads/{}", name), commit_id.to_string());
+        self.commit(name, commit_id)
+    }
+
+    pub fn commit(mut self, ref_name: &str, commit_id: &str) -> Self {
+        self.commit_ids.insert(ref_name.to_string(), commit_id.to_string());
+        self
+    }
+
+    pub fn count(mut self, range: &str, count: usize) -> Self {
+        self.counts.insert(range.to_string(), count);
+        self
+    }
+
+    pub fn status(mut self, porcelain: &str) -> Self {
+        self.status = porcelain.to_string();
+        self
+    }
+
+    pub fn status_v2(mut self, porcelain_v2: &str) -> Self {
+        self.status_v2 = porcelain_v2.to_string();
+        self
+    }
+
+    pub fn upstream(mut self, branch: &str, upstream: &str) -> Self {
+        self.upstreams.insert(branch.to_string(), upstream.to_string());
+        self
+    }
+
+    // ancestor が descendant に含まれる (merge-base --is-ancestor が成功する)
+    pub fn ancestor(mut self, ancestor: &str, descendant: &str) -> Self {
+        self.ancestors.insert((ancestor.to_string(), descendant.to_string()));
+        self
+    }
+
+    // 後から追加したものほど古いスタッシュになる (stash list と同じ新しい順)
+    pub fn stash(mut self, stash_ref: &str, message: &str) -> Self {
+        if !self.stashes.is_empty() { self.stashes.push('\n'); }
+        self.stashes.push_str(&format!("{}\t{}", stash_ref, message));
+        self
+    }
+}
+
+#[cfg(test)]
+impl crate::GitCommandTrait for MockGitCommand {
+    // "<ref>^{commit}" は <ref> と同じコミットとして扱う
+    fn rev_parse_verify(&self, ref_name: &str) -> CommandResult<bool> {
+        Ok(self.commit_ids.contains_key(ref_name.strip_suffix("^{commit}").unwrap_or(ref_name)))
+    }
+    fn rev_parse_commit_id(&self, ref_name: &str) -> CommandResult<String> {
+        let ref_name = ref_name.strip_suffix("^{commit}").unwrap_or(ref_name);
+        self.commit_ids.get(ref_name).cloned().ok_or_else(|| format!("unknown revision: {}", ref_name))
+    }
+    fn rev_list_count(&self, range: &str) -> CommandResult<usize> {
+        self.counts.get(range).copied().ok_or_else(|| format!("unknown range: {}", range))
+    }
+    fn status_porcelain_v1(&self) -> CommandResult<String> { Ok(self.status.clone()) }
+    fn status_porcelain_v2(&self) -> CommandResult<String> { Ok(self.status_v2.clone()) }
+    fn upstream_of(&self, branch: &str) -> CommandResult<String> {
+        self.upstreams.get(branch).cloned().ok_or_else(|| format!("no upstream configured for branch '{}'", branch))
+    }
+    fn is_ancestor(&self, ancestor: &str, descendant: &str) -> CommandResult<bool> {
+        Ok(self.ancestors.contains(&(ancestor.to_string(), descendant.to_string())))
+    }
+    fn push_delete(&self, remote: &str, branch: &str) -> CommandResult<()> {
+        self.calls.borrow_mut().push(format!("push {} --delete {}", remote, branch));
+        Ok(())
+    }
+    fn branch_delete_local_d(&self, branch: &str) -> CommandResult<()> {
+        self.calls.borrow_mut().push(format!("branch -d {}", branch));
+        Ok(())
+    }
+    fn branch_delete_local_force(&self, branch: &str) -> CommandResult<()> {
+        self.calls.borrow_mut().push(format!("branch -D {}", branch));
+        Ok(())
+    }
+    fn reset_head(&self, mode: &str, commit: &str) -> CommandResult<()> {
+        self.calls.borrow_mut().push(format!("reset --{} {}", mode, commit));
+        Ok(())
+    }
+    fn stash_list(&self) -> CommandResult<String> { Ok(self.stashes.clone()) }
+    fn stash_push(&self, message: Option<&str>) -> CommandResult<()> {
+        self.calls.borrow_mut().push(match message {
+            Some(msg) => format!("stash push -m {}", msg),
+            None => "stash push".to_string(),
+        });
+        Ok(())
+    }
+    fn stash_pop(&self, stash: &str) -> CommandResult<bool> {
+        self.calls.borrow_mut().push(format!("stash pop {}", stash));
+        Ok(true)
+    }
+    fn add(&self, files: &str) -> CommandResult<()> {
+        self.calls.borrow_mut().push(format!("add {}", files));
+        Ok(())
+    }
+    fn commit(&self, message: &str) -> CommandResult<()> {
+        self.calls.borrow_mut().push(format!("commit -m {}", message));
+        Ok(())
+    }
+    fn reset_hard_head(&self) -> CommandResult<()> {
+        self.calls.borrow_mut().push("reset --hard HEAD".to_string());
+        Ok(())
+    }
+    fn restore_head_in(&self, path: &str) -> CommandResult<()> {
+        self.calls.borrow_mut().push(format!("restore --source=HEAD --staged --worktree -- {}", path));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(subject: &str, rules: &Config) -> Vec<String> {
+        lint_commit_subject(subject, rules).into_iter().map(|p| p.message).collect()
+    }
+
+    #[test]
+    fn subject_length_limit() {
+        let rules = Config { commit_subject_max_length: Some(5), ..Config::default() };
+        assert!(messages("abcde", &rules).is_empty());
+        assert_eq!(messages("abcdef", &rules), vec!["件名が 6 文字あります (上限 5 文字)。"]);
+        // 文字数で数える (バイト数ではない)
+        assert!(messages("日本語の件名", &Config { commit_subject_max_length: Some(6), ..Config::default() }).is_empty());
+        // 未設定なら長さは問わない
+        assert!(messages(&"a".repeat(200), &Config::default()).is_empty());
+    }
+
+    #[test]
+    fn subject_trailing_period() {
+        let rules = Config { commit_subject_no_period: true, ..Config::default() };
+        assert_eq!(messages("Fix login.", &rules), vec!["件名の末尾にピリオドがあります。"]);
+        assert!(messages("Fix login", &rules).is_empty());
+        assert!(messages("Fix login.", &Config::default()).is_empty());
+    }
+
+    #[test]
+    fn subject_case_rules() {
+        let rules = Config { commit_subject_no_lowercase: true, commit_subject_no_uppercase: true, ..Config::default() };
+        assert_eq!(messages("fix login", &rules), vec!["件名がすべて小文字です。"]);
+        assert_eq!(messages("FIX LOGIN", &rules), vec!["件名がすべて大文字です。"]);
+        assert!(messages("Fix login", &rules).is_empty());
+        // 大文字・小文字の無い文字だけなら判定しない
+        assert!(messages("ログインを修正", &rules).is_empty());
+    }
+
+    #[test]
+    fn subject_strict_mode_makes_errors() {
+        let warn = Config { commit_subject_no_period: true, ..Config::default() };
+        assert!(lint_commit_subject("Fix.", &warn).iter().all(|p| !p.is_error));
+        let strict = Config { commit_subject_strict: true, ..warn };
+        let problems = lint_commit_subject("Fix.", &strict);
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].is_error);
+    }
+
+    #[test]
+    fn status_rename_entry_uses_new_path() {
+        let entries = parse_status("2 R. N... 100644 100644 100644 1111111 1111111 R100 src/new name.rs\tsrc/old.rs");
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].path, "src/new name.rs");
+        assert_eq!(WorkingTreeStatus::from_entries(&entries).summary(), "ステージ済み 1");
+    }
+
+    #[test]
+    fn status_conflicted_entry_is_not_staged() {
+        let status = parse_working_tree_status("u UU N... 100644 100644 100644 100644 1111111 2222222 3333333 src/lib.rs");
+        assert_eq!(status, WorkingTreeStatus { conflicted: 1, ..WorkingTreeStatus::default() });
+        assert_eq!(status.summary(), "コンフリクト 1");
+    }
+
+    #[test]
+    fn status_staged_and_unstaged_entry_counts_both() {
+        let status = parse_working_tree_status(concat!(
+            "1 MM N... 100644 100644 100644 1111111 2222222 src/main.rs\n",
+            "? notes.txt\n",
+        ));
+        assert_eq!(status, WorkingTreeStatus { staged: 1, unstaged: 1, untracked: 1, conflicted: 0 });
+        assert_eq!(status.summary(), "ステージ済み 1, 変更 1, 未追跡 1");
+    }
+
+
+    #[test]
+    fn visible_width_ignores_color_codes() {
+        assert_eq!(visible_width("\x1b[1;32mmain\x1b[0m"), 4);
+        assert_eq!(visible_width("feature/login"), 13);
+    }
+
+    #[test]
+    fn visible_width_counts_wide_and_combining_chars() {
+        // かな・漢字は 2 桁
+        assert_eq!(visible_width("機能/ログイン"), 13);
+        // 結合文字 (濁点, アクセント) は幅 0
+        assert_eq!(visible_width("か\u{3099}"), 2);
+        assert_eq!(visible_width("e\u{0301}"), 1);
+        // 絵文字は 2 桁
+        assert_eq!(visible_width("🚀"), 2);
+        assert_eq!(visible_width("fix-🐛"), 6);
+    }
+
+
+    #[test]
+    fn remote_url_forms() {
+        let url = |protocol, user: Option<&str>, host: &str, port: Option<&str>| Some(RemoteUrl {
+            protocol, user: user.map(str::to_string), host: host.to_string(), port: port.map(str::to_string),
+        });
+        let cases = [
+            ("git@github.com:o/r.git", url(RemoteProtocol::Ssh, Some("git"), "github.com", None)),
+            ("ssh://git@host:2222/o/r", url(RemoteProtocol::Ssh, Some("git"), "host", Some("2222"))),
+            ("https://user@host/o/r.git", url(RemoteProtocol::Https, Some("user"), "host", None)),
+            ("https://github.com/o/r", url(RemoteProtocol::Https, None, "github.com", None)),
+            // ローカルパスは対象外
+            ("/srv/git/r.git", None),
+            ("../r.git", None),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(parse_remote_url(input), expected, "{}", input);
+        }
+    }
+
+
+    #[test]
+    fn conventional_commit_scope_and_breaking() {
+        let commit = parse_conventional_commit("feat(api)!: drop v1 endpoints").unwrap();
+        assert_eq!((commit.kind, commit.scope, commit.breaking, commit.description), ("feat", Some("api"), true, "drop v1 endpoints"));
+        let commit = parse_conventional_commit("fix: ログインの不具合").unwrap();
+        assert_eq!((commit.kind, commit.scope, commit.breaking), ("fix", None, false));
+        assert!(parse_conventional_commit("Update README").is_none());
+        assert!(parse_conventional_commit("feat(api: missing paren").is_none());
+    }
+
+
+    #[test]
+    fn branch_header_without_upstream() {
+        // 上流が無いブランチには branch.upstream も branch.ab も出ない
+        let header = parse_branch_header("# branch.oid 1111111\n# branch.head feature\n1 .M N... 100644 100644 100644 1111111 1111111 a.rs");
+        assert_eq!((header.head.as_str(), header.upstream, header.ahead, header.behind), ("feature", None, 0, 0));
+        // 上流が削除済みの場合は branch.upstream だけが出る
+        let header = parse_branch_header("# branch.oid 1111111\n# branch.head feature\n# branch.upstream origin/feature");
+        assert_eq!((header.upstream.as_deref(), header.ahead, header.behind), (Some("origin/feature"), 0, 0));
+        // branch.ab だけがあっても件数は読める
+        let header = parse_branch_header("# branch.head main\n# branch.ab +2 -5");
+        assert_eq!((header.upstream, header.ahead, header.behind), (None, 2, 5));
+    }
+
+
+    #[test]
+    fn slugify_drops_non_ascii_and_punctuation() {
+        assert_eq!(slugify("Fix Login bug!"), "fix-login-bug");
+        assert_eq!(slugify("ログイン API の修正"), "api");
+        assert_eq!(slugify("ログイン画面の修正"), "");
+        assert_eq!(slugify("!!! ???"), "");
+    }
+
+}